@@ -1,10 +1,10 @@
 use std::{env, path::Path};
 
 use smithay_client_toolkit::{
-    compositor::{CompositorHandler, CompositorState},
+    compositor::{CompositorHandler, CompositorState, Rect},
     delegate_registry,
     output::{OutputHandler, OutputState},
-    registry::{ProvidesRegistryState, RegistryState, SimpleGlobal},
+    registry::{ProvidesRegistryState, RegistryState},
     registry_handlers,
     shell::{
         xdg::{
@@ -14,15 +14,12 @@ use smithay_client_toolkit::{
         WaylandSurface,
     },
     shm::{slot::SlotPool, Shm, ShmHandler},
+    viewporter::{Viewport, ViewporterState},
 };
 use wayland_client::{
     globals::registry_queue_init,
     protocol::{wl_output, wl_shm, wl_surface},
-    Connection, Dispatch, QueueHandle,
-};
-use wayland_protocols::wp::viewporter::client::{
-    wp_viewport::{self, WpViewport},
-    wp_viewporter::{self, WpViewporter},
+    Connection, QueueHandle,
 };
 
 fn main() {
@@ -44,10 +41,7 @@ fn main() {
     // we share with the compositor process.
     let shm = Shm::bind(&globals, &qh).expect("wl shm is not available.");
     // In this example, we use the viewporter to allow the compositor to scale and crop presented images.
-    //
-    // Since the wp_viewporter protocol has no events, we can use SimpleGlobal.
-    let wp_viewporter = SimpleGlobal::<wp_viewporter::WpViewporter, 1>::bind(&globals, &qh)
-        .expect("wp_viewporter not available");
+    let viewporter = ViewporterState::bind(&globals, &qh);
 
     let mut windows = Vec::new();
 
@@ -88,11 +82,10 @@ fn main() {
         window.commit();
 
         // For scaling, create a viewport for the window.
-        let viewport = wp_viewporter.get().expect("Requires wp_viewporter").get_viewport(
-            window.wl_surface(),
-            &qh,
-            (),
-        );
+        //
+        // The viewport is destroyed when it is dropped along with the rest of the viewer.
+        let viewport =
+            viewporter.get_viewport(window.wl_surface(), &qh).expect("Requires wp_viewporter");
 
         windows.push(ImageViewer {
             width: image.width(),
@@ -116,7 +109,6 @@ fn main() {
         registry_state: RegistryState::new(&globals),
         output_state: OutputState::new(&globals, &qh),
         shm,
-        wp_viewporter,
         pool,
         windows,
     };
@@ -137,7 +129,6 @@ struct State {
     registry_state: RegistryState,
     output_state: OutputState,
     shm: Shm,
-    wp_viewporter: SimpleGlobal<WpViewporter, 1>,
 
     pool: SlotPool,
     windows: Vec<ImageViewer>,
@@ -146,7 +137,7 @@ struct State {
 struct ImageViewer {
     window: Window,
     image: image::RgbaImage,
-    viewport: WpViewport,
+    viewport: Viewport,
     width: u32,
     height: u32,
     first_configure: bool,
@@ -255,7 +246,10 @@ impl WindowHandler for State {
             if let (Some(width), Some(height)) = configure.new_size {
                 viewer.width = width.get();
                 viewer.height = height.get();
-                viewer.viewport.set_destination(width.get() as _, height.get() as _);
+                viewer
+                    .viewport
+                    .set_destination(width, height)
+                    .expect("configured size is a valid destination");
                 if !viewer.first_configure {
                     viewer.window.commit();
                 }
@@ -308,7 +302,10 @@ impl State {
 
             // Set the entire buffer as the source area for the viewport.
             // Destination was set during configure.
-            viewer.viewport.set_source(0.0, 0.0, viewer.width as f64, viewer.height as f64);
+            viewer
+                .viewport
+                .set_source(Some(Rect::new(0.0, 0.0, viewer.width as f64, viewer.height as f64)))
+                .expect("buffer size is a valid source");
 
             // Attach and commit to present.
             buffer.attach_to(window.wl_surface()).expect("buffer attach");
@@ -317,8 +314,6 @@ impl State {
     }
 }
 
-wayland_client::delegate_noop!(State: WpViewporter);
-
 delegate_registry!(State);
 
 impl ProvidesRegistryState for State {
@@ -329,29 +324,4 @@ impl ProvidesRegistryState for State {
     registry_handlers!(OutputState);
 }
 
-impl AsMut<SimpleGlobal<WpViewporter, 1>> for State {
-    fn as_mut(&mut self) -> &mut SimpleGlobal<WpViewporter, 1> {
-        &mut self.wp_viewporter
-    }
-}
-
-impl Dispatch<WpViewport, ()> for State {
-    fn event(
-        _: &mut State,
-        _: &WpViewport,
-        _: wp_viewport::Event,
-        _: &(),
-        _: &Connection,
-        _: &QueueHandle<State>,
-    ) {
-        unreachable!("wp_viewport::Event is empty in version 1")
-    }
-}
-
-impl Drop for ImageViewer {
-    fn drop(&mut self) {
-        self.viewport.destroy()
-    }
-}
-
 smithay_client_toolkit::delegate_dispatch2!(State);
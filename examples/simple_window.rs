@@ -5,7 +5,7 @@ use smithay_client_toolkit::reexports::calloop::{EventLoop, LoopHandle};
 use smithay_client_toolkit::reexports::calloop_wayland_source::WaylandSource;
 use smithay_client_toolkit::{
     activation::{ActivationHandler, ActivationState},
-    compositor::{CompositorHandler, CompositorState, FrameCallbackData},
+    compositor::{CompositorHandler, CompositorState, FrameInfo, FrameScheduler, RedrawHandler},
     delegate_registry,
     output::{OutputHandler, OutputState},
     registry::{ProvidesRegistryState, RegistryState},
@@ -80,12 +80,9 @@ fn main() {
     if let Some(activation) = xdg_activation.as_ref() {
         activation.request_token(
             &qh,
-            RequestData {
-                seat_and_serial: None,
-                surface: Some(window.wl_surface().clone()),
-                app_id: Some(String::from("io.github.smithay.client-toolkit.SimpleWindow")),
-                udata: (),
-            },
+            RequestData::new(())
+                .with_surface(window.wl_surface())
+                .with_app_id("io.github.smithay.client-toolkit.SimpleWindow"),
         )
     }
 
@@ -109,6 +106,7 @@ fn main() {
         height: 256,
         shift: None,
         buffer: None,
+        scheduler: FrameScheduler::new(window.wl_surface().clone()),
         window,
         keyboard: None,
         keyboard_focus: false,
@@ -142,6 +140,7 @@ struct SimpleWindow {
     shift: Option<u32>,
     buffer: Option<Buffer>,
     window: Window,
+    scheduler: FrameScheduler,
     keyboard: Option<wl_keyboard::WlKeyboard>,
     keyboard_focus: bool,
     pointer: Option<wl_pointer::WlPointer>,
@@ -171,12 +170,12 @@ impl CompositorHandler for SimpleWindow {
 
     fn frame(
         &mut self,
-        conn: &Connection,
-        qh: &QueueHandle<Self>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
         _surface: &wl_surface::WlSurface,
         _time: u32,
     ) {
-        self.draw(conn, qh);
+        // Not needed for this example, redraws are scheduled with a `FrameScheduler`.
     }
 
     fn surface_enter(
@@ -257,6 +256,18 @@ impl WindowHandler for SimpleWindow {
     }
 }
 
+impl RedrawHandler for SimpleWindow {
+    fn redraw(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _frame: FrameInfo,
+    ) {
+        self.draw(conn, qh);
+    }
+}
+
 impl ActivationHandler for SimpleWindow {
     type RequestUdata = ();
 
@@ -507,8 +518,8 @@ impl SimpleWindow {
         // Damage the entire window
         self.window.wl_surface().damage_buffer(0, 0, self.width as i32, self.height as i32);
 
-        // Request our next frame
-        self.window.wl_surface().frame(qh, FrameCallbackData(self.window.wl_surface().clone()));
+        // Request our next frame, the callback is requested for the commit below.
+        self.scheduler.request_redraw(qh);
 
         // Attach and commit to present.
         buffer.attach_to(self.window.wl_surface()).expect("buffer attach");
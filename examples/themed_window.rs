@@ -1,5 +1,4 @@
 use std::sync::Arc;
-use std::time::Duration;
 use std::{convert::TryInto, num::NonZeroU32};
 
 use smithay_client_toolkit::reexports::client::{
@@ -7,10 +6,6 @@ use smithay_client_toolkit::reexports::client::{
     protocol::{wl_keyboard, wl_output, wl_pointer, wl_seat, wl_shm, wl_surface},
     Connection, Proxy, QueueHandle,
 };
-use smithay_client_toolkit::reexports::csd_frame::{
-    DecorationsFrame, FrameAction, FrameClick, ResizeEdge,
-};
-use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_toplevel::ResizeEdge as XdgResizeEdge;
 use smithay_client_toolkit::{
     compositor::{CompositorHandler, CompositorState, FrameCallbackData},
     delegate_registry,
@@ -27,9 +22,9 @@ use smithay_client_toolkit::{
     },
     shell::{
         xdg::{
-            fallback_frame::FallbackFrame,
-            window::{DecorationMode, Window, WindowConfigure, WindowDecorations, WindowHandler},
-            XdgShell, XdgSurface,
+            fallback_frame::DefaultFrameRenderer,
+            window::{Window, WindowConfigure, WindowDecorations, WindowHandler},
+            XdgShell,
         },
         WaylandSurface,
     },
@@ -93,6 +88,7 @@ fn main() {
     let subcompositor_state =
         SubcompositorState::bind(compositor_state.wl_compositor().clone(), &globals, &qh)
             .expect("wl_subcompositor not available");
+    let subcompositor_state = Arc::new(subcompositor_state);
     let shm_state = Shm::bind(&globals, &qh).expect("wl_shm not available");
     let xdg_shell_state = XdgShell::bind(&globals, &qh).expect("xdg shell not available");
 
@@ -110,6 +106,17 @@ fn main() {
     window.set_app_id("io.github.smithay.client-toolkit.SimpleWindow");
     window.set_min_size(Some((width.get(), height.get())));
 
+    // Let the window create and drive the client side decorations when they are needed.
+    let mut frame_renderer = DefaultFrameRenderer::new();
+    frame_renderer.set_shadow_size(12);
+    frame_renderer.set_corner_radius(8);
+    window.manage_decorations_with_renderer(
+        &shm_state,
+        subcompositor_state.clone(),
+        &qh,
+        frame_renderer,
+    );
+
     // In order for the window to be mapped, we need to perform an initial commit with no attached buffer.
     // For more info, see WaylandSurface::commit
     //
@@ -124,7 +131,7 @@ fn main() {
         seat_state,
         output_state,
         compositor_state,
-        subcompositor_state: Arc::new(subcompositor_state),
+        _subcompositor_state: subcompositor_state,
         shm_state,
         _xdg_shell_state: xdg_shell_state,
 
@@ -136,7 +143,6 @@ fn main() {
         shift: None,
         buffer: None,
         window,
-        window_frame: None,
         keyboard: None,
         keyboard_focus: false,
         themed_pointer: None,
@@ -161,7 +167,7 @@ struct SimpleWindow {
     seat_state: SeatState,
     output_state: OutputState,
     compositor_state: CompositorState,
-    subcompositor_state: Arc<SubcompositorState>,
+    _subcompositor_state: Arc<SubcompositorState>,
     shm_state: Shm,
     _xdg_shell_state: XdgShell,
 
@@ -173,7 +179,6 @@ struct SimpleWindow {
     shift: Option<u32>,
     buffer: Option<Buffer>,
     window: Window,
-    window_frame: Option<FallbackFrame<Self>>,
     keyboard: Option<wl_keyboard::WlKeyboard>,
     keyboard_focus: bool,
     themed_pointer: Option<ThemedPointer>,
@@ -284,80 +289,39 @@ impl WindowHandler for SimpleWindow {
             configure.new_size, configure.decoration_mode
         );
 
-        let (width, height) = if configure.decoration_mode == DecorationMode::Client {
-            let window_frame = self.window_frame.get_or_insert_with(|| {
-                FallbackFrame::new(
-                    &self.window,
-                    &self.shm_state,
-                    self.subcompositor_state.clone(),
-                    qh.clone(),
-                )
-                .expect("failed to create client side decorations frame.")
-            });
-
-            // Un-hide the frame.
-            window_frame.set_hidden(false);
+        // The configured size already excludes the decorations.
+        let width = configure.new_size.0.unwrap_or(self.width);
+        let height = configure.new_size.1.unwrap_or(self.height);
 
-            // Configure state before touching any resizing.
-            window_frame.update_state(configure.state);
-
-            // Update the capabilities.
-            window_frame.update_wm_capabilities(configure.capabilities);
-
-            let (width, height) = match configure.new_size {
-                (Some(width), Some(height)) => {
-                    // The size could be 0.
-                    window_frame.subtract_borders(width, height)
-                }
-                _ => {
-                    // You might want to consider checking for configure bounds.
-                    (Some(self.width), Some(self.height))
-                }
-            };
-
-            // Clamp the size to at least one pixel.
-            let width = width.unwrap_or(NonZeroU32::new(1).unwrap());
-            let height = height.unwrap_or(NonZeroU32::new(1).unwrap());
-
-            println!("New dimentions: {width}, {height}");
-            window_frame.resize(width, height);
-
-            let (x, y) = window_frame.location();
-            let outer_size = window_frame.add_borders(width.get(), height.get());
-            window.xdg_surface().set_window_geometry(
-                x,
-                y,
-                outer_size.0 as i32,
-                outer_size.1 as i32,
-            );
-
-            (width, height)
-        } else {
-            // Hide the frame, if any.
-            if let Some(frame) = self.window_frame.as_mut() {
-                frame.set_hidden(true)
-            }
-            let width = configure.new_size.0.unwrap_or(self.width);
-            let height = configure.new_size.1.unwrap_or(self.height);
-            self.window.xdg_surface().set_window_geometry(
-                0,
-                0,
-                width.get() as i32,
-                height.get() as i32,
-            );
-            (width, height)
-        };
+        println!("New dimentions: {width}, {height}");
 
         // Update new width and height;
         self.width = width;
         self.height = height;
 
+        // Without a configured size, the decorations wait for the size the window picked.
+        if configure.new_size.0.is_none() || configure.new_size.1.is_none() {
+            window.resize_decorations(width, height);
+        }
+
         // Initiate the first draw.
         if self.first_configure {
             self.first_configure = false;
             self.draw(conn, qh);
         }
     }
+
+    fn decorations_cursor(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &Window,
+        _: &wl_pointer::WlPointer,
+        cursor: CursorIcon,
+    ) {
+        self.set_cursor = true;
+        self.decorations_cursor = Some(cursor);
+    }
 }
 
 impl SeatHandler for SimpleWindow {
@@ -501,96 +465,38 @@ impl KeyboardHandler for SimpleWindow {
 impl PointerHandler for SimpleWindow {
     fn pointer_frame(
         &mut self,
-        _conn: &Connection,
-        _qh: &QueueHandle<Self>,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
         pointer: &wl_pointer::WlPointer,
         events: &[PointerEvent],
     ) {
         use PointerEventKind::*;
+        let seat = pointer.data::<PointerData<()>>().unwrap().seat().clone();
+        let window = self.window.clone();
         for event in events {
-            let (x, y) = event.position;
+            // Let the window handle the events over its decorations.
+            if window.handle_decorations_pointer_event(self, conn, qh, pointer, &seat, event) {
+                continue;
+            }
+
+            if &event.surface != self.window.wl_surface() {
+                continue;
+            }
+
             match event.kind {
                 Enter { .. } => {
                     self.set_cursor = true;
-                    self.decorations_cursor = self.window_frame.as_mut().and_then(|frame| {
-                        frame.click_point_moved(Duration::ZERO, &event.surface.id(), x, y)
-                    });
-                }
-                Leave { .. } => {
-                    if &event.surface != self.window.wl_surface() {
-                        if let Some(window_frame) = self.window_frame.as_mut() {
-                            window_frame.click_point_left();
-                        }
-                    }
-                }
-                Motion { time } => {
-                    if let Some(new_cursor) = self.window_frame.as_mut().and_then(|frame| {
-                        frame.click_point_moved(
-                            Duration::from_millis(time as u64),
-                            &event.surface.id(),
-                            x,
-                            y,
-                        )
-                    }) {
-                        self.set_cursor = true;
-                        self.decorations_cursor = Some(new_cursor);
-                    }
+                    self.decorations_cursor = None;
                 }
-                Press { button, serial, time } | Release { button, serial, time } => {
-                    let pressed = matches!(event.kind, Press { .. });
-                    if &event.surface != self.window.wl_surface() {
-                        let click = match button {
-                            0x110 => FrameClick::Normal,
-                            0x111 => FrameClick::Alternate,
-                            _ => continue,
-                        };
-
-                        if let Some(action) = self.window_frame.as_mut().and_then(|frame| {
-                            frame.on_click(Duration::from_millis(time as u64), click, pressed)
-                        }) {
-                            self.frame_action(pointer, serial, action);
-                        }
-                    } else if pressed {
-                        self.shift = self.shift.xor(Some(0));
-                    }
+                Press { .. } => {
+                    self.shift = self.shift.xor(Some(0));
                 }
-                Axis { .. } => {}
+                _ => {}
             }
         }
     }
 }
 
-impl SimpleWindow {
-    fn frame_action(&mut self, pointer: &wl_pointer::WlPointer, serial: u32, action: FrameAction) {
-        let pointer_data = pointer.data::<PointerData<()>>().unwrap();
-        let seat = pointer_data.seat();
-        match action {
-            FrameAction::Close => self.exit = true,
-            FrameAction::Minimize => self.window.set_minimized(),
-            FrameAction::Maximize => self.window.set_maximized(),
-            FrameAction::UnMaximize => self.window.unset_maximized(),
-            FrameAction::ShowMenu(x, y) => self.window.show_window_menu(seat, serial, (x, y)),
-            FrameAction::Resize(edge) => {
-                let edge = match edge {
-                    ResizeEdge::None => XdgResizeEdge::None,
-                    ResizeEdge::Top => XdgResizeEdge::Top,
-                    ResizeEdge::Bottom => XdgResizeEdge::Bottom,
-                    ResizeEdge::Left => XdgResizeEdge::Left,
-                    ResizeEdge::TopLeft => XdgResizeEdge::TopLeft,
-                    ResizeEdge::BottomLeft => XdgResizeEdge::BottomLeft,
-                    ResizeEdge::Right => XdgResizeEdge::Right,
-                    ResizeEdge::TopRight => XdgResizeEdge::TopRight,
-                    ResizeEdge::BottomRight => XdgResizeEdge::BottomRight,
-                    _ => return,
-                };
-                self.window.resize(seat, serial, edge);
-            }
-            FrameAction::Move => self.window.move_(seat, serial),
-            _ => (),
-        }
-    }
-}
-
 impl ShmHandler for SimpleWindow {
     fn shm_state(&mut self) -> &mut Shm {
         &mut self.shm_state
@@ -653,13 +559,6 @@ impl SimpleWindow {
             }
         }
 
-        // Draw the decorations frame.
-        if let Some(frame) = self.window_frame.as_mut() {
-            if frame.is_dirty() && !frame.is_hidden() {
-                frame.draw();
-            }
-        }
-
         // Damage the entire window
         self.window.wl_surface().damage_buffer(0, 0, width as i32, height as i32);
 
@@ -1,3 +1,5 @@
+use std::{env, process::Command};
+
 use wayland_client::{
     globals::{BindError, GlobalList},
     protocol::{wl_seat, wl_surface},
@@ -5,12 +7,18 @@ use wayland_client::{
 };
 use wayland_protocols::xdg::activation::v1::client::{xdg_activation_token_v1, xdg_activation_v1};
 
+#[cfg(feature = "xkbcommon")]
+use crate::seat::keyboard::KeyboardData;
 use crate::{
     dispatch2::Dispatch2,
     error::GlobalError,
     globals::{GlobalData, ProvidesBoundGlobal},
+    seat::pointer::PointerData,
 };
 
+/// The environment variable used to pass an activation token to a launched application.
+pub const ACTIVATION_TOKEN_ENV: &str = "XDG_ACTIVATION_TOKEN";
+
 /// Minimal implementation of [`RequestDataExt`].
 ///
 /// Use a custom type implementing [`RequestDataExt`] to store more data with a token request
@@ -33,6 +41,53 @@ pub struct RequestData<U> {
     pub udata: U,
 }
 
+impl<U> RequestData<U> {
+    /// Create request data with only `udata` set.
+    pub fn new(udata: U) -> Self {
+        Self { app_id: None, seat_and_serial: None, surface: None, udata }
+    }
+
+    /// Set the app_id of the application requesting the token.
+    pub fn with_app_id(mut self, app_id: impl Into<String>) -> Self {
+        self.app_id = Some(app_id.into());
+        self
+    }
+
+    /// Set the surface of the window requesting the token.
+    pub fn with_surface(mut self, surface: &wl_surface::WlSurface) -> Self {
+        self.surface = Some(surface.clone());
+        self
+    }
+
+    /// Set the seat and serial of the input event the token is requested for.
+    pub fn with_seat_and_serial(mut self, seat: &wl_seat::WlSeat, serial: u32) -> Self {
+        self.seat_and_serial = Some((seat.clone(), serial));
+        self
+    }
+
+    /// Use the serial of the latest button event of a pointer.
+    ///
+    /// Nothing is set if no button was pressed yet.
+    pub fn with_pointer_serial<V>(self, pointer: &PointerData<V>) -> Self {
+        match pointer.latest_button_serial() {
+            Some(serial) => self.with_seat_and_serial(pointer.seat(), serial),
+            None => self,
+        }
+    }
+
+    /// Use the serial of the latest key event of a keyboard, or of its latest enter event if no
+    /// key was pressed yet.
+    ///
+    /// Nothing is set if the keyboard never had focus.
+    #[cfg(feature = "xkbcommon")]
+    pub fn with_keyboard_serial<T, V>(self, keyboard: &KeyboardData<T, V>) -> Self {
+        match keyboard.latest_key_serial().or_else(|| keyboard.latest_enter_serial()) {
+            Some(serial) => self.with_seat_and_serial(keyboard.seat(), serial),
+            None => self,
+        }
+    }
+}
+
 /// Handler for xdg-activation
 pub trait ActivationHandler: Sized {
     /// Data type used for requesting activation tokens
@@ -89,6 +144,63 @@ impl ActivationState {
         }
         token.commit();
     }
+
+    /// Activate a surface with the token the application was launched with, if any.
+    ///
+    /// This should be called once the first window is mapped. The token is consumed, so only the
+    /// first call activates a surface. Returns whether a token was used.
+    pub fn activate_with_startup_token(
+        &self,
+        surface: &wl_surface::WlSurface,
+        startup_token: &mut StartupToken,
+    ) -> bool {
+        match startup_token.take() {
+            Some(token) => {
+                self.xdg_activation.activate(token, surface);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The activation token an application was launched with.
+///
+/// Launchers pass the token in the [`ACTIVATION_TOKEN_ENV`] environment variable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StartupToken {
+    token: Option<String>,
+}
+
+impl StartupToken {
+    /// Read the token from the environment, and remove it from the environment so it isn't
+    /// inherited by child processes.
+    ///
+    /// This should be called at startup, before other threads are spawned, since modifying the
+    /// environment is not thread-safe.
+    pub fn from_env() -> Self {
+        let token = env::var(ACTIVATION_TOKEN_ENV).ok().filter(|token| !token.is_empty());
+        env::remove_var(ACTIVATION_TOKEN_ENV);
+        Self { token }
+    }
+
+    /// The token, if it wasn't used yet.
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Take the token, leaving none.
+    pub fn take(&mut self) -> Option<String> {
+        self.token.take()
+    }
+}
+
+/// Pass an activation token to a process spawned with `command`.
+///
+/// The token is usually requested with [`ActivationState::request_token`] in response to the
+/// input event launching the process.
+pub fn set_child_token(command: &mut Command, token: &str) {
+    command.env(ACTIVATION_TOKEN_ENV, token);
 }
 
 impl<D> Dispatch2<xdg_activation_v1::XdgActivationV1, D> for GlobalData
@@ -0,0 +1,446 @@
+use std::sync::Mutex;
+
+use crate::reexports::client::{
+    globals::GlobalList,
+    protocol::{wl_buffer, wl_output},
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+use crate::reexports::protocols::ext::{
+    foreign_toplevel_list::v1::client::ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1,
+    image_capture_source::v1::client::{
+        ext_foreign_toplevel_image_capture_source_manager_v1::{
+            self, ExtForeignToplevelImageCaptureSourceManagerV1,
+        },
+        ext_image_capture_source_v1::{self, ExtImageCaptureSourceV1},
+        ext_output_image_capture_source_manager_v1::{self, ExtOutputImageCaptureSourceManagerV1},
+    },
+    image_copy_capture::v1::client::{
+        ext_image_copy_capture_frame_v1::{self, ExtImageCopyCaptureFrameV1, FailureReason},
+        ext_image_copy_capture_manager_v1::{self, ExtImageCopyCaptureManagerV1, Options},
+        ext_image_copy_capture_session_v1::{self, ExtImageCopyCaptureSessionV1},
+    },
+};
+
+use crate::{
+    compositor::Rect,
+    dispatch2::Dispatch2,
+    error::GlobalError,
+    globals::GlobalData,
+    registry::GlobalProxy,
+    shm::slot::{Buffer, SlotPool},
+};
+
+use super::{presentation_time, BufferConstraints, CaptureError, CaptureFailure, CaptureFrame};
+
+/// Handler trait for the events of a [`CaptureSession`].
+pub trait ImageCaptureHandler: Sized {
+    /// The buffers accepted by the session changed.
+    ///
+    /// Buffers allocated for the previous constraints should be reallocated.
+    fn constraints_changed(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        session: &ExtImageCopyCaptureSessionV1,
+        constraints: &BufferConstraints,
+    );
+
+    /// A frame was copied into the buffer passed to [`CaptureSession::capture`].
+    fn frame_ready(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        session: &ExtImageCopyCaptureSessionV1,
+        frame: CaptureFrame,
+    );
+
+    /// Capturing a frame failed, and the contents of the buffer are undefined.
+    fn frame_failed(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        session: &ExtImageCopyCaptureSessionV1,
+        reason: CaptureFailure,
+    );
+
+    /// The session was stopped, for example because its output or toplevel was removed.
+    ///
+    /// The [`CaptureSession`] should be dropped.
+    fn session_stopped(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        session: &ExtImageCopyCaptureSessionV1,
+    );
+}
+
+/// State for `ext_image_copy_capture_v1` and the capture sources of `ext_image_capture_source_v1`.
+#[derive(Debug)]
+pub struct ImageCaptureState {
+    manager: GlobalProxy<ExtImageCopyCaptureManagerV1>,
+    output_source_manager: GlobalProxy<ExtOutputImageCaptureSourceManagerV1>,
+    toplevel_source_manager: GlobalProxy<ExtForeignToplevelImageCaptureSourceManagerV1>,
+}
+
+impl ImageCaptureState {
+    pub fn bind<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<ExtImageCopyCaptureManagerV1, GlobalData>
+            + Dispatch<ExtOutputImageCaptureSourceManagerV1, GlobalData>
+            + Dispatch<ExtForeignToplevelImageCaptureSourceManagerV1, GlobalData>
+            + 'static,
+    {
+        Self {
+            manager: GlobalProxy::from(globals.bind(qh, 1..=1, GlobalData)),
+            output_source_manager: GlobalProxy::from(globals.bind(qh, 1..=1, GlobalData)),
+            toplevel_source_manager: GlobalProxy::from(globals.bind(qh, 1..=1, GlobalData)),
+        }
+    }
+
+    /// Create a source capturing an output.
+    pub fn create_output_source<D>(
+        &self,
+        output: &wl_output::WlOutput,
+        qh: &QueueHandle<D>,
+    ) -> Result<CaptureSource, GlobalError>
+    where
+        D: Dispatch<ExtImageCaptureSourceV1, GlobalData> + 'static,
+    {
+        let source = self.output_source_manager.get()?.create_source(output, qh, GlobalData);
+        Ok(CaptureSource { source })
+    }
+
+    /// Create a source capturing the toplevel of another client.
+    pub fn create_toplevel_source<D>(
+        &self,
+        toplevel: &ExtForeignToplevelHandleV1,
+        qh: &QueueHandle<D>,
+    ) -> Result<CaptureSource, GlobalError>
+    where
+        D: Dispatch<ExtImageCaptureSourceV1, GlobalData> + 'static,
+    {
+        let source = self.toplevel_source_manager.get()?.create_source(toplevel, qh, GlobalData);
+        Ok(CaptureSource { source })
+    }
+
+    /// Create a session capturing frames of `source`, with the cursor painted on them if
+    /// `paint_cursors` is set.
+    pub fn create_session<D>(
+        &self,
+        source: &CaptureSource,
+        paint_cursors: bool,
+        qh: &QueueHandle<D>,
+    ) -> Result<CaptureSession, GlobalError>
+    where
+        D: Dispatch<ExtImageCopyCaptureSessionV1, CaptureSessionData> + 'static,
+    {
+        let options = if paint_cursors { Options::PaintCursors } else { Options::empty() };
+        let session = self.manager.get()?.create_session(
+            &source.source,
+            options,
+            qh,
+            CaptureSessionData::default(),
+        );
+        Ok(CaptureSession { session })
+    }
+}
+
+/// An output or toplevel to capture.
+///
+/// The source may be dropped once sessions are created from it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CaptureSource {
+    source: ExtImageCaptureSourceV1,
+}
+
+impl CaptureSource {
+    /// The underlying wayland object.
+    pub fn inner(&self) -> &ExtImageCaptureSourceV1 {
+        &self.source
+    }
+}
+
+impl Drop for CaptureSource {
+    fn drop(&mut self) {
+        self.source.destroy();
+    }
+}
+
+/// A session capturing frames of a [`CaptureSource`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct CaptureSession {
+    session: ExtImageCopyCaptureSessionV1,
+}
+
+impl CaptureSession {
+    /// The buffers accepted by the session, once the compositor sent them.
+    pub fn constraints(&self) -> Option<BufferConstraints> {
+        self.data().inner.lock().unwrap().constraints.clone()
+    }
+
+    /// Capture the next frame into `buffer`.
+    ///
+    /// `damage` are the regions of the buffer which changed since it was last used for a frame
+    /// of this session, which lets the compositor only copy those. An empty list means the whole
+    /// buffer.
+    ///
+    /// A single frame may be captured at a time. The buffer must not be used until
+    /// [`ImageCaptureHandler::frame_ready`] or [`ImageCaptureHandler::frame_failed`] is called.
+    pub fn capture<D>(
+        &self,
+        qh: &QueueHandle<D>,
+        buffer: &wl_buffer::WlBuffer,
+        damage: &[Rect<i32>],
+    ) -> Result<(), CaptureError>
+    where
+        D: Dispatch<ExtImageCopyCaptureFrameV1, CaptureFrameData> + 'static,
+    {
+        let mut inner = self.data().inner.lock().unwrap();
+        if inner.stopped {
+            return Err(CaptureError::Stopped);
+        }
+        if inner.frame.is_some() {
+            return Err(CaptureError::FrameInProgress);
+        }
+
+        let data = CaptureFrameData { session: self.session.clone(), frame: Default::default() };
+        let frame = self.session.create_frame(qh, data);
+        frame.attach_buffer(buffer);
+        if damage.is_empty() {
+            frame.damage_buffer(0, 0, i32::MAX, i32::MAX);
+        }
+        for rect in damage {
+            frame.damage_buffer(rect.x, rect.y, rect.width, rect.height);
+        }
+        frame.capture();
+        inner.frame = Some(frame);
+
+        Ok(())
+    }
+
+    /// Allocate a shared memory buffer from `pool` and capture the next frame into it.
+    ///
+    /// The buffer must be kept until the frame is ready, and can then be read through
+    /// [`Buffer::canvas`].
+    pub fn capture_shm<D>(
+        &self,
+        qh: &QueueHandle<D>,
+        pool: &mut SlotPool,
+    ) -> Result<Buffer, CaptureError>
+    where
+        D: Dispatch<ExtImageCopyCaptureFrameV1, CaptureFrameData> + 'static,
+    {
+        let constraints = self.constraints().ok_or(CaptureError::NoConstraints)?;
+        let buffer = constraints.create_shm_buffer(pool)?;
+        self.capture(qh, buffer.wl_buffer(), &[])?;
+        Ok(buffer)
+    }
+
+    /// The underlying wayland object.
+    pub fn inner(&self) -> &ExtImageCopyCaptureSessionV1 {
+        &self.session
+    }
+
+    fn data(&self) -> &CaptureSessionData {
+        self.session.data::<CaptureSessionData>().unwrap()
+    }
+}
+
+impl Drop for CaptureSession {
+    fn drop(&mut self) {
+        if let Some(frame) = self.data().inner.lock().unwrap().frame.take() {
+            frame.destroy();
+        }
+        self.session.destroy();
+    }
+}
+
+/// The user data of a [`CaptureSession`].
+#[derive(Debug, Default)]
+pub struct CaptureSessionData {
+    inner: Mutex<CaptureSessionInner>,
+}
+
+#[derive(Debug, Default)]
+struct CaptureSessionInner {
+    constraints: Option<BufferConstraints>,
+    pending_constraints: BufferConstraints,
+    /// The frame being captured.
+    frame: Option<ExtImageCopyCaptureFrameV1>,
+    stopped: bool,
+}
+
+/// The user data of a frame captured by a [`CaptureSession`].
+#[derive(Debug)]
+pub struct CaptureFrameData {
+    session: ExtImageCopyCaptureSessionV1,
+    frame: Mutex<CaptureFrame>,
+}
+
+impl<D> Dispatch2<ExtImageCopyCaptureSessionV1, D> for CaptureSessionData
+where
+    D: ImageCaptureHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        session: &ExtImageCopyCaptureSessionV1,
+        event: ext_image_copy_capture_session_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        use ext_image_copy_capture_session_v1::Event;
+
+        let mut inner = self.inner.lock().unwrap();
+        match event {
+            Event::BufferSize { width, height } => {
+                inner.pending_constraints.width = width;
+                inner.pending_constraints.height = height;
+            }
+            Event::ShmFormat { format: WEnum::Value(format) } => {
+                inner.pending_constraints.shm_formats.push(format);
+            }
+            Event::ShmFormat { format: WEnum::Unknown(_) } => {}
+            Event::DmabufDevice { device } => {
+                inner.pending_constraints.dmabuf_device =
+                    device.try_into().ok().map(u64::from_ne_bytes);
+            }
+            Event::DmabufFormat { format, modifiers } => {
+                let modifiers = modifiers
+                    .chunks_exact(8)
+                    .flat_map(TryInto::<[u8; 8]>::try_into)
+                    .map(u64::from_ne_bytes)
+                    .collect();
+                inner.pending_constraints.dmabuf_formats.push((format, modifiers));
+            }
+            Event::Done => {
+                // The constraints are sent again in full when they change.
+                let constraints = std::mem::take(&mut inner.pending_constraints);
+                inner.constraints = Some(constraints.clone());
+
+                // Release the user data lock before calling into user.
+                drop(inner);
+                state.constraints_changed(conn, qh, session, &constraints);
+            }
+            Event::Stopped => {
+                inner.stopped = true;
+
+                drop(inner);
+                state.session_stopped(conn, qh, session);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch2<ExtImageCopyCaptureFrameV1, D> for CaptureFrameData
+where
+    D: ImageCaptureHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        frame: &ExtImageCopyCaptureFrameV1,
+        event: ext_image_copy_capture_frame_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        use ext_image_copy_capture_frame_v1::Event;
+
+        match event {
+            Event::Transform { transform: WEnum::Value(transform) } => {
+                self.frame.lock().unwrap().transform = transform;
+            }
+            Event::Transform { transform: WEnum::Unknown(_) } => {}
+            Event::Damage { x, y, width, height } => {
+                self.frame.lock().unwrap().damage.push(Rect::new(x, y, width, height));
+            }
+            Event::PresentationTime { tv_sec_hi, tv_sec_lo, tv_nsec } => {
+                self.frame.lock().unwrap().presentation_time =
+                    Some(presentation_time(tv_sec_hi, tv_sec_lo, tv_nsec));
+            }
+            Event::Ready => {
+                self.finish(frame);
+                let captured = std::mem::take(&mut *self.frame.lock().unwrap());
+                state.frame_ready(conn, qh, &self.session, captured);
+            }
+            Event::Failed { reason } => {
+                self.finish(frame);
+                let reason = match reason {
+                    WEnum::Value(FailureReason::BufferConstraints) => {
+                        CaptureFailure::BufferConstraints
+                    }
+                    WEnum::Value(FailureReason::Stopped) => CaptureFailure::Stopped,
+                    _ => CaptureFailure::Unknown,
+                };
+                state.frame_failed(conn, qh, &self.session, reason);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl CaptureFrameData {
+    /// Destroy the frame, allowing the session to capture the next one.
+    fn finish(&self, frame: &ExtImageCopyCaptureFrameV1) {
+        if let Some(data) = self.session.data::<CaptureSessionData>() {
+            let mut inner = data.inner.lock().unwrap();
+            if inner.frame.as_ref() == Some(frame) {
+                inner.frame = None;
+            }
+        }
+        frame.destroy();
+    }
+}
+
+impl<D> Dispatch2<ExtImageCopyCaptureManagerV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ExtImageCopyCaptureManagerV1,
+        _: ext_image_copy_capture_manager_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("ext_image_copy_capture_manager_v1 has no events")
+    }
+}
+
+impl<D> Dispatch2<ExtOutputImageCaptureSourceManagerV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ExtOutputImageCaptureSourceManagerV1,
+        _: ext_output_image_capture_source_manager_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("ext_output_image_capture_source_manager_v1 has no events")
+    }
+}
+
+impl<D> Dispatch2<ExtForeignToplevelImageCaptureSourceManagerV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ExtForeignToplevelImageCaptureSourceManagerV1,
+        _: ext_foreign_toplevel_image_capture_source_manager_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("ext_foreign_toplevel_image_capture_source_manager_v1 has no events")
+    }
+}
+
+impl<D> Dispatch2<ExtImageCaptureSourceV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ExtImageCaptureSourceV1,
+        _: ext_image_capture_source_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("ext_image_capture_source_v1 has no events")
+    }
+}
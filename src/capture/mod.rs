@@ -0,0 +1,194 @@
+//! Capture of the contents of outputs and toplevels, for screenshots and screen recording.
+//!
+//! [`ImageCaptureState`] uses `ext_image_copy_capture_v1`, and [`ScreencopyState`] uses
+//! `zwlr_screencopy_manager_v1` for compositors without it. Both report the buffers they accept
+//! as [`BufferConstraints`], which can be used to allocate shared memory buffers from a
+//! [`SlotPool`] or a [`MultiPool`], and deliver the captured frames as [`CaptureFrame`].
+
+use std::time::Duration;
+
+use crate::reexports::client::protocol::{wl_buffer, wl_output, wl_shm};
+
+use crate::{
+    compositor::Rect,
+    shm::{
+        multi::{MultiPool, PoolError},
+        slot::{Buffer, CreateBufferError, SlotPool},
+    },
+};
+
+pub mod image_copy;
+pub mod screencopy;
+
+pub use image_copy::{CaptureSession, CaptureSource, ImageCaptureHandler, ImageCaptureState};
+pub use screencopy::{ScreencopyFrame, ScreencopyHandler, ScreencopyState};
+
+/// The shared memory formats with 4 bytes per pixel, in order of preference.
+const SHM_FORMATS: [wl_shm::Format; 8] = [
+    wl_shm::Format::Argb8888,
+    wl_shm::Format::Xrgb8888,
+    wl_shm::Format::Abgr8888,
+    wl_shm::Format::Xbgr8888,
+    wl_shm::Format::Argb2101010,
+    wl_shm::Format::Xrgb2101010,
+    wl_shm::Format::Abgr2101010,
+    wl_shm::Format::Xbgr2101010,
+];
+
+/// The buffers accepted to capture a frame.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferConstraints {
+    pub width: u32,
+    pub height: u32,
+
+    /// The stride of shared memory buffers, if the protocol requires one.
+    pub stride: Option<u32>,
+
+    /// The accepted shared memory formats.
+    pub shm_formats: Vec<wl_shm::Format>,
+
+    /// The device to allocate dmabufs on, as a `dev_t`.
+    pub dmabuf_device: Option<u64>,
+
+    /// The accepted dmabuf formats, as fourcc codes with their modifiers.
+    pub dmabuf_formats: Vec<(u32, Vec<u64>)>,
+}
+
+impl BufferConstraints {
+    /// The preferred shared memory format, among the accepted formats with 4 bytes per pixel.
+    pub fn preferred_shm_format(&self) -> Option<wl_shm::Format> {
+        SHM_FORMATS.into_iter().find(|format| self.shm_formats.contains(format))
+    }
+
+    /// Allocate a shared memory buffer for the capture from `pool`.
+    pub fn create_shm_buffer(&self, pool: &mut SlotPool) -> Result<Buffer, CaptureError> {
+        let (format, stride) = self.shm_layout()?;
+        let (buffer, _) =
+            pool.create_buffer(self.width as i32, self.height as i32, stride as i32, format)?;
+        Ok(buffer)
+    }
+
+    /// Allocate a shared memory buffer for the capture from `pool`, stored with `key`.
+    pub fn create_multi_pool_buffer<'pool, K>(
+        &self,
+        pool: &'pool mut MultiPool<K>,
+        key: &K,
+    ) -> Result<&'pool wl_buffer::WlBuffer, CaptureError>
+    where
+        K: Clone + PartialEq,
+    {
+        let (format, stride) = self.shm_layout()?;
+        let (_, buffer, _) =
+            pool.create_buffer(self.width as i32, stride as i32, self.height as i32, key, format)?;
+        Ok(buffer)
+    }
+
+    fn shm_layout(&self) -> Result<(wl_shm::Format, u32), CaptureError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(CaptureError::NoConstraints);
+        }
+        let format = self.preferred_shm_format().ok_or(CaptureError::UnsupportedFormat)?;
+        Ok((format, self.stride.unwrap_or(self.width * 4)))
+    }
+}
+
+/// A captured frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CaptureFrame {
+    /// The transform to apply to the buffer to display it upright.
+    ///
+    /// `zwlr_screencopy_manager_v1` doesn't provide it, and the transform of the output should
+    /// be used instead.
+    pub transform: wl_output::Transform,
+
+    /// The regions of the buffer which changed since the previous frame.
+    pub damage: Vec<Rect<i32>>,
+
+    /// The time the frame was presented, in the clock of the compositor.
+    pub presentation_time: Option<Duration>,
+
+    /// Whether the buffer is upside down.
+    pub y_invert: bool,
+}
+
+impl Default for CaptureFrame {
+    fn default() -> Self {
+        Self {
+            transform: wl_output::Transform::Normal,
+            damage: Vec::new(),
+            presentation_time: None,
+            y_invert: false,
+        }
+    }
+}
+
+/// Why a capture failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaptureFailure {
+    /// The compositor didn't give a reason.
+    Unknown,
+    /// The buffer didn't match the constraints, which may have changed.
+    BufferConstraints,
+    /// The session was stopped.
+    Stopped,
+}
+
+/// An error that may occur when capturing a frame.
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+    #[error("the buffer constraints are not known yet")]
+    NoConstraints,
+
+    #[error("no shared memory format with 4 bytes per pixel is supported")]
+    UnsupportedFormat,
+
+    #[error("a frame is already being captured")]
+    FrameInProgress,
+
+    #[error("the capture was stopped")]
+    Stopped,
+
+    #[error(transparent)]
+    CreateBuffer(#[from] CreateBufferError),
+
+    #[error(transparent)]
+    Pool(#[from] PoolError),
+}
+
+/// Convert the time of a frame from its protocol representation.
+fn presentation_time(tv_sec_hi: u32, tv_sec_lo: u32, tv_nsec: u32) -> Duration {
+    Duration::new((u64::from(tv_sec_hi) << 32) | u64::from(tv_sec_lo), tv_nsec)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shm_layout() {
+        let mut constraints = BufferConstraints {
+            width: 10,
+            height: 5,
+            shm_formats: vec![wl_shm::Format::Rgb565, wl_shm::Format::Xrgb8888],
+            ..Default::default()
+        };
+        assert_eq!(constraints.shm_layout().unwrap(), (wl_shm::Format::Xrgb8888, 40));
+
+        constraints.stride = Some(48);
+        assert_eq!(constraints.shm_layout().unwrap(), (wl_shm::Format::Xrgb8888, 48));
+
+        constraints.shm_formats = vec![wl_shm::Format::Rgb565];
+        assert!(matches!(constraints.shm_layout(), Err(CaptureError::UnsupportedFormat)));
+
+        assert!(matches!(
+            BufferConstraints::default().shm_layout(),
+            Err(CaptureError::NoConstraints)
+        ));
+    }
+
+    #[test]
+    fn presentation_time_hi_lo() {
+        assert_eq!(presentation_time(1, 2, 3), Duration::new((1 << 32) + 2, 3));
+    }
+}
@@ -0,0 +1,300 @@
+use std::sync::Mutex;
+
+use crate::reexports::client::{
+    globals::GlobalList,
+    protocol::{wl_buffer, wl_output},
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+use crate::reexports::protocols_wlr::screencopy::v1::client::{
+    zwlr_screencopy_frame_v1::{self, Flags, ZwlrScreencopyFrameV1},
+    zwlr_screencopy_manager_v1::{self, ZwlrScreencopyManagerV1},
+};
+
+use crate::{
+    compositor::Rect,
+    dispatch2::Dispatch2,
+    error::GlobalError,
+    globals::GlobalData,
+    registry::GlobalProxy,
+    shm::slot::{Buffer, SlotPool},
+};
+
+use super::{presentation_time, BufferConstraints, CaptureError, CaptureFrame};
+
+/// Handler trait for the events of a [`ScreencopyFrame`].
+pub trait ScreencopyHandler: Sized {
+    /// The buffers accepted to copy the frame are known.
+    fn screencopy_constraints(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        frame: &ZwlrScreencopyFrameV1,
+        constraints: &BufferConstraints,
+    );
+
+    /// The frame was copied into the buffer, and the [`ScreencopyFrame`] may be dropped.
+    fn screencopy_ready(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        frame: &ZwlrScreencopyFrameV1,
+        captured: CaptureFrame,
+    );
+
+    /// Copying the frame failed, and the [`ScreencopyFrame`] should be dropped.
+    fn screencopy_failed(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        frame: &ZwlrScreencopyFrameV1,
+    );
+}
+
+/// State for `zwlr_screencopy_manager_v1`.
+///
+/// Each capture is a single [`ScreencopyFrame`], with its own buffer constraints.
+#[derive(Debug)]
+pub struct ScreencopyState {
+    manager: GlobalProxy<ZwlrScreencopyManagerV1>,
+}
+
+impl ScreencopyState {
+    pub fn bind<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<ZwlrScreencopyManagerV1, GlobalData> + 'static,
+    {
+        Self { manager: GlobalProxy::from(globals.bind(qh, 1..=3, GlobalData)) }
+    }
+
+    /// Capture the next frame of an output, with the cursor if `overlay_cursor` is set.
+    pub fn capture_output<D>(
+        &self,
+        qh: &QueueHandle<D>,
+        output: &wl_output::WlOutput,
+        overlay_cursor: bool,
+    ) -> Result<ScreencopyFrame, GlobalError>
+    where
+        D: Dispatch<ZwlrScreencopyFrameV1, ScreencopyFrameData> + 'static,
+    {
+        let frame = self.manager.get()?.capture_output(
+            overlay_cursor as i32,
+            output,
+            qh,
+            ScreencopyFrameData::default(),
+        );
+        Ok(ScreencopyFrame { frame })
+    }
+
+    /// Capture the next frame of a region of an output, in output logical coordinates.
+    pub fn capture_output_region<D>(
+        &self,
+        qh: &QueueHandle<D>,
+        output: &wl_output::WlOutput,
+        overlay_cursor: bool,
+        region: Rect<i32>,
+    ) -> Result<ScreencopyFrame, GlobalError>
+    where
+        D: Dispatch<ZwlrScreencopyFrameV1, ScreencopyFrameData> + 'static,
+    {
+        let frame = self.manager.get()?.capture_output_region(
+            overlay_cursor as i32,
+            output,
+            region.x,
+            region.y,
+            region.width,
+            region.height,
+            qh,
+            ScreencopyFrameData::default(),
+        );
+        Ok(ScreencopyFrame { frame })
+    }
+}
+
+impl Drop for ScreencopyState {
+    fn drop(&mut self) {
+        if let Ok(manager) = self.manager.get() {
+            manager.destroy();
+        }
+    }
+}
+
+/// A frame captured with `zwlr_screencopy_manager_v1`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScreencopyFrame {
+    frame: ZwlrScreencopyFrameV1,
+}
+
+impl ScreencopyFrame {
+    /// The buffers accepted to copy the frame, once the compositor sent them.
+    pub fn constraints(&self) -> Option<BufferConstraints> {
+        self.data().inner.lock().unwrap().constraints.clone()
+    }
+
+    /// Copy the frame into `buffer` right away.
+    ///
+    /// The buffer must not be used until [`ScreencopyHandler::screencopy_ready`] or
+    /// [`ScreencopyHandler::screencopy_failed`] is called.
+    pub fn copy(&self, buffer: &wl_buffer::WlBuffer) -> Result<(), CaptureError> {
+        self.start_copy()?;
+        self.frame.copy(buffer);
+        Ok(())
+    }
+
+    /// Copy the frame into `buffer` once the output changed since the previous frame, and
+    /// report the damage.
+    ///
+    /// This is the same as [`ScreencopyFrame::copy`] if the compositor only supports the first
+    /// version of the protocol.
+    pub fn copy_with_damage(&self, buffer: &wl_buffer::WlBuffer) -> Result<(), CaptureError> {
+        self.start_copy()?;
+        if self.frame.version() >= 2 {
+            self.frame.copy_with_damage(buffer);
+        } else {
+            self.frame.copy(buffer);
+        }
+        Ok(())
+    }
+
+    /// Allocate a shared memory buffer from `pool` and copy the frame into it right away.
+    ///
+    /// The buffer must be kept until the frame is ready, and can then be read through
+    /// [`Buffer::canvas`].
+    pub fn copy_shm(&self, pool: &mut SlotPool) -> Result<Buffer, CaptureError> {
+        let constraints = self.constraints().ok_or(CaptureError::NoConstraints)?;
+        let buffer = constraints.create_shm_buffer(pool)?;
+        self.copy(buffer.wl_buffer())?;
+        Ok(buffer)
+    }
+
+    /// The underlying wayland object.
+    pub fn inner(&self) -> &ZwlrScreencopyFrameV1 {
+        &self.frame
+    }
+
+    fn start_copy(&self) -> Result<(), CaptureError> {
+        let mut inner = self.data().inner.lock().unwrap();
+        if inner.failed {
+            return Err(CaptureError::Stopped);
+        }
+        if inner.constraints.is_none() {
+            return Err(CaptureError::NoConstraints);
+        }
+        if std::mem::replace(&mut inner.copied, true) {
+            return Err(CaptureError::FrameInProgress);
+        }
+        Ok(())
+    }
+
+    fn data(&self) -> &ScreencopyFrameData {
+        self.frame.data::<ScreencopyFrameData>().unwrap()
+    }
+}
+
+impl Drop for ScreencopyFrame {
+    fn drop(&mut self) {
+        self.frame.destroy();
+    }
+}
+
+/// The user data of a [`ScreencopyFrame`].
+#[derive(Debug, Default)]
+pub struct ScreencopyFrameData {
+    inner: Mutex<ScreencopyFrameInner>,
+}
+
+#[derive(Debug, Default)]
+struct ScreencopyFrameInner {
+    constraints: Option<BufferConstraints>,
+    pending_constraints: BufferConstraints,
+    frame: CaptureFrame,
+    copied: bool,
+    failed: bool,
+}
+
+impl<D> Dispatch2<ZwlrScreencopyFrameV1, D> for ScreencopyFrameData
+where
+    D: ScreencopyHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        frame: &ZwlrScreencopyFrameV1,
+        event: zwlr_screencopy_frame_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        use zwlr_screencopy_frame_v1::Event;
+
+        let mut inner = self.inner.lock().unwrap();
+        match event {
+            Event::Buffer { format, width, height, stride } => {
+                let pending = &mut inner.pending_constraints;
+                pending.width = width;
+                pending.height = height;
+                pending.stride = Some(stride);
+                if let WEnum::Value(format) = format {
+                    pending.shm_formats.push(format);
+                }
+
+                // Before version 3, the shared memory buffer is the only one.
+                if frame.version() < 3 {
+                    let constraints = std::mem::take(&mut inner.pending_constraints);
+                    inner.constraints = Some(constraints.clone());
+
+                    // Release the user data lock before calling into user.
+                    drop(inner);
+                    state.screencopy_constraints(conn, qh, frame, &constraints);
+                }
+            }
+            Event::LinuxDmabuf { format, width, height } => {
+                let pending = &mut inner.pending_constraints;
+                pending.width = width;
+                pending.height = height;
+                pending.dmabuf_formats.push((format, Vec::new()));
+            }
+            Event::BufferDone => {
+                let constraints = std::mem::take(&mut inner.pending_constraints);
+                inner.constraints = Some(constraints.clone());
+
+                drop(inner);
+                state.screencopy_constraints(conn, qh, frame, &constraints);
+            }
+            Event::Flags { flags } => {
+                inner.frame.y_invert = match flags {
+                    WEnum::Value(flags) => flags.contains(Flags::YInvert),
+                    WEnum::Unknown(raw) => Flags::from_bits_truncate(raw).contains(Flags::YInvert),
+                };
+            }
+            Event::Damage { x, y, width, height } => {
+                inner.frame.damage.push(Rect::new(x as i32, y as i32, width as i32, height as i32));
+            }
+            Event::Ready { tv_sec_hi, tv_sec_lo, tv_nsec } => {
+                let mut captured = std::mem::take(&mut inner.frame);
+                captured.presentation_time = Some(presentation_time(tv_sec_hi, tv_sec_lo, tv_nsec));
+
+                drop(inner);
+                state.screencopy_ready(conn, qh, frame, captured);
+            }
+            Event::Failed => {
+                inner.failed = true;
+
+                drop(inner);
+                state.screencopy_failed(conn, qh, frame);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch2<ZwlrScreencopyManagerV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ZwlrScreencopyManagerV1,
+        _: zwlr_screencopy_manager_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("zwlr_screencopy_manager_v1 has no events")
+    }
+}
@@ -0,0 +1,515 @@
+//! A per seat clipboard, covering both the regular and the primary selection.
+//!
+//! [`Clipboard`] owns the data devices of a seat and the sources of the selections it sets. The
+//! data of a selection is served from the event loop until another client replaces it, and loading
+//! the selection never blocks, so pasting the selection of the application itself is fine.
+//!
+//! The data devices are still dispatched to [`DataDeviceHandler`] and
+//! [`PrimarySelectionDeviceHandler`], which tell the application when the selection changed.
+//!
+//! [`DataDeviceHandler`]: crate::data_device_manager::data_device::DataDeviceHandler
+//! [`PrimarySelectionDeviceHandler`]: crate::primary_selection::device::PrimarySelectionDeviceHandler
+
+use std::{
+    borrow::Cow,
+    io,
+    sync::{Arc, Mutex},
+};
+
+use calloop::{channel, LoopHandle, RegistrationToken};
+use wayland_client::{
+    protocol::{
+        wl_data_device::WlDataDevice,
+        wl_data_device_manager::WlDataDeviceManager,
+        wl_data_source::{self, WlDataSource},
+        wl_seat::WlSeat,
+    },
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols::wp::primary_selection::zv1::client::{
+    zwp_primary_selection_device_manager_v1::ZwpPrimarySelectionDeviceManagerV1,
+    zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1,
+    zwp_primary_selection_source_v1::{self, ZwpPrimarySelectionSourceV1},
+};
+
+use crate::{
+    data_device_manager::{
+        data_device::{DataDevice, DataDeviceData},
+        data_offer::receive,
+        data_source::CopyPasteSource,
+        mime::{decode_text, negotiate, transcode_text, with_aliases, DataFormat, TEXT_MIME_TYPES},
+        DataDeviceManagerState, ReadPipe, TransferError, TransferOptions, WritePipe,
+    },
+    dispatch2::Dispatch2,
+    primary_selection::{
+        device::{PrimarySelectionDevice, PrimarySelectionDeviceData},
+        selection::PrimarySelectionSource,
+        PrimarySelectionManagerState,
+    },
+};
+
+/// The selection to operate on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SelectionKind {
+    /// The regular selection, used by copy and paste.
+    Clipboard,
+    /// The primary selection, set by selecting text and pasted with the middle button.
+    Primary,
+}
+
+/// An error that may occur when using the clipboard.
+#[derive(Debug, thiserror::Error)]
+pub enum ClipboardError {
+    #[error("the compositor does not support the primary selection")]
+    PrimarySelectionUnsupported,
+
+    #[error("the selection is empty")]
+    EmptySelection,
+
+    #[error("the selection cannot be converted to {0}")]
+    UnsupportedMimeType(String),
+
+    #[error("event loop error")]
+    Calloop(#[from] calloop::Error),
+
+    #[error("transfer error")]
+    Transfer(#[from] TransferError),
+
+    #[error("IO error")]
+    Io(#[from] io::Error),
+}
+
+/// The data of a selection, for each mime type.
+type SelectionContents = Vec<(String, Arc<[u8]>)>;
+
+/// The clipboard of a seat.
+///
+/// Creating the clipboard registers a source in the event loop, which writes the selection when
+/// other clients paste it. It is removed when the clipboard is dropped.
+#[derive(Debug)]
+pub struct Clipboard<D: 'static> {
+    seat: WlSeat,
+    qh: QueueHandle<D>,
+    loop_handle: LoopHandle<'static, D>,
+    data_device_manager: WlDataDeviceManager,
+    data_device: DataDevice,
+    primary_selection_manager: Option<ZwpPrimarySelectionDeviceManagerV1>,
+    primary_selection_device: Option<PrimarySelectionDevice>,
+    source: Option<CopyPasteSource>,
+    primary_source: Option<PrimarySelectionSource>,
+    sender: channel::Sender<SendRequest>,
+    token: RegistrationToken,
+}
+
+impl<D: 'static> Clipboard<D> {
+    /// Create the clipboard of `seat`.
+    ///
+    /// The primary selection is only available if `primary_selection_manager` is provided.
+    pub fn new(
+        seat: &WlSeat,
+        data_device_manager: &DataDeviceManagerState,
+        primary_selection_manager: Option<&PrimarySelectionManagerState>,
+        qh: &QueueHandle<D>,
+        loop_handle: LoopHandle<'static, D>,
+    ) -> Result<Self, ClipboardError>
+    where
+        D: Dispatch<WlDataDevice, DataDeviceData>
+            + Dispatch<ZwpPrimarySelectionDeviceV1, PrimarySelectionDeviceData>,
+    {
+        let (sender, channel) = channel::channel::<SendRequest>();
+
+        // The handle is weak so the source doesn't keep the event loop alive.
+        let weak_handle = loop_handle.downgrade();
+        let token = loop_handle
+            .insert_source(channel, move |event, _, _| {
+                if let (channel::Event::Msg(request), Some(loop_handle)) =
+                    (event, weak_handle.upgrade())
+                {
+                    write_selection(&loop_handle, request);
+                }
+            })
+            .map_err(|err| err.error)?;
+
+        Ok(Self {
+            seat: seat.clone(),
+            qh: qh.clone(),
+            loop_handle,
+            data_device_manager: data_device_manager.data_device_manager().clone(),
+            data_device: data_device_manager.get_data_device(qh, seat),
+            primary_selection_manager: primary_selection_manager
+                .map(|manager| manager.primary_selection_manager().clone()),
+            primary_selection_device: primary_selection_manager
+                .map(|manager| manager.get_selection_device(qh, seat)),
+            source: None,
+            primary_source: None,
+            sender,
+            token,
+        })
+    }
+
+    /// The seat of this clipboard.
+    pub fn seat(&self) -> &WlSeat {
+        &self.seat
+    }
+
+    /// The data device of the seat, which may be used for drag and drop.
+    pub fn data_device(&self) -> &DataDevice {
+        &self.data_device
+    }
+
+    /// The primary selection device of the seat, if the primary selection is supported.
+    pub fn primary_selection_device(&self) -> Option<&PrimarySelectionDevice> {
+        self.primary_selection_device.as_ref()
+    }
+
+    /// Whether the primary selection is supported.
+    pub fn has_primary_selection(&self) -> bool {
+        self.primary_selection_device.is_some()
+    }
+
+    /// Set the selection to `text`, offered as every text mime type.
+    ///
+    /// The `serial` is the serial of the input event which caused the copy.
+    pub fn set_text(
+        &mut self,
+        kind: SelectionKind,
+        text: impl Into<String>,
+        serial: u32,
+    ) -> Result<(), ClipboardError>
+    where
+        D: Dispatch<WlDataSource, ClipboardSourceData>
+            + Dispatch<ZwpPrimarySelectionSourceV1, ClipboardSourceData>,
+    {
+        self.set_contents(kind, [(TEXT_MIME_TYPES[0], text.into())], serial)
+    }
+
+    /// Set the selection to `contents`, which provides the data of each offered mime type.
+    ///
+    /// The data of a mime type is also offered under its aliases, see [`with_aliases`]. Text is
+    /// converted to Latin-1 for `STRING`.
+    ///
+    /// The `serial` is the serial of the input event which caused the copy.
+    pub fn set_contents<M, B>(
+        &mut self,
+        kind: SelectionKind,
+        contents: impl IntoIterator<Item = (M, B)>,
+        serial: u32,
+    ) -> Result<(), ClipboardError>
+    where
+        D: Dispatch<WlDataSource, ClipboardSourceData>
+            + Dispatch<ZwpPrimarySelectionSourceV1, ClipboardSourceData>,
+        M: Into<String>,
+        B: Into<Vec<u8>>,
+    {
+        self.set_selection(kind, expand_contents(contents), serial)
+    }
+
+    /// Remove the selection.
+    ///
+    /// The `serial` is the serial of the input event which caused the selection to be cleared.
+    pub fn clear(&mut self, kind: SelectionKind, serial: u32) -> Result<(), ClipboardError> {
+        match kind {
+            SelectionKind::Clipboard => {
+                self.source = None;
+                self.data_device.unset_selection(serial);
+            }
+            SelectionKind::Primary => {
+                let device = self
+                    .primary_selection_device
+                    .as_ref()
+                    .ok_or(ClipboardError::PrimarySelectionUnsupported)?;
+                self.primary_source = None;
+                device.unset_selection(serial);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the selection is currently set by this clipboard.
+    ///
+    /// This becomes false once another client replaced the selection.
+    pub fn owns_selection(&self, kind: SelectionKind) -> bool {
+        let data = match kind {
+            SelectionKind::Clipboard => {
+                self.source.as_ref().and_then(|source| source.inner().data::<ClipboardSourceData>())
+            }
+            SelectionKind::Primary => self
+                .primary_source
+                .as_ref()
+                .and_then(|source| source.inner().data::<ClipboardSourceData>()),
+        };
+
+        data.is_some_and(|data| data.contents.lock().unwrap().is_some())
+    }
+
+    /// The mime types offered by the current selection.
+    pub fn mime_types(&self, kind: SelectionKind) -> Vec<String> {
+        match kind {
+            SelectionKind::Clipboard => self
+                .data_device
+                .data()
+                .selection_offer()
+                .map(|offer| offer.with_mime_types(<[String]>::to_vec))
+                .unwrap_or_default(),
+            SelectionKind::Primary => self
+                .primary_selection_device
+                .as_ref()
+                .and_then(|device| device.data().selection_offer())
+                .map(|offer| offer.with_mime_types(<[String]>::to_vec))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Load the selection as `mime_type`.
+    ///
+    /// The data is read from the event loop, and `callback` is called once all of it was received,
+    /// or if reading failed. The request is sent on the next flush of the connection.
+    pub fn load<F>(
+        &self,
+        kind: SelectionKind,
+        mime_type: &str,
+        callback: F,
+    ) -> Result<(), ClipboardError>
+    where
+        F: FnOnce(&mut D, Result<Vec<u8>, ClipboardError>) + 'static,
+    {
+        let read_pipe = self.receive(kind, mime_type)?;
+        read_pipe.receive_all(
+            &self.loop_handle,
+            TransferOptions::default(),
+            move |state, result| callback(state, result.map_err(ClipboardError::from)),
+        )?;
+
+        Ok(())
+    }
+
+    /// Load the selection as text, using the preferred text mime type it offers.
+    ///
+    /// The text is decoded with [`decode_text`].
+    pub fn load_text<F>(&self, kind: SelectionKind, callback: F) -> Result<(), ClipboardError>
+    where
+        F: FnOnce(&mut D, Result<String, ClipboardError>) + 'static,
+    {
+        let (_, mime_type) = negotiate(&[DataFormat::Text], &self.mime_types(kind))
+            .ok_or_else(|| ClipboardError::UnsupportedMimeType(TEXT_MIME_TYPES[0].to_string()))?;
+
+        self.load(kind, &mime_type.clone(), move |state, result| {
+            callback(state, result.map(|data| decode_text(&mime_type, &data)))
+        })
+    }
+
+    fn set_selection(
+        &mut self,
+        kind: SelectionKind,
+        contents: SelectionContents,
+        serial: u32,
+    ) -> Result<(), ClipboardError>
+    where
+        D: Dispatch<WlDataSource, ClipboardSourceData>
+            + Dispatch<ZwpPrimarySelectionSourceV1, ClipboardSourceData>,
+    {
+        let mime_types: Vec<String> = contents.iter().map(|(mime, _)| mime.clone()).collect();
+        let data = ClipboardSourceData {
+            contents: Mutex::new(Some(Arc::new(contents))),
+            sender: self.sender.clone(),
+        };
+
+        match kind {
+            SelectionKind::Clipboard => {
+                let source = self.data_device_manager.create_data_source(&self.qh, data);
+                for mime in mime_types {
+                    source.offer(mime);
+                }
+
+                let source = CopyPasteSource { inner: source };
+                source.set_selection(&self.data_device, serial);
+                self.source = Some(source);
+            }
+            SelectionKind::Primary => {
+                let (manager, device) = self
+                    .primary_selection_manager
+                    .as_ref()
+                    .zip(self.primary_selection_device.as_ref())
+                    .ok_or(ClipboardError::PrimarySelectionUnsupported)?;
+
+                let source = manager.create_source(&self.qh, data);
+                for mime in mime_types {
+                    source.offer(mime);
+                }
+
+                let source = PrimarySelectionSource::new(source);
+                source.set_selection(device, serial);
+                self.primary_source = Some(source);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn receive(&self, kind: SelectionKind, mime_type: &str) -> Result<ReadPipe, ClipboardError> {
+        match kind {
+            SelectionKind::Clipboard => {
+                let offer = self
+                    .data_device
+                    .data()
+                    .selection_offer()
+                    .ok_or(ClipboardError::EmptySelection)?;
+                if !offer.with_mime_types(|mimes| mimes.iter().any(|mime| mime == mime_type)) {
+                    return Err(ClipboardError::UnsupportedMimeType(mime_type.to_string()));
+                }
+
+                Ok(receive(offer.inner(), mime_type.to_string())?)
+            }
+            SelectionKind::Primary => {
+                let offer = self
+                    .primary_selection_device
+                    .as_ref()
+                    .ok_or(ClipboardError::PrimarySelectionUnsupported)?
+                    .data()
+                    .selection_offer()
+                    .ok_or(ClipboardError::EmptySelection)?;
+                if !offer.with_mime_types(|mimes| mimes.iter().any(|mime| mime == mime_type)) {
+                    return Err(ClipboardError::UnsupportedMimeType(mime_type.to_string()));
+                }
+
+                Ok(offer.receive(mime_type.to_string())?)
+            }
+        }
+    }
+}
+
+impl<D: 'static> Drop for Clipboard<D> {
+    fn drop(&mut self) {
+        self.loop_handle.remove(self.token);
+    }
+}
+
+/// The user data of the sources created by [`Clipboard`].
+#[derive(Debug)]
+pub struct ClipboardSourceData {
+    /// The data of the selection, removed once the source is cancelled.
+    contents: Mutex<Option<Arc<SelectionContents>>>,
+    sender: channel::Sender<SendRequest>,
+}
+
+impl ClipboardSourceData {
+    fn send(&self, mime_type: String, write_pipe: WritePipe) {
+        let contents = self.contents.lock().unwrap().clone();
+        let data = contents.and_then(|contents| {
+            contents.iter().find(|(mime, _)| *mime == mime_type).map(|(_, data)| data.clone())
+        });
+
+        // Without data, dropping the pipe tells the receiver there is nothing to read.
+        if let Some(data) = data {
+            let _ = self.sender.send(SendRequest { data, write_pipe });
+        }
+    }
+
+    fn cancelled(&self) {
+        self.contents.lock().unwrap().take();
+    }
+}
+
+impl<D> Dispatch2<WlDataSource, D> for ClipboardSourceData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &WlDataSource,
+        event: wl_data_source::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        match event {
+            wl_data_source::Event::Send { mime_type, fd } => self.send(mime_type, fd.into()),
+            wl_data_source::Event::Cancelled => self.cancelled(),
+            // Drag and drop events are not sent to selection sources.
+            _ => {}
+        }
+    }
+}
+
+impl<D> Dispatch2<ZwpPrimarySelectionSourceV1, D> for ClipboardSourceData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ZwpPrimarySelectionSourceV1,
+        event: zwp_primary_selection_source_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        match event {
+            zwp_primary_selection_source_v1::Event::Send { mime_type, fd } => {
+                self.send(mime_type, fd.into())
+            }
+            zwp_primary_selection_source_v1::Event::Cancelled => self.cancelled(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SendRequest {
+    data: Arc<[u8]>,
+    write_pipe: WritePipe,
+}
+
+/// Offer the data of each mime type under its aliases too.
+fn expand_contents<M, B>(contents: impl IntoIterator<Item = (M, B)>) -> SelectionContents
+where
+    M: Into<String>,
+    B: Into<Vec<u8>>,
+{
+    let mut expanded: SelectionContents = Vec::new();
+    for (mime, data) in contents {
+        let mime = mime.into();
+        let data: Arc<[u8]> = data.into().into();
+        for alias in with_aliases([&mime]) {
+            if expanded.iter().any(|(existing, _)| *existing == alias) {
+                continue;
+            }
+            // Text aliases don't all have the same encoding.
+            let alias_data = match transcode_text(&mime, &alias, &data) {
+                Cow::Borrowed(_) => data.clone(),
+                Cow::Owned(transcoded) => transcoded.into(),
+            };
+            expanded.push((alias, alias_data));
+        }
+    }
+    expanded
+}
+
+/// Write the data of a selection from the event loop.
+fn write_selection<D: 'static>(loop_handle: &LoopHandle<'static, D>, request: SendRequest) {
+    let result = request.write_pipe.send_all(
+        loop_handle,
+        request.data,
+        TransferOptions::default(),
+        |_, result| match result {
+            // The receiver may close the pipe before reading everything.
+            Ok(_) | Err(TransferError::BrokenPipe) => {}
+            Err(err) => log::warn!(target: "sctk", "failed to write the selection: {err}"),
+        },
+    );
+
+    if let Err(err) = result {
+        log::warn!(target: "sctk", "failed to write the selection: {err}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn text_aliases() {
+        // The contents `Clipboard::set_text` sets.
+        let contents = expand_contents([(TEXT_MIME_TYPES[0], String::from("café"))]);
+
+        for (mime, data) in &contents {
+            assert_eq!(decode_text(mime, data), "café", "{mime}");
+        }
+        let (_, string) = contents.iter().find(|(mime, _)| mime == "STRING").unwrap();
+        assert_eq!(**string, b"caf\xe9"[..]);
+    }
+}
@@ -2,9 +2,10 @@ use std::mem;
 use std::os::unix::io::OwnedFd;
 use std::sync::MutexGuard;
 use std::sync::{
-    atomic::{AtomicI32, Ordering},
+    atomic::{AtomicI32, AtomicU32, Ordering},
     Arc, Mutex,
 };
+use std::time::Duration;
 
 use wayland_client::{
     globals::{BindError, GlobalList},
@@ -18,9 +19,16 @@ use wayland_client::{
 use crate::{
     dispatch2::Dispatch2,
     error::GlobalError,
+    fractional_scale::{FractionalScale, FractionalScaleData, FractionalScaleState},
     globals::{GlobalData, ProvidesBoundGlobal},
     output::{OutputData, OutputHandler, OutputState, ScaleWatcherHandle},
+    presentation_time::PresentationTimeState,
 };
+use wayland_protocols::wp::fractional_scale::v1::client::{
+    wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+    wp_fractional_scale_v1::WpFractionalScaleV1,
+};
+use wayland_protocols::wp::presentation_time::client::{wp_presentation, wp_presentation_feedback};
 
 pub trait CompositorHandler: Sized {
     /// The surface has either been moved into or out of an output and the output has a different scale factor.
@@ -32,6 +40,23 @@ pub trait CompositorHandler: Sized {
         new_factor: i32,
     );
 
+    /// The compositor has sent a new preferred fractional scale for the surface.
+    ///
+    /// The scale is the numerator of a fraction with a denominator of `120`. This is only called
+    /// for surfaces with a fractional scale object, see [`CompositorState::enable_fractional_scale`]
+    /// and [`FractionalScaleState::fractional_scale`].
+    ///
+    /// The default implementation does nothing.
+    fn fractional_scale_changed(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
+        new_scale: u32,
+    ) {
+        let _ = (conn, qh, surface, new_scale);
+    }
+
     /// The surface has either been moved into or out of an output and the output has different transform.
     fn transform_changed(
         &mut self,
@@ -81,6 +106,7 @@ pub trait CompositorHandler: Sized {
 #[derive(Clone, Debug)]
 pub struct CompositorState {
     wl_compositor: wl_compositor::WlCompositor,
+    fractional_scale_manager: Option<WpFractionalScaleManagerV1>,
 }
 
 impl CompositorState {
@@ -98,16 +124,29 @@ impl CompositorState {
         State: Dispatch<wl_compositor::WlCompositor, GlobalData, State> + 'static,
     {
         let wl_compositor = globals.bind(qh, 1..=Self::API_VERSION_MAX, GlobalData)?;
-        Ok(CompositorState { wl_compositor })
+        Ok(CompositorState { wl_compositor, fractional_scale_manager: None })
     }
 
     pub fn wl_compositor(&self) -> &wl_compositor::WlCompositor {
         &self.wl_compositor
     }
 
+    /// Attach a fractional scale object to the surfaces created from now on, if
+    /// `wp_fractional_scale_manager_v1` is available.
+    ///
+    /// The preferred scale is then available through [`SurfaceData::fractional_scale`] and
+    /// reported to [`CompositorHandler::fractional_scale_changed`]. The fractional scale object is
+    /// destroyed along with the surface data.
+    pub fn enable_fractional_scale(&mut self, fractional_scale: &FractionalScaleState) {
+        self.fractional_scale_manager =
+            fractional_scale.wp_fractional_scale_manager().ok().cloned();
+    }
+
     pub fn create_surface<D>(&self, qh: &QueueHandle<D>) -> wl_surface::WlSurface
     where
-        D: Dispatch<wl_surface::WlSurface, SurfaceData<()>> + 'static,
+        D: Dispatch<wl_surface::WlSurface, SurfaceData<()>>
+            + Dispatch<WpFractionalScaleV1, FractionalScaleData<()>>
+            + 'static,
     {
         self.create_surface_with_data(qh, None, 1, ())
     }
@@ -120,11 +159,21 @@ impl CompositorState {
         data: U,
     ) -> wl_surface::WlSurface
     where
-        D: Dispatch<wl_surface::WlSurface, SurfaceData<U>> + 'static,
+        D: Dispatch<wl_surface::WlSurface, SurfaceData<U>>
+            + Dispatch<WpFractionalScaleV1, FractionalScaleData<U>>
+            + 'static,
         U: Send + Sync + 'static,
     {
         let data = SurfaceData::new(parent_surface, scale_factor, data);
-        self.wl_compositor.create_surface(qh, data)
+        let surface = self.wl_compositor.create_surface(qh, data);
+
+        if let Some(manager) = &self.fractional_scale_manager {
+            let fractional_scale = FractionalScale::new(manager, &surface, qh);
+            let data = surface.data::<SurfaceData<U>>().unwrap();
+            data.inner.lock().unwrap().fractional_scale = Some(fractional_scale);
+        }
+
+        surface
     }
 }
 
@@ -134,6 +183,9 @@ pub struct SurfaceData<U> {
     /// The scale factor of the output with the highest scale factor.
     pub(crate) scale_factor: AtomicI32,
 
+    /// The preferred fractional scale, as a numerator over 120; `0` if none was received.
+    pub(crate) fractional_scale: AtomicU32,
+
     /// Parent surface used when creating subsurfaces.
     ///
     /// For top-level surfaces this is always `None`.
@@ -150,6 +202,7 @@ impl<U> SurfaceData<U> {
     pub fn new(parent_surface: Option<WlSurface>, scale_factor: i32, udata: U) -> Self {
         Self {
             scale_factor: AtomicI32::new(scale_factor),
+            fractional_scale: AtomicU32::new(0),
             parent_surface,
             inner: Default::default(),
             udata,
@@ -169,6 +222,18 @@ impl<U> SurfaceData<U> {
         self.scale_factor.load(Ordering::Relaxed)
     }
 
+    /// The preferred fractional scale of the surface, as the numerator of a fraction with a
+    /// denominator of `120`.
+    ///
+    /// This is [`None`] until the compositor sends a preferred scale through a fractional scale
+    /// object created for the surface.
+    pub fn fractional_scale(&self) -> Option<u32> {
+        match self.fractional_scale.load(Ordering::Relaxed) {
+            0 => None,
+            scale => Some(scale),
+        }
+    }
+
     /// The suggest transform for the surface.
     pub fn transform(&self) -> wl_output::Transform {
         self.inner.lock().unwrap().transform
@@ -206,11 +271,19 @@ struct SurfaceDataInner {
 
     /// A handle to the OutputInfo callback that dispatches scale updates.
     watcher: Option<ScaleWatcherHandle>,
+
+    /// The fractional scale object attached by [`CompositorState`].
+    fractional_scale: Option<FractionalScale>,
 }
 
 impl Default for SurfaceDataInner {
     fn default() -> Self {
-        Self { transform: wl_output::Transform::Normal, outputs: Vec::new(), watcher: None }
+        Self {
+            transform: wl_output::Transform::Normal,
+            outputs: Vec::new(),
+            watcher: None,
+            fractional_scale: None,
+        }
     }
 }
 
@@ -398,6 +471,21 @@ fn dispatch_surface_state_updates<D, U>(
     }
 }
 
+/// A rectangle with its origin at the top left corner.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect<T> {
+    pub x: T,
+    pub y: T,
+    pub width: T,
+    pub height: T,
+}
+
+impl<T> Rect<T> {
+    pub fn new(x: T, y: T, width: T, height: T) -> Self {
+        Self { x, y, width, height }
+    }
+}
+
 /// A trivial wrapper around a [`WlRegion`][wl_region::WlRegion].
 ///
 /// This destroys the region on drop.
@@ -500,3 +588,206 @@ where
         }
     }
 }
+
+/// Handler for the redraws scheduled by a [`FrameScheduler`].
+pub trait RedrawHandler: Sized {
+    /// The surface should be drawn and committed.
+    ///
+    /// [`FrameScheduler::request_redraw`] may be called from here before the commit of the drawn
+    /// frame to draw the next frame too, for example while an animation is running.
+    fn redraw(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
+        frame: FrameInfo,
+    );
+}
+
+/// Information about a frame to draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInfo {
+    /// The timestamp of the frame callback, in milliseconds with an undefined base.
+    pub time: u32,
+
+    /// When the previous frame was presented, if the scheduler uses presentation feedback.
+    ///
+    /// This is `None` before the first frame was presented, or if the previous frame was
+    /// discarded.
+    pub presented: Option<PresentedFrame>,
+}
+
+/// A frame presented to the user, as reported by `wp_presentation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresentedFrame {
+    /// The time the frame was presented, in the clock of [`PresentationTimeState`].
+    pub time: Duration,
+
+    /// The expected time until the next refresh of the output, if known.
+    pub refresh: Option<Duration>,
+
+    /// The vertical retrace counter of the output, or zero if it doesn't have one.
+    pub sequence: u64,
+}
+
+/// A redraw scheduler for a surface.
+///
+/// [`FrameScheduler::request_redraw`] requests a single frame callback however many times it is
+/// called until the callback is done, and then calls [`RedrawHandler::redraw`] for the surface.
+///
+/// The scheduler never commits the surface, the frame callback is requested for the next commit
+/// made by the application.
+///
+/// The scheduler can be cloned, all clones share the same state.
+#[derive(Debug, Clone)]
+pub struct FrameScheduler {
+    inner: Arc<FrameSchedulerInner>,
+}
+
+#[derive(Debug)]
+struct FrameSchedulerInner {
+    surface: wl_surface::WlSurface,
+    presentation: Option<wp_presentation::WpPresentation>,
+    state: Mutex<FrameSchedulerState>,
+}
+
+#[derive(Debug, Default)]
+struct FrameSchedulerState {
+    callback_pending: bool,
+    presented: Option<PresentedFrame>,
+}
+
+impl FrameScheduler {
+    /// Create a redraw scheduler for `surface`.
+    pub fn new(surface: wl_surface::WlSurface) -> Self {
+        Self::with_presentation_proxy(surface, None)
+    }
+
+    /// Create a redraw scheduler for `surface`, which requests presentation feedback for each
+    /// frame if `wp_presentation` is available.
+    pub fn with_presentation(
+        surface: wl_surface::WlSurface,
+        presentation: &PresentationTimeState,
+    ) -> Self {
+        Self::with_presentation_proxy(surface, presentation.wp_presentation().ok().cloned())
+    }
+
+    fn with_presentation_proxy(
+        surface: wl_surface::WlSurface,
+        presentation: Option<wp_presentation::WpPresentation>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(FrameSchedulerInner {
+                surface,
+                presentation,
+                state: Mutex::new(FrameSchedulerState::default()),
+            }),
+        }
+    }
+
+    /// Request a redraw of the surface on its next frame callback.
+    ///
+    /// This does nothing if a frame callback is already pending. Otherwise the callback is
+    /// requested for the next commit of the surface, which is left to the caller: the redraw only
+    /// happens once the surface is committed.
+    pub fn request_redraw<D>(&self, qh: &QueueHandle<D>)
+    where
+        D: Dispatch<wl_callback::WlCallback, RedrawCallbackData> + 'static,
+    {
+        let mut state = self.inner.state.lock().unwrap();
+        if mem::replace(&mut state.callback_pending, true) {
+            return;
+        }
+        drop(state);
+
+        self.inner.surface.frame(qh, RedrawCallbackData(self.clone()));
+    }
+
+    /// Whether a redraw was requested and its frame callback is not done yet.
+    pub fn is_redraw_pending(&self) -> bool {
+        self.inner.state.lock().unwrap().callback_pending
+    }
+
+    /// The last frame presented, if the scheduler uses presentation feedback.
+    pub fn last_presented(&self) -> Option<PresentedFrame> {
+        self.inner.state.lock().unwrap().presented
+    }
+
+    pub fn surface(&self) -> &wl_surface::WlSurface {
+        &self.inner.surface
+    }
+}
+
+/// The user data of the frame callbacks of a [`FrameScheduler`].
+#[derive(Debug)]
+pub struct RedrawCallbackData(FrameScheduler);
+
+impl<D> Dispatch2<wl_callback::WlCallback, D> for RedrawCallbackData
+where
+    D: RedrawHandler
+        + Dispatch<wp_presentation_feedback::WpPresentationFeedback, RedrawFeedbackData>
+        + 'static,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        _: &wl_callback::WlCallback,
+        event: wl_callback::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        match event {
+            wl_callback::Event::Done { callback_data } => {
+                let inner = &self.0.inner;
+                let mut scheduler_state = inner.state.lock().unwrap();
+                scheduler_state.callback_pending = false;
+                let presented = scheduler_state.presented;
+                drop(scheduler_state);
+
+                // The feedback applies to the next commit, which is the one of the drawn frame.
+                if let Some(presentation) = &inner.presentation {
+                    presentation.feedback(&inner.surface, qh, RedrawFeedbackData(self.0.clone()));
+                }
+
+                let frame = FrameInfo { time: callback_data, presented };
+                state.redraw(conn, qh, &inner.surface, frame);
+            }
+
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The user data of the presentation feedbacks of a [`FrameScheduler`].
+#[derive(Debug)]
+pub struct RedrawFeedbackData(FrameScheduler);
+
+impl<D> Dispatch2<wp_presentation_feedback::WpPresentationFeedback, D> for RedrawFeedbackData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &wp_presentation_feedback::WpPresentationFeedback,
+        event: wp_presentation_feedback::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        let presented = match event {
+            wp_presentation_feedback::Event::Presented {
+                tv_sec_hi,
+                tv_sec_lo,
+                tv_nsec,
+                refresh,
+                seq_hi,
+                seq_lo,
+                ..
+            } => Some(PresentedFrame {
+                time: Duration::new(((tv_sec_hi as u64) << 32) | (tv_sec_lo as u64), tv_nsec),
+                refresh: (refresh != 0).then(|| Duration::from_nanos(refresh.into())),
+                sequence: ((seq_hi as u64) << 32) | (seq_lo as u64),
+            }),
+            wp_presentation_feedback::Event::Discarded => None,
+            _ => return,
+        };
+        self.0.inner.state.lock().unwrap().presented = presented;
+    }
+}
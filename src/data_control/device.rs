@@ -0,0 +1,269 @@
+use std::sync::{Arc, Mutex};
+
+use crate::reexports::client::{
+    event_created_child, protocol::wl_seat::WlSeat, Connection, Dispatch, Proxy, QueueHandle,
+};
+use crate::reexports::protocols::ext::data_control::v1::client::{
+    ext_data_control_device_v1::{self, ExtDataControlDeviceV1},
+    ext_data_control_offer_v1::ExtDataControlOfferV1,
+};
+use crate::reexports::protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+    zwlr_data_control_offer_v1::ZwlrDataControlOfferV1,
+};
+
+use crate::dispatch2::Dispatch2;
+
+use super::{
+    offer::{DataControlOffer, DataControlOfferData},
+    DeviceProxy, OfferProxy,
+};
+
+pub trait DataControlDeviceHandler: Sized {
+    /// The selection of the seat changed.
+    ///
+    /// The new offer is available from [`DataControlDeviceData::selection_offer`].
+    fn selection(&mut self, conn: &Connection, qh: &QueueHandle<Self>, device: &DeviceProxy);
+
+    /// The primary selection of the seat changed.
+    ///
+    /// The new offer is available from [`DataControlDeviceData::primary_selection_offer`].
+    fn primary_selection(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        device: &DeviceProxy,
+    );
+
+    /// The device is no longer valid, usually because its seat was removed.
+    /// The [`DataControlDevice`] should be dropped.
+    fn finished(&mut self, conn: &Connection, qh: &QueueHandle<Self>, device: &DeviceProxy);
+}
+
+/// The data control device of a seat.
+///
+/// Dropping the device destroys it along with its offers.
+#[derive(Debug)]
+pub struct DataControlDevice {
+    pub(crate) device: DeviceProxy,
+}
+
+impl DataControlDevice {
+    /// Remove the selection.
+    pub fn unset_selection(&self) {
+        self.device.set_selection(None);
+    }
+
+    /// Remove the primary selection.
+    pub fn unset_primary_selection(&self) {
+        self.device.set_primary_selection(None);
+    }
+
+    /// Get the underlying data.
+    pub fn data(&self) -> &DataControlDeviceData {
+        match &self.device {
+            DeviceProxy::Ext(device) => device.data::<DataControlDeviceData>().unwrap(),
+            DeviceProxy::Wlr(device) => device.data::<DataControlDeviceData>().unwrap(),
+        }
+    }
+
+    pub fn inner(&self) -> &DeviceProxy {
+        &self.device
+    }
+}
+
+impl Drop for DataControlDevice {
+    fn drop(&mut self) {
+        // The offers are not destroyed with the device.
+        self.data().inner.lock().unwrap().destroy_offers();
+        self.device.destroy();
+    }
+}
+
+/// The events of the device, common to both protocols.
+enum DeviceEvent {
+    DataOffer(OfferProxy),
+    Selection(Option<OfferProxy>),
+    PrimarySelection(Option<OfferProxy>),
+    Finished,
+}
+
+impl<State> Dispatch2<ExtDataControlDeviceV1, State> for DataControlDeviceData
+where
+    State:
+        Dispatch<ExtDataControlOfferV1, DataControlOfferData> + DataControlDeviceHandler + 'static,
+{
+    event_created_child!(State, ExtDataControlDeviceV1, [
+        ext_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ExtDataControlOfferV1, DataControlOfferData::default())
+    ]);
+
+    fn event(
+        &self,
+        state: &mut State,
+        proxy: &ExtDataControlDeviceV1,
+        event: ext_data_control_device_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<State>,
+    ) {
+        use ext_data_control_device_v1::Event;
+        let event = match event {
+            Event::DataOffer { id } => DeviceEvent::DataOffer(OfferProxy::Ext(id)),
+            Event::Selection { id } => DeviceEvent::Selection(id.map(OfferProxy::Ext)),
+            Event::PrimarySelection { id } => {
+                DeviceEvent::PrimarySelection(id.map(OfferProxy::Ext))
+            }
+            Event::Finished => DeviceEvent::Finished,
+            _ => unreachable!(),
+        };
+
+        self.handle_event(state, &DeviceProxy::Ext(proxy.clone()), event, conn, qh);
+    }
+}
+
+impl<State> Dispatch2<ZwlrDataControlDeviceV1, State> for DataControlDeviceData
+where
+    State:
+        Dispatch<ZwlrDataControlOfferV1, DataControlOfferData> + DataControlDeviceHandler + 'static,
+{
+    event_created_child!(State, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, DataControlOfferData::default())
+    ]);
+
+    fn event(
+        &self,
+        state: &mut State,
+        proxy: &ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<State>,
+    ) {
+        use zwlr_data_control_device_v1::Event;
+        let event = match event {
+            Event::DataOffer { id } => DeviceEvent::DataOffer(OfferProxy::Wlr(id)),
+            Event::Selection { id } => DeviceEvent::Selection(id.map(OfferProxy::Wlr)),
+            Event::PrimarySelection { id } => {
+                DeviceEvent::PrimarySelection(id.map(OfferProxy::Wlr))
+            }
+            Event::Finished => DeviceEvent::Finished,
+            _ => unreachable!(),
+        };
+
+        self.handle_event(state, &DeviceProxy::Wlr(proxy.clone()), event, conn, qh);
+    }
+}
+
+/// The user data associated with the data control device.
+#[derive(Debug)]
+pub struct DataControlDeviceData {
+    /// The seat associated with this device.
+    seat: WlSeat,
+    /// The inner mutable storage.
+    inner: Arc<Mutex<DataControlDeviceDataInner>>,
+}
+
+impl DataControlDeviceData {
+    pub(crate) fn new(seat: WlSeat) -> Self {
+        Self { seat, inner: Default::default() }
+    }
+
+    /// The seat used to create this device.
+    pub fn seat(&self) -> &WlSeat {
+        &self.seat
+    }
+
+    /// The offer of the selection.
+    pub fn selection_offer(&self) -> Option<DataControlOffer> {
+        let inner = self.inner.lock().unwrap();
+        inner.selection.as_ref().map(|offer| DataControlOffer { offer: offer.clone() })
+    }
+
+    /// The offer of the primary selection.
+    pub fn primary_selection_offer(&self) -> Option<DataControlOffer> {
+        let inner = self.inner.lock().unwrap();
+        inner.primary_selection.as_ref().map(|offer| DataControlOffer { offer: offer.clone() })
+    }
+
+    fn handle_event<State>(
+        &self,
+        state: &mut State,
+        device: &DeviceProxy,
+        event: DeviceEvent,
+        conn: &Connection,
+        qh: &QueueHandle<State>,
+    ) where
+        State: DataControlDeviceHandler,
+    {
+        let mut inner = self.inner.lock().unwrap();
+        match event {
+            DeviceEvent::DataOffer(offer) => {
+                inner.pending_offers.push(offer);
+            }
+            DeviceEvent::Selection(offer) => {
+                let offer = inner.take_pending(offer);
+                let previous = std::mem::replace(&mut inner.selection, offer);
+                inner.destroy_unused(previous);
+
+                // Release the user data lock before calling into user.
+                drop(inner);
+                state.selection(conn, qh, device);
+            }
+            DeviceEvent::PrimarySelection(offer) => {
+                let offer = inner.take_pending(offer);
+                let previous = std::mem::replace(&mut inner.primary_selection, offer);
+                inner.destroy_unused(previous);
+
+                // Release the user data lock before calling into user.
+                drop(inner);
+                state.primary_selection(conn, qh, device);
+            }
+            DeviceEvent::Finished => {
+                inner.destroy_offers();
+
+                // Release the user data lock before calling into user.
+                drop(inner);
+                state.finished(conn, qh, device);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct DataControlDeviceDataInner {
+    /// The offer of the selection, valid until the next `selection` event.
+    selection: Option<OfferProxy>,
+    /// The offer of the primary selection, valid until the next `primary_selection` event.
+    primary_selection: Option<OfferProxy>,
+    /// The offers introduced with `data_offer`, not used by a selection yet.
+    pending_offers: Vec<OfferProxy>,
+}
+
+impl DataControlDeviceDataInner {
+    /// Remove `offer` from the pending offers.
+    fn take_pending(&mut self, offer: Option<OfferProxy>) -> Option<OfferProxy> {
+        let offer = offer?;
+        self.pending_offers.retain(|pending| *pending != offer);
+        Some(offer)
+    }
+
+    /// Destroy all the offers of the device.
+    fn destroy_offers(&mut self) {
+        for offer in self.pending_offers.drain(..) {
+            offer.destroy();
+        }
+        let selections = [self.selection.take(), self.primary_selection.take()];
+        for offer in selections {
+            self.destroy_unused(offer);
+        }
+    }
+
+    /// Destroy an offer replaced by a new one, unless the other selection still uses it.
+    fn destroy_unused(&mut self, offer: Option<OfferProxy>) {
+        if let Some(offer) = offer {
+            if self.selection.as_ref() != Some(&offer)
+                && self.primary_selection.as_ref() != Some(&offer)
+            {
+                offer.destroy();
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Access to the selections of a seat without focus, for clipboard managers.
+//!
+//! This uses `ext_data_control_v1`, or `zwlr_data_control_v1` if the compositor doesn't support
+//! it. Both protocols are handled by the same types, and the objects of the protocol in use are
+//! wrapped by [`DeviceProxy`], [`SourceProxy`] and [`OfferProxy`].
+
+use crate::dispatch2::Dispatch2;
+use crate::globals::GlobalData;
+use crate::reexports::client::{
+    globals::{BindError, GlobalList},
+    protocol::wl_seat::WlSeat,
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use crate::reexports::protocols::ext::data_control::v1::client::{
+    ext_data_control_device_v1::ExtDataControlDeviceV1,
+    ext_data_control_manager_v1::{self, ExtDataControlManagerV1},
+    ext_data_control_offer_v1::ExtDataControlOfferV1,
+    ext_data_control_source_v1::ExtDataControlSourceV1,
+};
+use crate::reexports::protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+    zwlr_data_control_manager_v1::{self, ZwlrDataControlManagerV1},
+    zwlr_data_control_offer_v1::ZwlrDataControlOfferV1,
+    zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
+};
+
+pub mod device;
+pub mod offer;
+pub mod source;
+
+use device::{DataControlDevice, DataControlDeviceData};
+use source::DataControlSource;
+
+/// The manager of the protocol in use.
+#[derive(Debug)]
+enum ManagerProxy {
+    Ext(ExtDataControlManagerV1),
+    Wlr(ZwlrDataControlManagerV1),
+}
+
+/// A data control device, of either protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceProxy {
+    Ext(ExtDataControlDeviceV1),
+    Wlr(ZwlrDataControlDeviceV1),
+}
+
+/// A data control source, of either protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceProxy {
+    Ext(ExtDataControlSourceV1),
+    Wlr(ZwlrDataControlSourceV1),
+}
+
+/// A data control offer, of either protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OfferProxy {
+    Ext(ExtDataControlOfferV1),
+    Wlr(ZwlrDataControlOfferV1),
+}
+
+#[derive(Debug)]
+pub struct DataControlState {
+    manager: ManagerProxy,
+}
+
+impl DataControlState {
+    /// Bind `ext_data_control_manager_v1`, or `zwlr_data_control_manager_v1` if it is not present.
+    pub fn bind<State>(globals: &GlobalList, qh: &QueueHandle<State>) -> Result<Self, BindError>
+    where
+        State: Dispatch<ExtDataControlManagerV1, GlobalData, State>
+            + Dispatch<ZwlrDataControlManagerV1, GlobalData, State>
+            + 'static,
+    {
+        let manager = match globals.bind(qh, 1..=1, GlobalData) {
+            Ok(manager) => ManagerProxy::Ext(manager),
+            Err(_) => ManagerProxy::Wlr(globals.bind(qh, 1..=2, GlobalData)?),
+        };
+
+        Ok(Self { manager })
+    }
+
+    /// Whether the primary selection can be accessed.
+    pub fn has_primary_selection(&self) -> bool {
+        match &self.manager {
+            ManagerProxy::Ext(_) => true,
+            ManagerProxy::Wlr(manager) => manager.version() >= 2,
+        }
+    }
+
+    /// Create a source, which may be used for either selection.
+    pub fn create_source<State, I, T>(
+        &self,
+        qh: &QueueHandle<State>,
+        mime_types: I,
+    ) -> DataControlSource
+    where
+        State: Dispatch<ExtDataControlSourceV1, GlobalData, State>
+            + Dispatch<ZwlrDataControlSourceV1, GlobalData, State>
+            + 'static,
+        I: IntoIterator<Item = T>,
+        T: ToString,
+    {
+        let source = match &self.manager {
+            ManagerProxy::Ext(manager) => {
+                SourceProxy::Ext(manager.create_data_source(qh, GlobalData))
+            }
+            ManagerProxy::Wlr(manager) => {
+                SourceProxy::Wlr(manager.create_data_source(qh, GlobalData))
+            }
+        };
+
+        for mime_type in mime_types {
+            source.offer(mime_type.to_string());
+        }
+
+        DataControlSource::new(source)
+    }
+
+    /// Get the data control device of the given seat.
+    pub fn get_data_device<State>(
+        &self,
+        qh: &QueueHandle<State>,
+        seat: &WlSeat,
+    ) -> DataControlDevice
+    where
+        State: Dispatch<ExtDataControlDeviceV1, DataControlDeviceData, State>
+            + Dispatch<ZwlrDataControlDeviceV1, DataControlDeviceData, State>
+            + 'static,
+    {
+        let data = DataControlDeviceData::new(seat.clone());
+        let device = match &self.manager {
+            ManagerProxy::Ext(manager) => DeviceProxy::Ext(manager.get_data_device(seat, qh, data)),
+            ManagerProxy::Wlr(manager) => DeviceProxy::Wlr(manager.get_data_device(seat, qh, data)),
+        };
+
+        DataControlDevice { device }
+    }
+}
+
+impl Drop for DataControlState {
+    fn drop(&mut self) {
+        match &self.manager {
+            ManagerProxy::Ext(manager) => manager.destroy(),
+            ManagerProxy::Wlr(manager) => manager.destroy(),
+        }
+    }
+}
+
+impl DeviceProxy {
+    fn set_selection(&self, source: Option<&SourceProxy>) {
+        match (self, source) {
+            (DeviceProxy::Ext(device), Some(SourceProxy::Ext(source))) => {
+                device.set_selection(Some(source))
+            }
+            (DeviceProxy::Wlr(device), Some(SourceProxy::Wlr(source))) => {
+                device.set_selection(Some(source))
+            }
+            (DeviceProxy::Ext(device), None) => device.set_selection(None),
+            (DeviceProxy::Wlr(device), None) => device.set_selection(None),
+            _ => log::warn!(target: "sctk", "data control source of another protocol"),
+        }
+    }
+
+    fn set_primary_selection(&self, source: Option<&SourceProxy>) {
+        match (self, source) {
+            (DeviceProxy::Ext(device), Some(SourceProxy::Ext(source))) => {
+                device.set_primary_selection(Some(source))
+            }
+            (DeviceProxy::Wlr(device), Some(SourceProxy::Wlr(source))) => {
+                if device.version() >= 2 {
+                    device.set_primary_selection(Some(source))
+                }
+            }
+            (DeviceProxy::Ext(device), None) => device.set_primary_selection(None),
+            (DeviceProxy::Wlr(device), None) => {
+                if device.version() >= 2 {
+                    device.set_primary_selection(None)
+                }
+            }
+            _ => log::warn!(target: "sctk", "data control source of another protocol"),
+        }
+    }
+
+    fn destroy(&self) {
+        match self {
+            DeviceProxy::Ext(device) => device.destroy(),
+            DeviceProxy::Wlr(device) => device.destroy(),
+        }
+    }
+}
+
+impl SourceProxy {
+    fn offer(&self, mime_type: String) {
+        match self {
+            SourceProxy::Ext(source) => source.offer(mime_type),
+            SourceProxy::Wlr(source) => source.offer(mime_type),
+        }
+    }
+
+    fn destroy(&self) {
+        match self {
+            SourceProxy::Ext(source) => source.destroy(),
+            SourceProxy::Wlr(source) => source.destroy(),
+        }
+    }
+}
+
+impl OfferProxy {
+    fn destroy(&self) {
+        match self {
+            OfferProxy::Ext(offer) => offer.destroy(),
+            OfferProxy::Wlr(offer) => offer.destroy(),
+        }
+    }
+}
+
+impl<D> Dispatch2<ExtDataControlManagerV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ExtDataControlManagerV1,
+        _: ext_data_control_manager_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("ext_data_control_manager_v1 has no events")
+    }
+}
+
+impl<D> Dispatch2<ZwlrDataControlManagerV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ZwlrDataControlManagerV1,
+        _: zwlr_data_control_manager_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("zwlr_data_control_manager_v1 has no events")
+    }
+}
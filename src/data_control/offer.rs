@@ -0,0 +1,123 @@
+use std::{
+    os::unix::io::{BorrowedFd, OwnedFd},
+    sync::Mutex,
+};
+
+use crate::reexports::client::{Connection, Proxy, QueueHandle};
+use crate::reexports::protocols::ext::data_control::v1::client::ext_data_control_offer_v1::{
+    self, ExtDataControlOfferV1,
+};
+use crate::reexports::protocols_wlr::data_control::v1::client::zwlr_data_control_offer_v1::{
+    self, ZwlrDataControlOfferV1,
+};
+
+use crate::data_device_manager::{
+    data_offer::{self, ReceiveOffer},
+    mime::{negotiate, DataFormat},
+    ReadPipe,
+};
+use crate::dispatch2::Dispatch2;
+
+use super::OfferProxy;
+
+/// The offer of a selection, received through a data control device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataControlOffer {
+    pub(crate) offer: OfferProxy,
+}
+
+impl DataControlOffer {
+    /// Inspect the mime types available on the given offer.
+    pub fn with_mime_types<T, F: Fn(&[String]) -> T>(&self, callback: F) -> T {
+        let data = match &self.offer {
+            OfferProxy::Ext(offer) => offer.data::<DataControlOfferData>(),
+            OfferProxy::Wlr(offer) => offer.data::<DataControlOfferData>(),
+        };
+        let mime_types = data.unwrap().mimes.lock().unwrap();
+        callback(mime_types.as_ref())
+    }
+
+    /// Choose the mime type to receive, from the formats accepted in order of preference.
+    pub fn negotiate<'a>(&self, accepted: &'a [DataFormat]) -> Option<(&'a DataFormat, String)> {
+        self.with_mime_types(|offered| negotiate(accepted, offered))
+    }
+
+    /// Request to receive the data of a given mime type.
+    ///
+    /// You can call this function several times.
+    ///
+    /// Note that you should *not* read the contents right away in a
+    /// blocking way, as you may deadlock your application doing so.
+    /// At least make sure you flush your events to the server before
+    /// doing so.
+    ///
+    /// Fails if too many file descriptors were already open and a pipe
+    /// could not be created.
+    pub fn receive(&self, mime_type: String) -> std::io::Result<ReadPipe> {
+        data_offer::receive(&self.offer, mime_type)
+    }
+
+    /// Request to receive the data of a given mime type, writen to `writefd`.
+    ///
+    /// The provided file destructor must be a valid FD for writing, and will be closed
+    /// once the contents are written.
+    pub fn receive_to_fd(&self, mime_type: String, writefd: OwnedFd) {
+        data_offer::receive_to_fd(&self.offer, mime_type, writefd);
+    }
+
+    /// The underlying wayland object.
+    pub fn inner(&self) -> &OfferProxy {
+        &self.offer
+    }
+}
+
+impl ReceiveOffer for OfferProxy {
+    fn receive_fd(&self, mime_type: String, writefd: BorrowedFd<'_>) {
+        match self {
+            OfferProxy::Ext(offer) => offer.receive(mime_type, writefd),
+            OfferProxy::Wlr(offer) => offer.receive(mime_type, writefd),
+        }
+    }
+}
+
+impl<State> Dispatch2<ExtDataControlOfferV1, State> for DataControlOfferData {
+    fn event(
+        &self,
+        _: &mut State,
+        _: &ExtDataControlOfferV1,
+        event: ext_data_control_offer_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<State>,
+    ) {
+        match event {
+            ext_data_control_offer_v1::Event::Offer { mime_type } => {
+                self.mimes.lock().unwrap().push(mime_type);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<State> Dispatch2<ZwlrDataControlOfferV1, State> for DataControlOfferData {
+    fn event(
+        &self,
+        _: &mut State,
+        _: &ZwlrDataControlOfferV1,
+        event: zwlr_data_control_offer_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<State>,
+    ) {
+        match event {
+            zwlr_data_control_offer_v1::Event::Offer { mime_type } => {
+                self.mimes.lock().unwrap().push(mime_type);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The data associated with a data control offer.
+#[derive(Debug, Default)]
+pub struct DataControlOfferData {
+    mimes: Mutex<Vec<String>>,
+}
@@ -0,0 +1,113 @@
+use crate::reexports::client::{Connection, QueueHandle};
+use crate::reexports::protocols::ext::data_control::v1::client::ext_data_control_source_v1::{
+    self, ExtDataControlSourceV1,
+};
+use crate::reexports::protocols_wlr::data_control::v1::client::zwlr_data_control_source_v1::{
+    self, ZwlrDataControlSourceV1,
+};
+use crate::{data_device_manager::WritePipe, dispatch2::Dispatch2, globals::GlobalData};
+
+use super::{device::DataControlDevice, SourceProxy};
+
+/// Handler trait for `DataControlSource` events.
+///
+/// The functions defined in this trait are called as source events are received from the compositor.
+pub trait DataControlSourceHandler: Sized {
+    /// A client has requested the data for this source to be sent.
+    /// Send the data, then close the fd.
+    fn send_request(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        source: &SourceProxy,
+        mime: String,
+        write_pipe: WritePipe,
+    );
+
+    /// The data source is no longer valid
+    /// Cleanup & destroy this resource
+    fn cancelled(&mut self, conn: &Connection, qh: &QueueHandle<Self>, source: &SourceProxy);
+}
+
+/// A source for the selections, owned by the client.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DataControlSource {
+    source: SourceProxy,
+}
+
+impl DataControlSource {
+    pub(crate) fn new(source: SourceProxy) -> Self {
+        Self { source }
+    }
+
+    /// Set the selection of the seat of `device` to this source.
+    pub fn set_selection(&self, device: &DataControlDevice) {
+        device.device.set_selection(Some(&self.source));
+    }
+
+    /// Set the primary selection of the seat of `device` to this source.
+    ///
+    /// This is ignored if the primary selection is not supported.
+    pub fn set_primary_selection(&self, device: &DataControlDevice) {
+        device.device.set_primary_selection(Some(&self.source));
+    }
+
+    /// The underlying wayland object.
+    pub fn inner(&self) -> &SourceProxy {
+        &self.source
+    }
+}
+
+impl Drop for DataControlSource {
+    fn drop(&mut self) {
+        self.source.destroy();
+    }
+}
+
+impl<State> Dispatch2<ExtDataControlSourceV1, State> for GlobalData
+where
+    State: DataControlSourceHandler,
+{
+    fn event(
+        &self,
+        state: &mut State,
+        proxy: &ExtDataControlSourceV1,
+        event: ext_data_control_source_v1::Event,
+        conn: &Connection,
+        qhandle: &QueueHandle<State>,
+    ) {
+        let source = SourceProxy::Ext(proxy.clone());
+        match event {
+            ext_data_control_source_v1::Event::Send { mime_type, fd } => {
+                state.send_request(conn, qhandle, &source, mime_type, fd.into())
+            }
+            ext_data_control_source_v1::Event::Cancelled => state.cancelled(conn, qhandle, &source),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<State> Dispatch2<ZwlrDataControlSourceV1, State> for GlobalData
+where
+    State: DataControlSourceHandler,
+{
+    fn event(
+        &self,
+        state: &mut State,
+        proxy: &ZwlrDataControlSourceV1,
+        event: zwlr_data_control_source_v1::Event,
+        conn: &Connection,
+        qhandle: &QueueHandle<State>,
+    ) {
+        let source = SourceProxy::Wlr(proxy.clone());
+        match event {
+            zwlr_data_control_source_v1::Event::Send { mime_type, fd } => {
+                state.send_request(conn, qhandle, &source, mime_type, fd.into())
+            }
+            zwlr_data_control_source_v1::Event::Cancelled => {
+                state.cancelled(conn, qhandle, &source)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     ops::{Deref, DerefMut},
-    os::unix::prelude::{AsFd, OwnedFd},
+    os::unix::prelude::{AsFd, BorrowedFd, OwnedFd},
     sync::{Arc, Mutex},
 };
 
@@ -16,7 +16,10 @@ use crate::reexports::client::{
     Connection, Proxy, QueueHandle,
 };
 
-use super::ReadPipe;
+use super::{
+    mime::{negotiate, DataFormat},
+    ReadPipe,
+};
 
 /// Handler trait for DataOffer events.
 ///
@@ -100,6 +103,11 @@ impl DragOffer {
         callback(mime_types)
     }
 
+    /// Choose the mime type to receive, from the formats accepted in order of preference.
+    pub fn negotiate<'a>(&self, accepted: &'a [DataFormat]) -> Option<(&'a DataFormat, String)> {
+        self.with_mime_types(|offered| negotiate(accepted, offered))
+    }
+
     /// Set the accepted and preferred drag and drop actions.
     /// This request determines the final result of the drag-and-drop operation.
     /// If the end result is that no action is accepted, the drag source will receive wl_data_source.cancelled.
@@ -161,6 +169,11 @@ impl SelectionOffer {
         callback(mime_types)
     }
 
+    /// Choose the mime type to receive, from the formats accepted in order of preference.
+    pub fn negotiate<'a>(&self, accepted: &'a [DataFormat]) -> Option<(&'a DataFormat, String)> {
+        self.with_mime_types(|offered| negotiate(accepted, offered))
+    }
+
     pub fn receive(&self, mime_type: String) -> Result<ReadPipe, DataOfferError> {
         receive(&self.data_offer, mime_type).map_err(DataOfferError::Io)
     }
@@ -423,6 +436,21 @@ impl PartialEq for UndeterminedOffer {
     }
 }
 
+/// An offer of data, such as a [`WlDataOffer`].
+///
+/// This lets [`receive`] and [`receive_to_fd`] be used with the offers of every protocol
+/// transferring data.
+pub trait ReceiveOffer {
+    /// Request the data of a given mime type to be written to `writefd`.
+    fn receive_fd(&self, mime_type: String, writefd: BorrowedFd<'_>);
+}
+
+impl ReceiveOffer for WlDataOffer {
+    fn receive_fd(&self, mime_type: String, writefd: BorrowedFd<'_>) {
+        self.receive(mime_type, writefd);
+    }
+}
+
 /// Request to receive the data of a given mime type.
 ///
 /// You can do this several times, as a reaction to motion of
@@ -436,7 +464,7 @@ impl PartialEq for UndeterminedOffer {
 ///
 /// Fails if too many file descriptors were already open and a pipe
 /// could not be created.
-pub fn receive(offer: &WlDataOffer, mime_type: String) -> std::io::Result<ReadPipe> {
+pub fn receive(offer: &impl ReceiveOffer, mime_type: String) -> std::io::Result<ReadPipe> {
     use rustix::pipe::{pipe_with, PipeFlags};
     // create a pipe
     let (readfd, writefd) = pipe_with(PipeFlags::CLOEXEC)?;
@@ -459,6 +487,6 @@ pub fn receive(offer: &WlDataOffer, mime_type: String) -> std::io::Result<ReadPi
 ///
 /// The provided file destructor must be a valid FD for writing, and will be closed
 /// once the contents are written.
-pub fn receive_to_fd(offer: &WlDataOffer, mime_type: String, writefd: OwnedFd) {
-    offer.receive(mime_type, writefd.as_fd());
+pub fn receive_to_fd(offer: &impl ReceiveOffer, mime_type: String, writefd: OwnedFd) {
+    offer.receive_fd(mime_type, writefd.as_fd());
 }
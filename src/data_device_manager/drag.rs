@@ -0,0 +1,411 @@
+//! Drag and drop sources managed for the whole drag.
+//!
+//! A [`DragSession`] owns the source of a drag and the surface of its icon. The icon is drawn from
+//! a [`DragIcon`] image into a shared memory buffer, and the end of the drag is reported once to
+//! [`DragHandler::drag_finished`] with a [`DragOutcome`].
+
+use std::sync::Mutex;
+
+use crate::reexports::client::{
+    protocol::{
+        wl_data_device_manager::DndAction,
+        wl_data_source::{self, WlDataSource},
+        wl_shm,
+        wl_surface::WlSurface,
+    },
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols::wp::fractional_scale::v1::client::wp_fractional_scale_v1::WpFractionalScaleV1;
+
+use crate::{
+    compositor::{CompositorState, SurfaceData},
+    dispatch2::Dispatch2,
+    fractional_scale::FractionalScaleData,
+    shm::{
+        slot::{Buffer, CreateBufferError, SlotPool},
+        CreatePoolError, Shm,
+    },
+};
+
+use super::{data_device::DataDevice, data_source::DragSource, DataDeviceManagerState, WritePipe};
+
+/// Handler trait for the events of a [`DragSession`].
+pub trait DragHandler: Sized {
+    /// The drop target requested the data as `mime`.
+    /// Send the data, then close the pipe.
+    fn drag_send_request(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        source: &WlDataSource,
+        mime: String,
+        write_pipe: WritePipe,
+    );
+
+    /// The drag ended, and the session may be dropped.
+    ///
+    /// When the outcome is [`DragOutcome::Moved`], the source should delete the data.
+    fn drag_finished(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        source: &WlDataSource,
+        outcome: DragOutcome,
+    );
+}
+
+/// How a drag ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DragOutcome {
+    /// The data was copied by the drop target.
+    Copied,
+    /// The data was moved by the drop target.
+    Moved,
+    /// The drag was cancelled, or the drop target did not accept it.
+    Cancelled,
+}
+
+/// An image used as the icon of a drag.
+///
+/// The pixels are premultiplied [`Argb8888`](wl_shm::Format::Argb8888), without padding between
+/// rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DragIcon {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+
+    /// The scale the image was drawn for.
+    pub scale: u32,
+
+    /// The point of the icon under the pointer, in surface coordinates.
+    pub hotspot: (i32, i32),
+}
+
+impl DragIcon {
+    /// Create an icon drawn at a scale of `1`.
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>, hotspot: (i32, i32)) -> Self {
+        Self { width, height, pixels, scale: 1, hotspot }
+    }
+}
+
+/// An error that may occur when setting the icon of a drag.
+#[derive(Debug, thiserror::Error)]
+pub enum DragIconError {
+    #[error("the icon pixels do not match its size")]
+    InvalidImage,
+
+    #[error(transparent)]
+    CreatePool(#[from] CreatePoolError),
+
+    #[error(transparent)]
+    CreateBuffer(#[from] CreateBufferError),
+}
+
+impl DataDeviceManagerState {
+    /// Create a drag and drop source managed by a [`DragSession`].
+    pub fn create_drag_session<D, T: ToString>(
+        &self,
+        qh: &QueueHandle<D>,
+        mime_types: impl IntoIterator<Item = T>,
+        dnd_actions: DndAction,
+    ) -> DragSession
+    where
+        D: Dispatch<WlDataSource, DragSourceData> + 'static,
+    {
+        let source = self.data_device_manager().create_data_source(qh, DragSourceData::default());
+
+        for mime in mime_types {
+            source.offer(mime.to_string());
+        }
+
+        if source.version() >= 3 {
+            source.set_actions(dnd_actions);
+        }
+
+        DragSession { source: DragSource { inner: source }, icon: None, started: false }
+    }
+}
+
+/// A drag and drop operation with its icon.
+///
+/// The drag is cancelled if the session is dropped before it finishes.
+#[derive(Debug)]
+pub struct DragSession {
+    source: DragSource,
+    icon: Option<IconSurface>,
+    started: bool,
+}
+
+impl DragSession {
+    /// Set the icon of the drag, drawn for a buffer scale of `scale`.
+    ///
+    /// The scale is usually the one of the surface the drag starts from, as returned by
+    /// [`SurfaceData::scale_factor`] for the origin surface. The icon surface reports its own scale
+    /// changes to [`CompositorHandler::scale_factor_changed`] once it enters an output, which may
+    /// be passed to [`DragSession::set_scale`] during the drag.
+    ///
+    /// Once the drag is started, this only replaces the image of the icon set before, since a
+    /// drag can't gain an icon.
+    ///
+    /// [`CompositorHandler::scale_factor_changed`]: crate::compositor::CompositorHandler::scale_factor_changed
+    pub fn set_icon<D>(
+        &mut self,
+        compositor: &CompositorState,
+        shm: &Shm,
+        qh: &QueueHandle<D>,
+        icon: DragIcon,
+        scale: i32,
+    ) -> Result<(), DragIconError>
+    where
+        D: Dispatch<WlSurface, SurfaceData<()>>
+            + Dispatch<WpFractionalScaleV1, FractionalScaleData<()>>
+            + 'static,
+    {
+        if icon.pixels.len() != icon.width as usize * icon.height as usize * 4 {
+            return Err(DragIconError::InvalidImage);
+        }
+
+        if self.started && self.icon.is_none() {
+            return Ok(());
+        }
+
+        let mut offset = (0, 0);
+        if let Some(previous) = self.icon.as_mut() {
+            offset = (
+                previous.image.hotspot.0 - icon.hotspot.0,
+                previous.image.hotspot.1 - icon.hotspot.1,
+            );
+            previous.image = icon;
+            previous.scale = scale;
+        } else {
+            self.icon = Some(IconSurface {
+                surface: compositor.create_surface(qh),
+                pool: SlotPool::new(icon.pixels.len(), shm)?,
+                buffer: None,
+                image: icon,
+                scale,
+            });
+        }
+
+        let icon_surface = self.icon.as_mut().unwrap();
+        icon_surface.draw()?;
+
+        if self.started {
+            icon_surface.attach(offset);
+            icon_surface.surface.commit();
+        }
+
+        Ok(())
+    }
+
+    /// Start the drag from `origin`, as a response to the pointer or touch event with `serial`.
+    pub fn start(&mut self, device: &DataDevice, origin: &WlSurface, serial: u32) {
+        let icon = self.icon.as_ref();
+        self.source.start_drag(device, origin, icon.map(|icon| &icon.surface), serial);
+        self.started = true;
+
+        // The icon is placed at the pointer, so the hotspot is moved there.
+        if let Some(icon) = icon {
+            icon.attach((-icon.image.hotspot.0, -icon.image.hotspot.1));
+            icon.surface.commit();
+        }
+    }
+
+    /// Move the icon so the point `hotspot` of the icon is under the pointer.
+    pub fn set_hotspot(&mut self, hotspot: (i32, i32)) {
+        let started = self.started;
+        let icon = match self.icon.as_mut() {
+            Some(icon) => icon,
+            None => return,
+        };
+
+        let offset = (icon.image.hotspot.0 - hotspot.0, icon.image.hotspot.1 - hotspot.1);
+        icon.image.hotspot = hotspot;
+        if started {
+            icon.attach(offset);
+            icon.surface.commit();
+        }
+    }
+
+    /// Redraw the icon for a buffer scale of `scale`.
+    ///
+    /// This is meant for [`CompositorHandler::scale_factor_changed`] calls for the surface
+    /// returned by [`DragSession::icon_surface`].
+    ///
+    /// [`CompositorHandler::scale_factor_changed`]: crate::compositor::CompositorHandler::scale_factor_changed
+    pub fn set_scale(&mut self, scale: i32) -> Result<(), DragIconError> {
+        let started = self.started;
+        let icon = match self.icon.as_mut() {
+            Some(icon) if icon.scale != scale => icon,
+            _ => return Ok(()),
+        };
+
+        icon.scale = scale;
+        icon.draw()?;
+        if started {
+            icon.attach((0, 0));
+            icon.surface.commit();
+        }
+
+        Ok(())
+    }
+
+    /// The surface of the icon, if an icon is set.
+    pub fn icon_surface(&self) -> Option<&WlSurface> {
+        self.icon.as_ref().map(|icon| &icon.surface)
+    }
+
+    /// The source of the drag.
+    pub fn source(&self) -> &WlDataSource {
+        self.source.inner()
+    }
+}
+
+#[derive(Debug)]
+struct IconSurface {
+    surface: WlSurface,
+    pool: SlotPool,
+    buffer: Option<Buffer>,
+    image: DragIcon,
+    scale: i32,
+}
+
+impl IconSurface {
+    /// Draw the image into a new buffer, scaled to the buffer scale.
+    fn draw(&mut self) -> Result<(), DragIconError> {
+        let image = &self.image;
+        let scale = self.scale.max(1) as u32;
+        let image_scale = image.scale.max(1);
+        let width = image.width.div_ceil(image_scale) * scale;
+        let height = image.height.div_ceil(image_scale) * scale;
+
+        let (buffer, canvas) = self.pool.create_buffer(
+            width as i32,
+            height as i32,
+            width as i32 * 4,
+            wl_shm::Format::Argb8888,
+        )?;
+
+        // Nearest neighbour scaling, which is exact when the scales are the same.
+        for (y, row) in canvas.chunks_exact_mut(width as usize * 4).enumerate() {
+            let src_y = (y as u32 * image_scale / scale).min(image.height.saturating_sub(1));
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let src_x = (x as u32 * image_scale / scale).min(image.width.saturating_sub(1));
+                let offset = (src_y * image.width + src_x) as usize * 4;
+                match image.pixels.get(offset..offset + 4) {
+                    Some(src) => pixel.copy_from_slice(src),
+                    None => pixel.fill(0),
+                }
+            }
+        }
+
+        if self.surface.version() >= 3 {
+            self.surface.set_buffer_scale(scale as i32);
+        }
+        self.buffer = Some(buffer);
+        Ok(())
+    }
+
+    /// Attach the buffer, moving the surface by `offset`.
+    fn attach(&self, offset: (i32, i32)) {
+        let buffer = match self.buffer.as_ref() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+
+        // Attaching the same buffer again is fine, even if the compositor didn't release it yet.
+        let _ = buffer.activate();
+        if self.surface.version() >= 5 {
+            self.surface.attach(Some(buffer.wl_buffer()), 0, 0);
+            if offset != (0, 0) {
+                self.surface.offset(offset.0, offset.1);
+            }
+        } else {
+            self.surface.attach(Some(buffer.wl_buffer()), offset.0, offset.1);
+        }
+        // `damage_buffer` was added in version 4.
+        if self.surface.version() >= 4 {
+            self.surface.damage_buffer(0, 0, i32::MAX, i32::MAX);
+        } else {
+            self.surface.damage(0, 0, i32::MAX, i32::MAX);
+        }
+    }
+}
+
+impl Drop for IconSurface {
+    fn drop(&mut self) {
+        self.surface.destroy();
+    }
+}
+
+/// The user data of the source of a [`DragSession`].
+#[derive(Debug, Default)]
+pub struct DragSourceData {
+    inner: Mutex<DragSourceInner>,
+}
+
+#[derive(Debug, Default)]
+struct DragSourceInner {
+    /// The latest action selected by the compositor.
+    action: Option<DndAction>,
+    /// Whether the data was requested, used when the source can't know how the drag ended.
+    sent: bool,
+    finished: bool,
+}
+
+impl DragSourceData {
+    /// Mark the drag as finished, returning false if it already was.
+    fn finish(&self) -> bool {
+        !std::mem::replace(&mut self.inner.lock().unwrap().finished, true)
+    }
+}
+
+impl<D> Dispatch2<WlDataSource, D> for DragSourceData
+where
+    D: DragHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        source: &WlDataSource,
+        event: wl_data_source::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        match event {
+            wl_data_source::Event::Send { mime_type, fd } => {
+                self.inner.lock().unwrap().sent = true;
+                state.drag_send_request(conn, qh, source, mime_type, fd.into());
+            }
+            wl_data_source::Event::Action { dnd_action: WEnum::Value(action) } => {
+                self.inner.lock().unwrap().action = Some(action);
+            }
+            wl_data_source::Event::DndFinished => {
+                let action = self.inner.lock().unwrap().action;
+                let outcome = match action {
+                    Some(DndAction::Move) => DragOutcome::Moved,
+                    _ => DragOutcome::Copied,
+                };
+
+                if self.finish() {
+                    state.drag_finished(conn, qh, source, outcome);
+                }
+            }
+            wl_data_source::Event::Cancelled => {
+                // Before version 3, a drop is only known from the data being requested.
+                let sent = self.inner.lock().unwrap().sent;
+                let outcome = if source.version() < 3 && sent {
+                    DragOutcome::Copied
+                } else {
+                    DragOutcome::Cancelled
+                };
+
+                if self.finish() {
+                    state.drag_finished(conn, qh, source, outcome);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Negotiation and conversion of the mime types of offers and sources.
+//!
+//! The same data is often offered under several mime types, especially text, for which X11
+//! clients use their own names. A [`DataFormat`] groups those aliases, so an application can list
+//! the formats it accepts in order of preference with [`negotiate`], and advertise every alias of
+//! the formats it provides with [`with_aliases`].
+
+use std::{borrow::Cow, ffi::OsString, os::unix::ffi::OsStringExt, path::PathBuf};
+
+/// The mime types of text, in order of preference.
+///
+/// The encoding of `STRING` is Latin-1, while the others are UTF-8.
+pub const TEXT_MIME_TYPES: [&str; 5] =
+    ["text/plain;charset=utf-8", "UTF8_STRING", "text/plain", "TEXT", "STRING"];
+
+/// The mime type of a list of URIs, used for files.
+pub const URI_LIST_MIME_TYPE: &str = "text/uri-list";
+
+/// A format of data, which may be offered under several mime types.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DataFormat {
+    /// Text, under any of the [`TEXT_MIME_TYPES`].
+    Text,
+    /// A list of URIs, decoded with [`decode_uri_list`].
+    UriList,
+    /// Data of exactly this mime type.
+    Mime(String),
+}
+
+impl DataFormat {
+    /// The format of `mime_type`.
+    pub fn from_mime_type(mime_type: &str) -> Self {
+        if TEXT_MIME_TYPES.contains(&mime_type) {
+            DataFormat::Text
+        } else if mime_type == URI_LIST_MIME_TYPE {
+            DataFormat::UriList
+        } else {
+            DataFormat::Mime(mime_type.to_string())
+        }
+    }
+
+    /// The mime types of this format, in order of preference.
+    pub fn mime_types(&self) -> Vec<&str> {
+        match self {
+            DataFormat::Text => TEXT_MIME_TYPES.to_vec(),
+            DataFormat::UriList => vec![URI_LIST_MIME_TYPE],
+            DataFormat::Mime(mime_type) => vec![mime_type],
+        }
+    }
+}
+
+/// Choose the mime type to receive from the `offered` mime types.
+///
+/// The `accepted` formats are tried in order, and the preferred mime type of the first one that is
+/// offered is returned along with it.
+pub fn negotiate<'a>(
+    accepted: &'a [DataFormat],
+    offered: &[String],
+) -> Option<(&'a DataFormat, String)> {
+    accepted.iter().find_map(|format| {
+        format
+            .mime_types()
+            .into_iter()
+            .find(|mime_type| offered.iter().any(|offered| offered == mime_type))
+            .map(|mime_type| (format, mime_type.to_string()))
+    })
+}
+
+/// Add the aliases of the given mime types, keeping the order and removing duplicates.
+///
+/// This is meant for sources, so clients asking for any of the aliases are served.
+pub fn with_aliases<T: AsRef<str>>(mime_types: impl IntoIterator<Item = T>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for mime_type in mime_types {
+        for alias in DataFormat::from_mime_type(mime_type.as_ref()).mime_types() {
+            if !result.iter().any(|existing| existing == alias) {
+                result.push(alias.to_string());
+            }
+        }
+    }
+    result
+}
+
+/// Decode text received as `mime_type`.
+///
+/// Line endings are normalized to `\n`, and invalid UTF-8 is replaced with the replacement
+/// character.
+pub fn decode_text(mime_type: &str, data: &[u8]) -> String {
+    let text = if mime_type == "STRING" {
+        data.iter().map(|&byte| char::from(byte)).collect()
+    } else {
+        String::from_utf8_lossy(data).into_owned()
+    };
+
+    normalize_line_endings(&text)
+}
+
+/// Convert text data from the encoding of `from` to the encoding of `to`.
+///
+/// This is meant for sources serving text under all its aliases, since `STRING` is Latin-1.
+/// Characters which Latin-1 can't represent are replaced with `?`.
+pub fn transcode_text<'a>(from: &str, to: &str, data: &'a [u8]) -> Cow<'a, [u8]> {
+    match (from == "STRING", to == "STRING") {
+        (false, true) => String::from_utf8_lossy(data)
+            .chars()
+            .map(|ch| u8::try_from(u32::from(ch)).unwrap_or(b'?'))
+            .collect(),
+        (true, false) => {
+            Cow::Owned(data.iter().map(|&byte| char::from(byte)).collect::<String>().into_bytes())
+        }
+        _ => Cow::Borrowed(data),
+    }
+}
+
+fn normalize_line_endings(text: &str) -> String {
+    if !text.contains('\r') {
+        return text.to_string();
+    }
+
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// An entry of a `text/uri-list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UriListEntry {
+    /// A local file.
+    Path(PathBuf),
+    /// Any other URI, including files on other hosts.
+    Url(String),
+}
+
+/// Decode a `text/uri-list`, as defined by RFC 2483.
+///
+/// Comments and empty lines are skipped, and `file` URIs on the local host are turned into paths.
+pub fn decode_uri_list(data: &[u8]) -> Vec<UriListEntry> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|uri| match file_uri_path(uri) {
+            Some(path) => UriListEntry::Path(path),
+            None => UriListEntry::Url(uri.to_string()),
+        })
+        .collect()
+}
+
+/// Encode local files as a `text/uri-list`.
+pub fn encode_uri_list<'a>(paths: impl IntoIterator<Item = &'a std::path::Path>) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut list = Vec::new();
+    for path in paths {
+        list.extend_from_slice(b"file://");
+        for &byte in path.as_os_str().as_bytes() {
+            if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+                list.push(byte);
+            } else {
+                list.extend_from_slice(format!("%{byte:02X}").as_bytes());
+            }
+        }
+        list.extend_from_slice(b"\r\n");
+    }
+    list
+}
+
+fn file_uri_path(uri: &str) -> Option<PathBuf> {
+    if !uri.get(..7)?.eq_ignore_ascii_case("file://") {
+        return None;
+    }
+
+    let rest = &uri[7..];
+    let path = match rest.find('/') {
+        Some(0) => rest,
+        Some(start) if &rest[..start] == "localhost" => &rest[start..],
+        _ => return None,
+    };
+
+    percent_decode(path).map(|path| PathBuf::from(OsString::from_vec(path)))
+}
+
+fn percent_decode(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = text.bytes();
+    let mut decoded = Vec::with_capacity(text.len());
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+
+    fn offered(mime_types: &[&str]) -> Vec<String> {
+        mime_types.iter().map(|mime| mime.to_string()).collect()
+    }
+
+    #[test]
+    fn negotiate_preference() {
+        let accepted = [DataFormat::Mime("image/png".into()), DataFormat::Text];
+
+        let (format, mime) = negotiate(&accepted, &offered(&["STRING", "UTF8_STRING"])).unwrap();
+        assert_eq!(format, &DataFormat::Text);
+        assert_eq!(mime, "UTF8_STRING");
+
+        let (format, mime) = negotiate(&accepted, &offered(&["text/plain", "image/png"])).unwrap();
+        assert_eq!(format, &DataFormat::Mime("image/png".into()));
+        assert_eq!(mime, "image/png");
+
+        assert!(negotiate(&accepted, &offered(&["text/html"])).is_none());
+    }
+
+    #[test]
+    fn aliases() {
+        assert_eq!(
+            with_aliases(["image/png", "text/plain", "UTF8_STRING"]),
+            [
+                "image/png",
+                "text/plain;charset=utf-8",
+                "UTF8_STRING",
+                "text/plain",
+                "TEXT",
+                "STRING"
+            ]
+        );
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(decode_text("text/plain;charset=utf-8", b"a\r\nb\rc\n"), "a\nb\nc\n");
+        assert_eq!(decode_text("STRING", b"caf\xe9"), "café");
+        assert_eq!(decode_text("UTF8_STRING", "café".as_bytes()), "café");
+
+        assert_eq!(transcode_text("UTF8_STRING", "STRING", "café €".as_bytes()), &b"caf\xe9 ?"[..]);
+        assert_eq!(transcode_text("STRING", "text/plain", b"caf\xe9"), "café".as_bytes());
+        assert_eq!(transcode_text("TEXT", "text/plain", b"abc"), &b"abc"[..]);
+    }
+
+    #[test]
+    fn uri_list() {
+        let list = b"# comment\r\nfile:///tmp/a%20b\r\nfile://localhost/c\r\n\
+            file://other/d\r\nhttps://example.org/\r\n";
+        assert_eq!(
+            decode_uri_list(list),
+            [
+                UriListEntry::Path("/tmp/a b".into()),
+                UriListEntry::Path("/c".into()),
+                UriListEntry::Url("file://other/d".into()),
+                UriListEntry::Url("https://example.org/".into()),
+            ]
+        );
+
+        let encoded = encode_uri_list([Path::new("/tmp/a b"), Path::new("/c")]);
+        assert_eq!(encoded, b"file:///tmp/a%20b\r\nfile:///c\r\n");
+        assert_eq!(
+            decode_uri_list(&encoded),
+            [UriListEntry::Path("/tmp/a b".into()), UriListEntry::Path("/c".into())]
+        );
+    }
+}
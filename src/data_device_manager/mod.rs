@@ -15,10 +15,16 @@ use crate::reexports::client::{
 pub mod data_device;
 pub mod data_offer;
 pub mod data_source;
+pub mod drag;
+pub mod mime;
 mod read_pipe;
+#[cfg(feature = "calloop")]
+mod transfer;
 mod write_pipe;
 
 pub use read_pipe::*;
+#[cfg(feature = "calloop")]
+pub use transfer::*;
 pub use write_pipe::*;
 
 use data_device::{DataDevice, DataDeviceData};
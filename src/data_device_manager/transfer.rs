@@ -0,0 +1,407 @@
+//! Non-blocking transfers through a [`ReadPipe`] or a [`WritePipe`], driven by a calloop event
+//! loop.
+//!
+//! The data is moved in chunks each time the pipe is ready, so large transfers don't stall the
+//! other sources of the event loop. A transfer ends with a single call to its callback.
+
+use std::{
+    cell::{Cell, RefCell},
+    fs,
+    io::{self, Cursor, Read, Write},
+    mem,
+    os::unix::io::AsFd,
+    ptr,
+    rc::Rc,
+    time::Duration,
+};
+
+use calloop::{
+    generic::NoIoDrop,
+    timer::{TimeoutAction, Timer},
+    EventSource, LoopHandle, PostAction,
+};
+
+use super::{ReadPipe, WritePipe};
+
+/// The amount of data moved each time a pipe is ready.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The options of a transfer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TransferOptions {
+    /// The maximum amount of data to receive, in bytes.
+    ///
+    /// This is ignored when sending data.
+    pub max_size: Option<usize>,
+
+    /// The time after which the transfer is abandoned.
+    pub timeout: Option<Duration>,
+}
+
+/// An error that ends a transfer.
+#[derive(Debug, thiserror::Error)]
+pub enum TransferError {
+    /// The other end of the pipe was closed before all the data was written.
+    #[error("the pipe was closed by the receiver")]
+    BrokenPipe,
+
+    /// More data than [`TransferOptions::max_size`] was received.
+    #[error("more than {0} bytes were received")]
+    TooLarge(usize),
+
+    /// The transfer didn't finish within [`TransferOptions::timeout`].
+    #[error("the transfer timed out")]
+    TimedOut,
+
+    #[error("IO error")]
+    Io(#[from] io::Error),
+}
+
+impl ReadPipe {
+    /// Read everything from the pipe, until the writer closes it.
+    ///
+    /// `callback` is called with the received data once the transfer is finished, or with the
+    /// error which ended it. The pipe is closed at that point.
+    pub fn receive_all<D, F>(
+        self,
+        loop_handle: &LoopHandle<'static, D>,
+        options: TransferOptions,
+        callback: F,
+    ) -> io::Result<()>
+    where
+        D: 'static,
+        F: FnOnce(&mut D, Result<Vec<u8>, TransferError>) + 'static,
+    {
+        let mut data = Vec::new();
+        let mut buf = vec![0; CHUNK_SIZE];
+
+        insert_transfer(loop_handle, self, options.timeout, callback, move |file| {
+            let mut received = 0;
+            while received < CHUNK_SIZE {
+                match file.read(&mut buf) {
+                    Ok(0) => return Some(Ok(mem::take(&mut data))),
+                    Ok(n) => {
+                        received += n;
+                        data.extend_from_slice(&buf[..n]);
+                        if let Some(max_size) = options.max_size.filter(|max| data.len() > *max) {
+                            return Some(Err(TransferError::TooLarge(max_size)));
+                        }
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => return None,
+                    Err(err) => return Some(Err(err.into())),
+                }
+            }
+
+            None
+        })
+    }
+}
+
+impl WritePipe {
+    /// Write all of `data` to the pipe, then close it.
+    ///
+    /// `callback` is called with the number of bytes written once the transfer is finished, or
+    /// with the error which ended it.
+    pub fn send_all<D, T, F>(
+        self,
+        loop_handle: &LoopHandle<'static, D>,
+        data: T,
+        options: TransferOptions,
+        callback: F,
+    ) -> io::Result<()>
+    where
+        D: 'static,
+        T: AsRef<[u8]> + 'static,
+        F: FnOnce(&mut D, Result<usize, TransferError>) + 'static,
+    {
+        self.send_from(loop_handle, Cursor::new(data), options, callback)
+    }
+
+    /// Write everything `reader` provides to the pipe, then close it.
+    ///
+    /// The reader is read from the event loop, a chunk at a time, so it should not block for long.
+    ///
+    /// `callback` is called with the number of bytes written once the transfer is finished, or
+    /// with the error which ended it.
+    ///
+    /// A receiver closing the pipe early ends the transfer with [`TransferError::BrokenPipe`].
+    /// `SIGPIPE` is blocked during the writes, so this doesn't depend on how the process handles
+    /// it.
+    pub fn send_from<D, R, F>(
+        self,
+        loop_handle: &LoopHandle<'static, D>,
+        mut reader: R,
+        options: TransferOptions,
+        callback: F,
+    ) -> io::Result<()>
+    where
+        D: 'static,
+        R: Read + 'static,
+        F: FnOnce(&mut D, Result<usize, TransferError>) + 'static,
+    {
+        let mut buf = vec![0; CHUNK_SIZE];
+        let mut pending = 0..0;
+        let mut written = 0;
+
+        insert_transfer(loop_handle, self, options.timeout, callback, move |file| {
+            if pending.is_empty() {
+                match reader.read(&mut buf) {
+                    Ok(0) => return Some(Ok(written)),
+                    Ok(n) => pending = 0..n,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => return None,
+                    Err(err) => return Some(Err(err.into())),
+                }
+            }
+
+            while !pending.is_empty() {
+                match write_without_sigpipe(file, &buf[pending.clone()]) {
+                    Ok(n) => {
+                        pending.start += n;
+                        written += n;
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => return None,
+                    Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {
+                        return Some(Err(TransferError::BrokenPipe))
+                    }
+                    Err(err) => return Some(Err(err.into())),
+                }
+            }
+
+            None
+        })
+    }
+}
+
+/// Insert a pipe in the event loop, calling `step` each time it is ready until it returns the
+/// result of the transfer.
+fn insert_transfer<D, S, T, F, G>(
+    loop_handle: &LoopHandle<'static, D>,
+    pipe: S,
+    timeout: Option<Duration>,
+    callback: F,
+    mut step: G,
+) -> io::Result<()>
+where
+    D: 'static,
+    S: EventSource<Event = (), Metadata = NoIoDrop<fs::File>, Ret = PostAction> + AsFd + 'static,
+    F: FnOnce(&mut D, Result<T, TransferError>) + 'static,
+    G: FnMut(&mut fs::File) -> Option<Result<T, TransferError>> + 'static,
+{
+    set_nonblocking(&pipe)?;
+
+    let callback = Rc::new(RefCell::new(Some(callback)));
+    let timer_token = Rc::new(Cell::new(None));
+
+    let pipe_token = {
+        let callback = callback.clone();
+        let timer_token = timer_token.clone();
+        // The handles are weak so the sources don't keep the event loop alive.
+        let weak_handle = loop_handle.downgrade();
+
+        loop_handle.insert_source(pipe, move |_, file, state| {
+            // SAFETY: the file is not closed.
+            let result = match step(unsafe { file.get_mut() }) {
+                Some(result) => result,
+                None => return PostAction::Continue,
+            };
+
+            if let (Some(token), Some(loop_handle)) = (timer_token.take(), weak_handle.upgrade()) {
+                loop_handle.remove(token);
+            }
+
+            let callback = callback.borrow_mut().take();
+            if let Some(callback) = callback {
+                callback(state, result);
+            }
+
+            PostAction::Remove
+        })
+    }
+    .map_err(|err| err.error)?;
+
+    if let Some(timeout) = timeout {
+        let weak_handle = loop_handle.downgrade();
+        let token = loop_handle
+            .insert_source(Timer::from_duration(timeout), move |_, _, state| {
+                if let Some(loop_handle) = weak_handle.upgrade() {
+                    loop_handle.remove(pipe_token);
+                }
+
+                let callback = callback.borrow_mut().take();
+                if let Some(callback) = callback {
+                    callback(state, Err(TransferError::TimedOut));
+                }
+
+                TimeoutAction::Drop
+            })
+            .map_err(|err| {
+                loop_handle.remove(pipe_token);
+                err.error
+            })?;
+        timer_token.set(Some(token));
+    }
+
+    Ok(())
+}
+
+/// Write to a pipe with `SIGPIPE` blocked for the current thread, so a pipe closed by the reader
+/// is always reported as `EPIPE` instead of a signal.
+fn write_without_sigpipe(file: &mut fs::File, buf: &[u8]) -> io::Result<usize> {
+    // SAFETY: the signal sets are initialized by `sigemptyset` or `pthread_sigmask` before they are
+    // read, and the previous mask of the thread is restored before returning.
+    unsafe {
+        let mut sigpipe = mem::zeroed();
+        libc::sigemptyset(&mut sigpipe);
+        libc::sigaddset(&mut sigpipe, libc::SIGPIPE);
+
+        let mut old_mask = mem::zeroed();
+        libc::pthread_sigmask(libc::SIG_BLOCK, &sigpipe, &mut old_mask);
+
+        // A signal already pending was not raised by this write, and must be left alone.
+        let was_pending = sigpipe_pending();
+        let result = file.write(buf);
+
+        // Consume the signal raised by the write, so it isn't delivered once unblocked. It is not
+        // pending if the process ignores it.
+        if matches!(&result, Err(err) if err.kind() == io::ErrorKind::BrokenPipe)
+            && !was_pending
+            && sigpipe_pending()
+        {
+            let mut signal = 0;
+            libc::sigwait(&sigpipe, &mut signal);
+        }
+
+        libc::pthread_sigmask(libc::SIG_SETMASK, &old_mask, ptr::null_mut());
+        result
+    }
+}
+
+/// Whether `SIGPIPE` is pending for the current thread.
+fn sigpipe_pending() -> bool {
+    // SAFETY: the signal set is initialized by `sigpending`.
+    unsafe {
+        let mut pending = mem::zeroed();
+        libc::sigpending(&mut pending);
+        libc::sigismember(&pending, libc::SIGPIPE) == 1
+    }
+}
+
+fn set_nonblocking(fd: impl AsFd) -> io::Result<()> {
+    use rustix::fs::{fcntl_getfl, fcntl_setfl, OFlags};
+
+    let flags = fcntl_getfl(&fd)?;
+    fcntl_setfl(&fd, flags | OFlags::NONBLOCK)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use calloop::EventLoop;
+    use rustix::pipe::{pipe_with, PipeFlags};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct State {
+        sent: Option<Result<usize, TransferError>>,
+        received: Option<Result<Vec<u8>, TransferError>>,
+    }
+
+    fn pipe() -> (ReadPipe, WritePipe) {
+        let (read, write) = pipe_with(PipeFlags::CLOEXEC).unwrap();
+        (ReadPipe::from(read), WritePipe::from(write))
+    }
+
+    fn run(event_loop: &mut EventLoop<'static, State>, state: &mut State) {
+        for _ in 0..100 {
+            if state.sent.is_some() && state.received.is_some() {
+                break;
+            }
+            event_loop.dispatch(Duration::from_millis(10), state).unwrap();
+        }
+    }
+
+    #[test]
+    fn transfer_larger_than_pipe() {
+        let mut event_loop = EventLoop::<State>::try_new().unwrap();
+        let mut state = State::default();
+        let data: Vec<u8> = (0..1024 * 1024).map(|i| i as u8).collect();
+
+        let (read, write) = pipe();
+        let handle = event_loop.handle();
+        write
+            .send_all(&handle, data.clone(), TransferOptions::default(), |state, result| {
+                state.sent = Some(result)
+            })
+            .unwrap();
+        read.receive_all(&handle, TransferOptions::default(), |state, result| {
+            state.received = Some(result)
+        })
+        .unwrap();
+
+        run(&mut event_loop, &mut state);
+        assert_eq!(state.sent.unwrap().unwrap(), data.len());
+        assert_eq!(state.received.unwrap().unwrap(), data);
+    }
+
+    #[test]
+    fn receive_too_large() {
+        let mut event_loop = EventLoop::<State>::try_new().unwrap();
+        let mut state = State::default();
+
+        let (read, write) = pipe();
+        let handle = event_loop.handle();
+        write
+            .send_all(&handle, vec![0; 1024], TransferOptions::default(), |state, result| {
+                state.sent = Some(result)
+            })
+            .unwrap();
+        let options = TransferOptions { max_size: Some(100), ..Default::default() };
+        read.receive_all(&handle, options, |state, result| state.received = Some(result)).unwrap();
+
+        run(&mut event_loop, &mut state);
+        assert!(matches!(state.received, Some(Err(TransferError::TooLarge(100)))));
+    }
+
+    #[test]
+    fn send_to_closed_pipe() {
+        let mut event_loop = EventLoop::<State>::try_new().unwrap();
+        let mut state = State { received: Some(Ok(Vec::new())), ..Default::default() };
+
+        let (read, write) = pipe();
+        drop(read);
+        write
+            .send_all(
+                &event_loop.handle(),
+                vec![0; 16],
+                TransferOptions::default(),
+                |state, result| state.sent = Some(result),
+            )
+            .unwrap();
+
+        run(&mut event_loop, &mut state);
+        assert!(matches!(state.sent, Some(Err(TransferError::BrokenPipe))));
+    }
+
+    #[test]
+    fn receive_timeout() {
+        let mut event_loop = EventLoop::<State>::try_new().unwrap();
+        let mut state = State { sent: Some(Ok(0)), ..Default::default() };
+
+        // The write end is kept open, so the transfer never finishes.
+        let (read, _write) = pipe();
+        let options =
+            TransferOptions { timeout: Some(Duration::from_millis(20)), ..Default::default() };
+        read.receive_all(&event_loop.handle(), options, |state, result| {
+            state.received = Some(result)
+        })
+        .unwrap();
+
+        run(&mut event_loop, &mut state);
+        assert!(matches!(state.received, Some(Err(TransferError::TimedOut))));
+    }
+}
@@ -0,0 +1,346 @@
+//! Management of the toplevels of other clients, for taskbars and docks.
+//!
+//! This uses `zwlr_foreign_toplevel_manager_v1`. Unlike the read-only
+//! [`foreign_toplevel_list`](crate::foreign_toplevel_list), it reports the states and outputs of
+//! toplevels and allows to activate, close, minimize, maximize and fullscreen them.
+
+use crate::{
+    dispatch2::Dispatch2, error::GlobalError, foreign_toplevel_list::ForeignToplevelInfo,
+    globals::GlobalData, registry::GlobalProxy,
+};
+use bitflags::bitflags;
+use std::sync::{Arc, Mutex};
+use wayland_client::{
+    globals::GlobalList,
+    protocol::{wl_output, wl_seat, wl_surface},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1, zwlr_foreign_toplevel_manager_v1,
+};
+
+bitflags! {
+    /// The states of a toplevel.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ToplevelStates: u32 {
+        const MAXIMIZED = 1;
+        const MINIMIZED = 2;
+        const ACTIVATED = 4;
+        const FULLSCREEN = 8;
+    }
+}
+
+impl ToplevelStates {
+    fn from_raw(raw: &[u8]) -> Self {
+        use zwlr_foreign_toplevel_handle_v1::State;
+
+        raw.chunks_exact(4)
+            .flat_map(TryInto::<[u8; 4]>::try_into)
+            .map(u32::from_ne_bytes)
+            .flat_map(State::try_from)
+            .fold(ToplevelStates::empty(), |states, state| {
+                states
+                    | match state {
+                        State::Maximized => ToplevelStates::MAXIMIZED,
+                        State::Minimized => ToplevelStates::MINIMIZED,
+                        State::Activated => ToplevelStates::ACTIVATED,
+                        State::Fullscreen => ToplevelStates::FULLSCREEN,
+                        _ => ToplevelStates::empty(),
+                    }
+            })
+    }
+}
+
+/// Information about a managed toplevel.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct ManagedToplevelInfo {
+    /// Title and app id.
+    ///
+    /// The identifier is always empty, since this protocol doesn't provide one.
+    pub info: ForeignToplevelInfo,
+    /// States of the toplevel
+    pub states: ToplevelStates,
+    /// Outputs the toplevel is visible on
+    pub outputs: Vec<wl_output::WlOutput>,
+    /// Parent of the toplevel, such as the main window of a dialog
+    pub parent: Option<zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1>,
+}
+
+#[derive(Debug, Default)]
+struct ManagedToplevelInner {
+    current_info: Option<ManagedToplevelInfo>,
+    pending_info: ManagedToplevelInfo,
+}
+
+#[doc(hidden)]
+#[derive(Debug, Default, Clone)]
+pub struct ManagedToplevelData(Arc<Mutex<ManagedToplevelInner>>);
+
+#[derive(Debug)]
+pub struct ForeignToplevelManager {
+    manager: GlobalProxy<zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1>,
+    toplevels: Vec<zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1>,
+}
+
+impl ForeignToplevelManager {
+    pub fn new<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1, GlobalData>
+            + 'static,
+    {
+        let manager = GlobalProxy::from(globals.bind(qh, 1..=3, GlobalData));
+        Self { manager, toplevels: Vec::new() }
+    }
+
+    /// Returns list of toplevels.
+    pub fn toplevels(&self) -> &[zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1] {
+        &self.toplevels
+    }
+
+    /// Returns information about a toplevel.
+    ///
+    /// This may be none if the toplevel has been destroyed or the compositor has not sent
+    /// information about the toplevel yet.
+    pub fn info(
+        &self,
+        toplevel: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+    ) -> Option<ManagedToplevelInfo> {
+        toplevel.data::<ManagedToplevelData>()?.0.lock().unwrap().current_info.clone()
+    }
+
+    /// Activate the toplevel for `seat`.
+    pub fn activate(
+        &self,
+        toplevel: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+        seat: &wl_seat::WlSeat,
+    ) {
+        toplevel.activate(seat);
+    }
+
+    /// Request the toplevel to be closed.
+    ///
+    /// The client may ignore the request, or ask the user first.
+    pub fn close(&self, toplevel: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1) {
+        toplevel.close();
+    }
+
+    pub fn set_minimized(
+        &self,
+        toplevel: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+        minimized: bool,
+    ) {
+        if minimized {
+            toplevel.set_minimized();
+        } else {
+            toplevel.unset_minimized();
+        }
+    }
+
+    pub fn set_maximized(
+        &self,
+        toplevel: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+        maximized: bool,
+    ) {
+        if maximized {
+            toplevel.set_maximized();
+        } else {
+            toplevel.unset_maximized();
+        }
+    }
+
+    /// Make the toplevel fullscreen, on `output` if provided.
+    ///
+    /// Fails if the compositor doesn't support version 2 of the protocol.
+    pub fn set_fullscreen(
+        &self,
+        toplevel: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+        output: Option<&wl_output::WlOutput>,
+    ) -> Result<(), GlobalError> {
+        if toplevel.version() < 2 {
+            return Err(GlobalError::InvalidVersion {
+                name: zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1::interface()
+                    .name,
+                required: 2,
+                available: toplevel.version(),
+            });
+        }
+        toplevel.set_fullscreen(output);
+        Ok(())
+    }
+
+    /// Leave fullscreen.
+    ///
+    /// Fails if the compositor doesn't support version 2 of the protocol.
+    pub fn unset_fullscreen(
+        &self,
+        toplevel: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+    ) -> Result<(), GlobalError> {
+        if toplevel.version() < 2 {
+            return Err(GlobalError::InvalidVersion {
+                name: zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1::interface()
+                    .name,
+                required: 2,
+                available: toplevel.version(),
+            });
+        }
+        toplevel.unset_fullscreen();
+        Ok(())
+    }
+
+    /// Set the area of `surface` representing the toplevel, such as a taskbar button.
+    ///
+    /// The compositor may use it as the target of the minimize animation.
+    pub fn set_rectangle(
+        &self,
+        toplevel: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+        surface: &wl_surface::WlSurface,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    ) {
+        toplevel.set_rectangle(surface, x, y, width, height);
+    }
+
+    pub fn stop(&self) {
+        if let Ok(manager) = self.manager.get() {
+            manager.stop();
+        }
+    }
+}
+
+/// Handler trait for foreign toplevel management protocol.
+pub trait ForeignToplevelManagerHandler: Sized {
+    fn foreign_toplevel_manager_state(&mut self) -> &mut ForeignToplevelManager;
+
+    /// A new toplevel has been opened.
+    fn new_toplevel(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        toplevel_handle: zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+    );
+
+    /// An existing toplevel has changed.
+    fn update_toplevel(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        toplevel_handle: zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+    );
+
+    /// A toplevel has closed.
+    fn toplevel_closed(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        toplevel_handle: zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+    );
+
+    fn finished(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>) {}
+}
+
+impl<D> Dispatch2<zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1, D> for GlobalData
+where
+    D: Dispatch<zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1, ManagedToplevelData>
+        + ForeignToplevelManagerHandler
+        + 'static,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        _: &zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        match event {
+            zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel: _ } => {}
+            zwlr_foreign_toplevel_manager_v1::Event::Finished => {
+                // The manager is destroyed by the compositor.
+                state.finished(conn, qh);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    wayland_client::event_created_child!(D, zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1, Default::default())
+    ]);
+}
+
+impl<D> Dispatch2<zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1, D>
+    for ManagedToplevelData
+where
+    D: ForeignToplevelManagerHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        handle: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                state.toplevel_closed(conn, qh, handle.clone());
+                let toplevels = &mut state.foreign_toplevel_manager_state().toplevels;
+                if let Some(idx) = toplevels.iter().position(|x| x == handle) {
+                    toplevels.remove(idx);
+                }
+                handle.destroy();
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Done => {
+                let mut inner = self.0.lock().unwrap();
+                let just_created = inner.current_info.is_none();
+                inner.current_info = Some(inner.pending_info.clone());
+                drop(inner);
+                if just_created {
+                    state.foreign_toplevel_manager_state().toplevels.push(handle.clone());
+                    state.new_toplevel(conn, qh, handle.clone());
+                } else {
+                    state.update_toplevel(conn, qh, handle.clone());
+                }
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => {
+                self.0.lock().unwrap().pending_info.info.title = title;
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                self.0.lock().unwrap().pending_info.info.app_id = app_id;
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::OutputEnter { output } => {
+                let outputs = &mut self.0.lock().unwrap().pending_info.outputs;
+                if !outputs.contains(&output) {
+                    outputs.push(output);
+                }
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::OutputLeave { output } => {
+                self.0.lock().unwrap().pending_info.outputs.retain(|o| *o != output);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::State { state } => {
+                self.0.lock().unwrap().pending_info.states = ToplevelStates::from_raw(&state);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Parent { parent } => {
+                self.0.lock().unwrap().pending_info.parent = parent;
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ToplevelStates;
+
+    #[test]
+    fn states_from_raw() {
+        let raw: Vec<u8> = [2u32, 0, 42].iter().flat_map(|state| state.to_ne_bytes()).collect();
+        assert_eq!(
+            ToplevelStates::from_raw(&raw),
+            ToplevelStates::ACTIVATED | ToplevelStates::MAXIMIZED
+        );
+        assert_eq!(ToplevelStates::from_raw(&[]), ToplevelStates::empty());
+    }
+}
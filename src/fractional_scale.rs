@@ -0,0 +1,186 @@
+//! Fractional scaling of surfaces.
+//!
+//! The `wp_fractional_scale_v1` protocol lets the compositor suggest a preferred scale for a
+//! surface that is not necessarily an integer. The preferred scale is delivered as the numerator
+//! of a fraction with a denominator of [`SCALE_DENOMINATOR`] (`120`).
+//!
+//! Surfaces which render at a fractional scale should keep their buffer scale at `1` and use a
+//! viewport to set the logical size of the surface.
+//!
+//! Once [`CompositorState::enable_fractional_scale`] was called, surfaces created through the
+//! [`CompositorState`] get a fractional scale object automatically, which lives as long as the
+//! data of the surface.
+//!
+//! [`CompositorState`]: crate::compositor::CompositorState
+//! [`CompositorState::enable_fractional_scale`]: crate::compositor::CompositorState::enable_fractional_scale
+
+use std::{marker::PhantomData, sync::atomic::Ordering};
+
+use wayland_client::{
+    globals::GlobalList, protocol::wl_surface, Connection, Dispatch, Proxy, QueueHandle, Weak,
+};
+use wayland_protocols::wp::fractional_scale::v1::client::{
+    wp_fractional_scale_manager_v1, wp_fractional_scale_v1,
+};
+
+use crate::{
+    compositor::{CompositorHandler, SurfaceData},
+    dispatch2::Dispatch2,
+    error::GlobalError,
+    globals::{GlobalData, ProvidesBoundGlobal},
+    registry::GlobalProxy,
+};
+
+/// The denominator of the preferred scale sent by the compositor.
+pub const SCALE_DENOMINATOR: u32 = 120;
+
+#[derive(Debug, Clone)]
+pub struct FractionalScaleState {
+    fractional_scale_manager:
+        GlobalProxy<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1>,
+}
+
+impl FractionalScaleState {
+    /// Bind `wp_fractional_scale_manager_v1` global, if it exists
+    pub fn bind<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1, GlobalData>
+            + 'static,
+    {
+        let fractional_scale_manager = GlobalProxy::from(globals.bind(qh, 1..=1, GlobalData));
+        Self { fractional_scale_manager }
+    }
+
+    /// Get `wp_fractional_scale_v1` for a given `wl_surface`.
+    ///
+    /// `U` is the user data type of the [`SurfaceData`] the surface was created with, which is
+    /// `()` for surfaces created with [`CompositorState::create_surface`]. The preferred scale is
+    /// stored in the surface data and reported through
+    /// [`CompositorHandler::fractional_scale_changed`].
+    ///
+    /// This is only needed for surfaces which weren't created through a [`CompositorState`] with
+    /// fractional scaling enabled, since those already have one. It is a protocol error to request
+    /// a second fractional scale object for the same surface.
+    ///
+    /// The object is destroyed when the returned [`FractionalScale`] is dropped, and stops
+    /// sending events once the surface is destroyed.
+    ///
+    /// Returns error if `wp_fractional_scale_manager_v1` global is not present.
+    ///
+    /// [`CompositorState`]: crate::compositor::CompositorState
+    /// [`CompositorState::create_surface`]: crate::compositor::CompositorState::create_surface
+    pub fn fractional_scale<D, U>(
+        &self,
+        surface: &wl_surface::WlSurface,
+        qh: &QueueHandle<D>,
+    ) -> Result<FractionalScale, GlobalError>
+    where
+        D: Dispatch<wp_fractional_scale_v1::WpFractionalScaleV1, FractionalScaleData<U>> + 'static,
+        U: Send + Sync + 'static,
+    {
+        Ok(FractionalScale::new(self.fractional_scale_manager.get()?, surface, qh))
+    }
+
+    /// The `wp_fractional_scale_manager_v1` global, if any.
+    pub fn wp_fractional_scale_manager(
+        &self,
+    ) -> Result<&wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1, GlobalError> {
+        self.fractional_scale_manager.get()
+    }
+}
+
+impl ProvidesBoundGlobal<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1, 1>
+    for FractionalScaleState
+{
+    fn bound_global(
+        &self,
+    ) -> Result<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1, GlobalError> {
+        self.fractional_scale_manager.get().cloned()
+    }
+}
+
+/// An owned `wp_fractional_scale_v1`.
+///
+/// This destroys the fractional scale object on drop.
+#[derive(Debug)]
+pub struct FractionalScale(wp_fractional_scale_v1::WpFractionalScaleV1);
+
+impl FractionalScale {
+    pub(crate) fn new<D, U>(
+        manager: &wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+        surface: &wl_surface::WlSurface,
+        qh: &QueueHandle<D>,
+    ) -> Self
+    where
+        D: Dispatch<wp_fractional_scale_v1::WpFractionalScaleV1, FractionalScaleData<U>> + 'static,
+        U: Send + Sync + 'static,
+    {
+        // The surface is weak, since the surface data may own this object.
+        let udata =
+            FractionalScaleData { surface: surface.downgrade(), _surface_data: PhantomData };
+        Self(manager.get_fractional_scale(surface, qh, udata))
+    }
+
+    pub fn wp_fractional_scale(&self) -> &wp_fractional_scale_v1::WpFractionalScaleV1 {
+        &self.0
+    }
+}
+
+impl Drop for FractionalScale {
+    fn drop(&mut self) {
+        self.0.destroy();
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct FractionalScaleData<U = ()> {
+    surface: Weak<wl_surface::WlSurface>,
+    _surface_data: PhantomData<fn() -> U>,
+}
+
+impl<D> Dispatch2<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+        _: wp_fractional_scale_manager_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("wp_fractional_scale_manager_v1 has no events")
+    }
+}
+
+impl<D, U> Dispatch2<wp_fractional_scale_v1::WpFractionalScaleV1, D> for FractionalScaleData<U>
+where
+    D: CompositorHandler,
+    U: Send + Sync + 'static,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        _: &wp_fractional_scale_v1::WpFractionalScaleV1,
+        event: wp_fractional_scale_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        match event {
+            wp_fractional_scale_v1::Event::PreferredScale { scale } => {
+                let Ok(surface) = self.surface.upgrade() else {
+                    return;
+                };
+                let changed = match surface.data::<SurfaceData<U>>() {
+                    Some(data) => data.fractional_scale.swap(scale, Ordering::Relaxed) != scale,
+                    None => true,
+                };
+
+                if changed {
+                    state.fractional_scale_changed(conn, qh, &surface, scale);
+                }
+            }
+
+            _ => unreachable!(),
+        }
+    }
+}
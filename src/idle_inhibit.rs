@@ -0,0 +1,118 @@
+//! Inhibiting the idle behavior of the compositor.
+//!
+//! While an inhibitor exists and its surface is visible, the compositor will not blank the screen,
+//! lock the session or otherwise act on the user being idle. This is mainly useful for video
+//! players and presentations.
+
+use wayland_client::{
+    globals::GlobalList, protocol::wl_surface, Connection, Dispatch, QueueHandle,
+};
+use wayland_protocols::wp::idle_inhibit::zv1::client::{
+    zwp_idle_inhibit_manager_v1, zwp_idle_inhibitor_v1,
+};
+
+use crate::{
+    dispatch2::Dispatch2,
+    error::GlobalError,
+    globals::{GlobalData, ProvidesBoundGlobal},
+    registry::GlobalProxy,
+};
+
+#[derive(Debug)]
+pub struct IdleInhibitState {
+    idle_inhibit_manager: GlobalProxy<zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1>,
+}
+
+impl IdleInhibitState {
+    /// Bind `zwp_idle_inhibit_manager_v1` global, if it exists
+    pub fn bind<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1, GlobalData> + 'static,
+    {
+        let idle_inhibit_manager = GlobalProxy::from(globals.bind(qh, 1..=1, GlobalData));
+        Self { idle_inhibit_manager }
+    }
+
+    /// Inhibit idling while `surface` is visible.
+    ///
+    /// Returns error if `zwp_idle_inhibit_manager_v1` global is not present.
+    pub fn inhibit<D>(
+        &self,
+        surface: &wl_surface::WlSurface,
+        qh: &QueueHandle<D>,
+    ) -> Result<IdleInhibitor, GlobalError>
+    where
+        D: Dispatch<zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1, GlobalData> + 'static,
+    {
+        IdleInhibitor::new(self, surface, qh)
+    }
+}
+
+impl ProvidesBoundGlobal<zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1, 1>
+    for IdleInhibitState
+{
+    fn bound_global(
+        &self,
+    ) -> Result<zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1, GlobalError> {
+        self.idle_inhibit_manager.get().cloned()
+    }
+}
+
+/// An owned [`ZwpIdleInhibitorV1`](zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1).
+///
+/// Idling is inhibited until this is dropped, or the surface is destroyed.
+#[derive(Debug)]
+pub struct IdleInhibitor(zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1);
+
+impl IdleInhibitor {
+    pub fn new<D>(
+        idle_inhibit_manager: &impl ProvidesBoundGlobal<
+            zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1,
+            1,
+        >,
+        surface: &wl_surface::WlSurface,
+        qh: &QueueHandle<D>,
+    ) -> Result<Self, GlobalError>
+    where
+        D: Dispatch<zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1, GlobalData> + 'static,
+    {
+        let idle_inhibit_manager = idle_inhibit_manager.bound_global()?;
+        Ok(IdleInhibitor(idle_inhibit_manager.create_inhibitor(surface, qh, GlobalData)))
+    }
+
+    pub fn zwp_idle_inhibitor_v1(&self) -> &zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1 {
+        &self.0
+    }
+}
+
+impl Drop for IdleInhibitor {
+    fn drop(&mut self) {
+        self.0.destroy();
+    }
+}
+
+impl<D> Dispatch2<zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1,
+        _: zwp_idle_inhibit_manager_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("zwp_idle_inhibit_manager_v1 has no events")
+    }
+}
+
+impl<D> Dispatch2<zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1,
+        _: zwp_idle_inhibitor_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("zwp_idle_inhibitor_v1 has no events")
+    }
+}
@@ -0,0 +1,209 @@
+//! Notifications about the user being idle.
+//!
+//! A notification is created for a seat with a timeout, and the compositor tells the client when
+//! the seat has been idle for at least that long, and when the user is active again.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use wayland_client::{
+    globals::GlobalList, protocol::wl_seat, Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols::ext::idle_notify::v1::client::{
+    ext_idle_notification_v1, ext_idle_notifier_v1,
+};
+
+use crate::{
+    dispatch2::Dispatch2,
+    error::GlobalError,
+    globals::{GlobalData, ProvidesBoundGlobal},
+    registry::GlobalProxy,
+};
+
+#[derive(Debug)]
+pub struct IdleNotifierState {
+    idle_notifier: GlobalProxy<ext_idle_notifier_v1::ExtIdleNotifierV1>,
+}
+
+impl IdleNotifierState {
+    /// Bind `ext_idle_notifier_v1` global, if it exists
+    pub fn bind<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<ext_idle_notifier_v1::ExtIdleNotifierV1, GlobalData> + 'static,
+    {
+        let idle_notifier = GlobalProxy::from(globals.bind(qh, 1..=2, GlobalData));
+        Self { idle_notifier }
+    }
+
+    /// Get notified when `seat` has been idle for at least `timeout`.
+    ///
+    /// Idle inhibitors of other clients are taken into account, so the seat is not considered
+    /// idle while a video is playing, for example.
+    ///
+    /// Returns error if `ext_idle_notifier_v1` global is not present.
+    pub fn get_idle_notification<D>(
+        &self,
+        seat: &wl_seat::WlSeat,
+        timeout: Duration,
+        qh: &QueueHandle<D>,
+    ) -> Result<IdleNotification, GlobalError>
+    where
+        D: Dispatch<ext_idle_notification_v1::ExtIdleNotificationV1, IdleNotificationData>
+            + 'static,
+    {
+        let udata = IdleNotificationData::new(seat.clone());
+        let notification = self.idle_notifier.get()?.get_idle_notification(
+            timeout_millis(timeout),
+            seat,
+            qh,
+            udata,
+        );
+        Ok(IdleNotification(notification))
+    }
+
+    /// Get notified when there was no user input on `seat` for at least `timeout`.
+    ///
+    /// Unlike [`get_idle_notification`](Self::get_idle_notification), idle inhibitors are
+    /// ignored, and only input from the user counts as activity.
+    ///
+    /// Returns error if `ext_idle_notifier_v1` global is not present, or does not support version
+    /// 2.
+    pub fn get_input_idle_notification<D>(
+        &self,
+        seat: &wl_seat::WlSeat,
+        timeout: Duration,
+        qh: &QueueHandle<D>,
+    ) -> Result<IdleNotification, GlobalError>
+    where
+        D: Dispatch<ext_idle_notification_v1::ExtIdleNotificationV1, IdleNotificationData>
+            + 'static,
+    {
+        let udata = IdleNotificationData::new(seat.clone());
+        let notification = self.idle_notifier.with_min_version(2)?.get_input_idle_notification(
+            timeout_millis(timeout),
+            seat,
+            qh,
+            udata,
+        );
+        Ok(IdleNotification(notification))
+    }
+}
+
+impl ProvidesBoundGlobal<ext_idle_notifier_v1::ExtIdleNotifierV1, 2> for IdleNotifierState {
+    fn bound_global(&self) -> Result<ext_idle_notifier_v1::ExtIdleNotifierV1, GlobalError> {
+        self.idle_notifier.get().cloned()
+    }
+}
+
+fn timeout_millis(timeout: Duration) -> u32 {
+    timeout.as_millis().try_into().unwrap_or(u32::MAX)
+}
+
+/// An owned [`ExtIdleNotificationV1`](ext_idle_notification_v1::ExtIdleNotificationV1).
+///
+/// This destroys the notification on drop.
+#[derive(Debug)]
+pub struct IdleNotification(ext_idle_notification_v1::ExtIdleNotificationV1);
+
+impl IdleNotification {
+    /// Whether the seat is currently idle.
+    pub fn is_idle(&self) -> bool {
+        self.data().idle.load(Ordering::Relaxed)
+    }
+
+    /// The seat the notification was created for.
+    pub fn seat(&self) -> &wl_seat::WlSeat {
+        &self.data().seat
+    }
+
+    pub fn ext_idle_notification_v1(&self) -> &ext_idle_notification_v1::ExtIdleNotificationV1 {
+        &self.0
+    }
+
+    fn data(&self) -> &IdleNotificationData {
+        self.0.data::<IdleNotificationData>().unwrap()
+    }
+}
+
+impl PartialEq<ext_idle_notification_v1::ExtIdleNotificationV1> for IdleNotification {
+    fn eq(&self, other: &ext_idle_notification_v1::ExtIdleNotificationV1) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Drop for IdleNotification {
+    fn drop(&mut self) {
+        self.0.destroy();
+    }
+}
+
+pub trait IdleNotifyHandler: Sized {
+    /// The seat has been idle for at least the timeout of the notification.
+    fn idled(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        notification: &ext_idle_notification_v1::ExtIdleNotificationV1,
+    );
+
+    /// The user is active again after the notification idled.
+    fn resumed(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        notification: &ext_idle_notification_v1::ExtIdleNotificationV1,
+    );
+}
+
+#[derive(Debug)]
+pub struct IdleNotificationData {
+    seat: wl_seat::WlSeat,
+    idle: AtomicBool,
+}
+
+impl IdleNotificationData {
+    fn new(seat: wl_seat::WlSeat) -> Self {
+        Self { seat, idle: AtomicBool::new(false) }
+    }
+}
+
+impl<D> Dispatch2<ext_idle_notifier_v1::ExtIdleNotifierV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ext_idle_notifier_v1::ExtIdleNotifierV1,
+        _: ext_idle_notifier_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("ext_idle_notifier_v1 has no events")
+    }
+}
+
+impl<D> Dispatch2<ext_idle_notification_v1::ExtIdleNotificationV1, D> for IdleNotificationData
+where
+    D: IdleNotifyHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        notification: &ext_idle_notification_v1::ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        match event {
+            ext_idle_notification_v1::Event::Idled => {
+                self.idle.store(true, Ordering::Relaxed);
+                state.idled(conn, qh, notification);
+            }
+            ext_idle_notification_v1::Event::Resumed => {
+                self.idle.store(false, Ordering::Relaxed);
+                state.resumed(conn, qh, notification);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -24,14 +24,23 @@ pub mod reexports {
 
 pub mod activation;
 pub mod background_effect;
+pub mod capture;
+#[cfg(feature = "calloop")]
+pub mod clipboard;
 pub mod compositor;
+pub mod data_control;
 pub mod data_device_manager;
 pub mod dispatch2;
 pub mod dmabuf;
 pub mod error;
 pub mod foreign_toplevel_list;
+pub mod foreign_toplevel_management;
+pub mod fractional_scale;
 pub mod globals;
+pub mod idle_inhibit;
+pub mod idle_notify;
 pub mod output;
+pub mod output_management;
 pub mod presentation_time;
 pub mod primary_selection;
 pub mod registry;
@@ -40,3 +49,5 @@ pub mod session_lock;
 pub mod shell;
 pub mod shm;
 pub mod subcompositor;
+pub mod viewporter;
+pub mod workspace;
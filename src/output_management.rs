@@ -0,0 +1,629 @@
+//! Configuration of outputs, for display settings tools.
+//!
+//! This uses `zwlr_output_manager_v1`. The compositor describes each output as a head, with the
+//! modes it supports. Changes are made through an [`OutputConfiguration`], which may be tested
+//! before it is applied, and the result is reported to
+//! [`OutputManagementHandler::configuration_result`].
+//!
+//! Heads may be matched with the outputs of [`OutputState`](crate::output::OutputState) with
+//! [`OutputManagementState::head_for_output`].
+
+use crate::{
+    dispatch2::Dispatch2, error::GlobalError, globals::GlobalData, output::OutputInfo,
+    registry::GlobalProxy,
+};
+use std::sync::Mutex;
+use wayland_client::{
+    globals::GlobalList, protocol::wl_output::Transform, Connection, Dispatch, Proxy, QueueHandle,
+    WEnum,
+};
+use wayland_protocols_wlr::output_management::v1::client::{
+    zwlr_output_configuration_head_v1::{self, ZwlrOutputConfigurationHeadV1},
+    zwlr_output_configuration_v1::{self, ZwlrOutputConfigurationV1},
+    zwlr_output_head_v1::{self, AdaptiveSyncState, ZwlrOutputHeadV1},
+    zwlr_output_manager_v1::{self, ZwlrOutputManagerV1},
+    zwlr_output_mode_v1::{self, ZwlrOutputModeV1},
+};
+
+/// A mode supported by a head.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadMode {
+    pub mode: ZwlrOutputModeV1,
+
+    /// Size of the mode, in hardware units.
+    pub dimensions: (i32, i32),
+
+    /// Refresh rate in mHz, or zero if it isn't known.
+    pub refresh_rate: i32,
+
+    /// Whether this is the preferred mode of the head.
+    pub preferred: bool,
+}
+
+/// Information about a head.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct HeadInfo {
+    /// The name of the head, which matches the name of its output.
+    pub name: String,
+    pub description: String,
+
+    /// Physical dimensions of the head, in millimeters.
+    pub physical_size: (i32, i32),
+
+    pub make: String,
+    pub model: String,
+    pub serial_number: Option<String>,
+
+    /// Modes supported by the head.
+    pub modes: Vec<HeadMode>,
+
+    /// Whether the head is enabled, which is when it has an output.
+    ///
+    /// The other properties only describe the current state of enabled heads.
+    pub enabled: bool,
+
+    /// The current mode, unless a custom mode is used.
+    pub current_mode: Option<ZwlrOutputModeV1>,
+
+    /// Position of the top-left corner of the head in compositor space.
+    pub position: (i32, i32),
+
+    pub transform: Transform,
+    pub scale: f64,
+
+    /// Whether adaptive sync is enabled, if the compositor reports it.
+    pub adaptive_sync: Option<bool>,
+}
+
+impl Default for HeadInfo {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            description: String::new(),
+            physical_size: (0, 0),
+            make: String::new(),
+            model: String::new(),
+            serial_number: None,
+            modes: Vec::new(),
+            enabled: false,
+            current_mode: None,
+            position: (0, 0),
+            transform: Transform::Normal,
+            scale: 1.0,
+            adaptive_sync: None,
+        }
+    }
+}
+
+impl HeadInfo {
+    /// The current mode with its properties.
+    pub fn current_mode(&self) -> Option<&HeadMode> {
+        let current = self.current_mode.as_ref()?;
+        self.modes.iter().find(|mode| mode.mode == *current)
+    }
+
+    /// Whether the head describes the output of `info`.
+    ///
+    /// Outputs are matched by name, or by make, model and position if the compositor doesn't
+    /// advertise output names.
+    pub fn matches_output(&self, info: &OutputInfo) -> bool {
+        match &info.name {
+            Some(name) => *name == self.name,
+            None => {
+                self.enabled
+                    && info.make == self.make
+                    && info.model == self.model
+                    && info.location == self.position
+            }
+        }
+    }
+}
+
+/// How a configuration was handled by the compositor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigurationResult {
+    /// The configuration was applied, or would be applied if it was a test.
+    Succeeded,
+    /// The configuration was rejected.
+    Failed,
+    /// The heads changed since the configuration was created, and it should be created again.
+    Cancelled,
+}
+
+#[derive(Debug)]
+pub struct OutputManagementState {
+    manager: GlobalProxy<ZwlrOutputManagerV1>,
+    heads: Vec<ZwlrOutputHeadV1>,
+    /// Heads created since the last `done` event.
+    new_heads: Vec<ZwlrOutputHeadV1>,
+    serial: Option<u32>,
+}
+
+impl OutputManagementState {
+    pub fn new<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<ZwlrOutputManagerV1, GlobalData> + 'static,
+    {
+        let manager = GlobalProxy::from(globals.bind(qh, 1..=4, GlobalData));
+        Self { manager, heads: Vec::new(), new_heads: Vec::new(), serial: None }
+    }
+
+    /// Returns list of heads.
+    pub fn heads(&self) -> &[ZwlrOutputHeadV1] {
+        &self.heads
+    }
+
+    /// Returns information about a head.
+    ///
+    /// This may be none if the head has been removed or the compositor has not sent information
+    /// about the head yet.
+    pub fn head_info(&self, head: &ZwlrOutputHeadV1) -> Option<HeadInfo> {
+        head.data::<HeadData>()?.inner.lock().unwrap().current_info.clone()
+    }
+
+    /// Find the head of an output.
+    pub fn head_for_output(&self, info: &OutputInfo) -> Option<ZwlrOutputHeadV1> {
+        self.heads
+            .iter()
+            .find(|head| self.head_info(head).is_some_and(|head| head.matches_output(info)))
+            .cloned()
+    }
+
+    /// Start a configuration of the heads.
+    ///
+    /// The configuration is cancelled if the heads change before it is sent.
+    pub fn create_configuration<D>(
+        &self,
+        qh: &QueueHandle<D>,
+    ) -> Result<OutputConfiguration, GlobalError>
+    where
+        D: Dispatch<ZwlrOutputConfigurationV1, OutputConfigurationData> + 'static,
+    {
+        let serial = self.serial.unwrap_or_default();
+        let configuration =
+            self.manager.get()?.create_configuration(serial, qh, OutputConfigurationData);
+        let unconfigured = self
+            .heads
+            .iter()
+            .filter_map(|head| Some((head.clone(), self.head_info(head)?.enabled)))
+            .collect();
+        Ok(OutputConfiguration { configuration, unconfigured, used: false })
+    }
+
+    pub fn stop(&self) {
+        if let Ok(manager) = self.manager.get() {
+            manager.stop();
+        }
+    }
+}
+
+/// A configuration of the heads, sent with [`OutputConfiguration::test`] or
+/// [`OutputConfiguration::apply`].
+///
+/// Heads which aren't enabled or disabled keep their current state. Configuring a head twice is a
+/// protocol error.
+#[derive(Debug)]
+pub struct OutputConfiguration {
+    configuration: ZwlrOutputConfigurationV1,
+    /// The heads not configured yet, with whether they are enabled.
+    unconfigured: Vec<(ZwlrOutputHeadV1, bool)>,
+    used: bool,
+}
+
+impl OutputConfiguration {
+    /// Enable a head, returning the configuration of its properties.
+    ///
+    /// Properties which aren't set keep their current value.
+    pub fn enable_head<D>(
+        &mut self,
+        qh: &QueueHandle<D>,
+        head: &ZwlrOutputHeadV1,
+    ) -> HeadConfiguration
+    where
+        D: Dispatch<ZwlrOutputConfigurationHeadV1, GlobalData> + 'static,
+    {
+        self.unconfigured.retain(|(unconfigured, _)| unconfigured != head);
+        HeadConfiguration { head: self.configuration.enable_head(head, qh, GlobalData) }
+    }
+
+    /// Disable a head.
+    pub fn disable_head(&mut self, head: &ZwlrOutputHeadV1) {
+        self.unconfigured.retain(|(unconfigured, _)| unconfigured != head);
+        self.configuration.disable_head(head);
+    }
+
+    /// Check whether the compositor would apply the configuration, without applying it.
+    ///
+    /// The returned object is passed to [`OutputManagementHandler::configuration_result`].
+    pub fn test<D>(mut self, qh: &QueueHandle<D>) -> ZwlrOutputConfigurationV1
+    where
+        D: Dispatch<ZwlrOutputConfigurationHeadV1, GlobalData> + 'static,
+    {
+        self.configure_remaining(qh);
+        self.configuration.test();
+        self.configuration.clone()
+    }
+
+    /// Apply the configuration.
+    ///
+    /// The returned object is passed to [`OutputManagementHandler::configuration_result`].
+    pub fn apply<D>(mut self, qh: &QueueHandle<D>) -> ZwlrOutputConfigurationV1
+    where
+        D: Dispatch<ZwlrOutputConfigurationHeadV1, GlobalData> + 'static,
+    {
+        self.configure_remaining(qh);
+        self.configuration.apply();
+        self.configuration.clone()
+    }
+
+    /// Keep the current state of the heads which weren't configured, since the compositor
+    /// requires all heads to be.
+    fn configure_remaining<D>(&mut self, qh: &QueueHandle<D>)
+    where
+        D: Dispatch<ZwlrOutputConfigurationHeadV1, GlobalData> + 'static,
+    {
+        for (head, enabled) in self.unconfigured.drain(..) {
+            if enabled {
+                self.configuration.enable_head(&head, qh, GlobalData);
+            } else {
+                self.configuration.disable_head(&head);
+            }
+        }
+        self.used = true;
+    }
+}
+
+impl Drop for OutputConfiguration {
+    fn drop(&mut self) {
+        // A sent configuration is destroyed once its result is received.
+        if !self.used {
+            self.configuration.destroy();
+        }
+    }
+}
+
+/// The configuration of an enabled head.
+///
+/// Each property may only be set once.
+#[derive(Debug)]
+pub struct HeadConfiguration {
+    head: ZwlrOutputConfigurationHeadV1,
+}
+
+impl HeadConfiguration {
+    /// Use one of the modes of the head.
+    pub fn set_mode(&self, mode: &ZwlrOutputModeV1) -> &Self {
+        self.head.set_mode(mode);
+        self
+    }
+
+    /// Use a mode which isn't one of the modes of the head.
+    ///
+    /// The refresh rate is in mHz, and may be zero to let the compositor choose it.
+    pub fn set_custom_mode(&self, width: i32, height: i32, refresh_rate: i32) -> &Self {
+        self.head.set_custom_mode(width, height, refresh_rate);
+        self
+    }
+
+    /// Set the position of the top-left corner of the head in compositor space.
+    pub fn set_position(&self, x: i32, y: i32) -> &Self {
+        self.head.set_position(x, y);
+        self
+    }
+
+    pub fn set_transform(&self, transform: Transform) -> &Self {
+        self.head.set_transform(transform);
+        self
+    }
+
+    pub fn set_scale(&self, scale: f64) -> &Self {
+        self.head.set_scale(scale);
+        self
+    }
+
+    /// Enable or disable adaptive sync.
+    ///
+    /// Fails if the compositor doesn't support version 4 of the protocol.
+    pub fn set_adaptive_sync(&self, enabled: bool) -> Result<&Self, GlobalError> {
+        if self.head.version() < 4 {
+            return Err(GlobalError::InvalidVersion {
+                name: ZwlrOutputConfigurationHeadV1::interface().name,
+                required: 4,
+                available: self.head.version(),
+            });
+        }
+
+        let state = if enabled { AdaptiveSyncState::Enabled } else { AdaptiveSyncState::Disabled };
+        self.head.set_adaptive_sync(state);
+        Ok(self)
+    }
+
+    /// The underlying wayland object.
+    pub fn inner(&self) -> &ZwlrOutputConfigurationHeadV1 {
+        &self.head
+    }
+}
+
+/// Handler trait for output management protocol.
+pub trait OutputManagementHandler: Sized {
+    fn output_management_state(&mut self) -> &mut OutputManagementState;
+
+    /// A new head has been advertised.
+    fn new_head(&mut self, conn: &Connection, qh: &QueueHandle<Self>, head: ZwlrOutputHeadV1);
+
+    /// An existing head has changed.
+    fn update_head(&mut self, conn: &Connection, qh: &QueueHandle<Self>, head: ZwlrOutputHeadV1);
+
+    /// A head is no longer available.
+    ///
+    /// The info of the head is the one before it was removed.
+    fn head_removed(&mut self, conn: &Connection, qh: &QueueHandle<Self>, head: ZwlrOutputHeadV1);
+
+    /// A configuration sent with [`OutputConfiguration::test`] or [`OutputConfiguration::apply`]
+    /// was handled.
+    fn configuration_result(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        configuration: &ZwlrOutputConfigurationV1,
+        result: ConfigurationResult,
+    );
+
+    fn finished(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    New,
+    Updated,
+    Removed,
+    /// Removed before it was ever announced.
+    Discarded,
+}
+
+#[doc(hidden)]
+#[derive(Debug, Default)]
+pub struct HeadData {
+    inner: Mutex<HeadInner>,
+}
+
+#[derive(Debug, Default)]
+struct HeadInner {
+    current_info: Option<HeadInfo>,
+    /// The pending info, except for its modes.
+    pending_info: HeadInfo,
+    modes: Vec<ZwlrOutputModeV1>,
+    finished: bool,
+}
+
+impl HeadInner {
+    fn apply(&mut self) -> Option<Change> {
+        if self.finished {
+            return Some(if self.current_info.is_some() {
+                Change::Removed
+            } else {
+                Change::Discarded
+            });
+        }
+
+        self.modes.retain(|mode| {
+            let finished =
+                mode.data::<ModeData>().is_none_or(|data| data.inner.lock().unwrap().finished);
+            if finished && mode.version() >= 3 {
+                mode.release();
+            }
+            !finished
+        });
+        self.pending_info.modes = self
+            .modes
+            .iter()
+            .filter_map(|mode| {
+                let data = mode.data::<ModeData>()?.inner.lock().unwrap();
+                Some(HeadMode {
+                    mode: mode.clone(),
+                    dimensions: data.dimensions,
+                    refresh_rate: data.refresh_rate,
+                    preferred: data.preferred,
+                })
+            })
+            .collect();
+
+        let change = match &self.current_info {
+            None => Change::New,
+            Some(current) if *current != self.pending_info => Change::Updated,
+            Some(_) => return None,
+        };
+        self.current_info = Some(self.pending_info.clone());
+        Some(change)
+    }
+
+    /// Release the head and its modes.
+    fn release(&mut self, head: &ZwlrOutputHeadV1) {
+        if head.version() >= 3 {
+            for mode in self.modes.drain(..) {
+                mode.release();
+            }
+            head.release();
+        }
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug, Default)]
+pub struct ModeData {
+    inner: Mutex<ModeInner>,
+}
+
+#[derive(Debug, Default)]
+struct ModeInner {
+    dimensions: (i32, i32),
+    refresh_rate: i32,
+    preferred: bool,
+    finished: bool,
+}
+
+#[doc(hidden)]
+#[derive(Debug, Default)]
+pub struct OutputConfigurationData;
+
+impl<D> Dispatch2<ZwlrOutputManagerV1, D> for GlobalData
+where
+    D: Dispatch<ZwlrOutputHeadV1, HeadData> + OutputManagementHandler + 'static,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        _: &ZwlrOutputManagerV1,
+        event: zwlr_output_manager_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        match event {
+            zwlr_output_manager_v1::Event::Head { head } => {
+                state.output_management_state().new_heads.push(head);
+            }
+            zwlr_output_manager_v1::Event::Done { serial } => {
+                let management_state = state.output_management_state();
+                management_state.serial = Some(serial);
+                let new_heads = std::mem::take(&mut management_state.new_heads);
+                management_state.heads.extend(new_heads);
+
+                let mut changes = Vec::new();
+                management_state.heads.retain(|head| {
+                    let change = head.data::<HeadData>().unwrap().inner.lock().unwrap().apply();
+                    if let Some(change) = change {
+                        changes.push((change, head.clone()));
+                    }
+                    !matches!(change, Some(Change::Removed | Change::Discarded))
+                });
+
+                for (change, head) in changes {
+                    match change {
+                        Change::New => state.new_head(conn, qh, head.clone()),
+                        Change::Updated => state.update_head(conn, qh, head.clone()),
+                        Change::Removed => state.head_removed(conn, qh, head.clone()),
+                        Change::Discarded => (),
+                    }
+                    if matches!(change, Change::Removed | Change::Discarded) {
+                        head.data::<HeadData>().unwrap().inner.lock().unwrap().release(&head);
+                    }
+                }
+            }
+            zwlr_output_manager_v1::Event::Finished => {
+                // The manager is destroyed by the compositor.
+                state.finished(conn, qh);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    wayland_client::event_created_child!(D, ZwlrOutputManagerV1, [
+        zwlr_output_manager_v1::EVT_HEAD_OPCODE => (ZwlrOutputHeadV1, Default::default())
+    ]);
+}
+
+impl<D> Dispatch2<ZwlrOutputHeadV1, D> for HeadData
+where
+    D: Dispatch<ZwlrOutputModeV1, ModeData> + 'static,
+{
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ZwlrOutputHeadV1,
+        event: zwlr_output_head_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        use zwlr_output_head_v1::Event;
+
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+        let pending = &mut inner.pending_info;
+        match event {
+            Event::Name { name } => pending.name = name,
+            Event::Description { description } => pending.description = description,
+            Event::PhysicalSize { width, height } => pending.physical_size = (width, height),
+            Event::Mode { mode } => inner.modes.push(mode),
+            Event::Enabled { enabled } => pending.enabled = enabled != 0,
+            Event::CurrentMode { mode } => pending.current_mode = Some(mode),
+            Event::Position { x, y } => pending.position = (x, y),
+            Event::Transform { transform: WEnum::Value(transform) } => {
+                pending.transform = transform
+            }
+            Event::Transform { transform: WEnum::Unknown(_) } => {}
+            Event::Scale { scale } => pending.scale = scale,
+            Event::Finished => inner.finished = true,
+            Event::Make { make } => pending.make = make,
+            Event::Model { model } => pending.model = model,
+            Event::SerialNumber { serial_number } => pending.serial_number = Some(serial_number),
+            Event::AdaptiveSync { state } => {
+                pending.adaptive_sync = Some(state == WEnum::Value(AdaptiveSyncState::Enabled))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    wayland_client::event_created_child!(D, ZwlrOutputHeadV1, [
+        zwlr_output_head_v1::EVT_MODE_OPCODE => (ZwlrOutputModeV1, Default::default())
+    ]);
+}
+
+impl<D> Dispatch2<ZwlrOutputModeV1, D> for ModeData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ZwlrOutputModeV1,
+        event: zwlr_output_mode_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        match event {
+            zwlr_output_mode_v1::Event::Size { width, height } => {
+                inner.dimensions = (width, height)
+            }
+            zwlr_output_mode_v1::Event::Refresh { refresh } => inner.refresh_rate = refresh,
+            zwlr_output_mode_v1::Event::Preferred => inner.preferred = true,
+            zwlr_output_mode_v1::Event::Finished => inner.finished = true,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch2<ZwlrOutputConfigurationV1, D> for OutputConfigurationData
+where
+    D: OutputManagementHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        configuration: &ZwlrOutputConfigurationV1,
+        event: zwlr_output_configuration_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        let result = match event {
+            zwlr_output_configuration_v1::Event::Succeeded => ConfigurationResult::Succeeded,
+            zwlr_output_configuration_v1::Event::Failed => ConfigurationResult::Failed,
+            zwlr_output_configuration_v1::Event::Cancelled => ConfigurationResult::Cancelled,
+            _ => unreachable!(),
+        };
+
+        state.configuration_result(conn, qh, configuration, result);
+        configuration.destroy();
+    }
+}
+
+impl<D> Dispatch2<ZwlrOutputConfigurationHeadV1, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ZwlrOutputConfigurationHeadV1,
+        _: zwlr_output_configuration_head_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("zwlr_output_configuration_head_v1 has no events")
+    }
+}
@@ -47,6 +47,10 @@ impl PresentationTimeState {
         };
         Ok(self.presentation.get()?.feedback(surface, qh, udata))
     }
+
+    pub(crate) fn wp_presentation(&self) -> Result<&wp_presentation::WpPresentation, GlobalError> {
+        self.presentation.get()
+    }
 }
 
 pub trait PresentationTimeHandler: Sized {
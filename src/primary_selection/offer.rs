@@ -1,12 +1,16 @@
 use std::{
-    os::unix::io::{AsFd, OwnedFd},
+    os::unix::io::{BorrowedFd, OwnedFd},
     sync::Mutex,
 };
 
 use crate::reexports::client::{Connection, QueueHandle, Proxy};
 use crate::reexports::protocols::wp::primary_selection::zv1::client::zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1;
 
-use crate::data_device_manager::ReadPipe;
+use crate::data_device_manager::{
+    data_offer::{self, ReceiveOffer},
+    mime::{negotiate, DataFormat},
+    ReadPipe,
+};
 use crate::dispatch2::Dispatch2;
 
 /// Wrapper around the [`ZwpPrimarySelectionOfferV1`].
@@ -23,6 +27,11 @@ impl PrimarySelectionOffer {
         callback(mime_types.as_ref())
     }
 
+    /// Choose the mime type to receive, from the formats accepted in order of preference.
+    pub fn negotiate<'a>(&self, accepted: &'a [DataFormat]) -> Option<(&'a DataFormat, String)> {
+        self.with_mime_types(|offered| negotiate(accepted, offered))
+    }
+
     /// Request to receive the data of a given mime type.
     ///
     /// You can call this function several times.
@@ -35,13 +44,7 @@ impl PrimarySelectionOffer {
     /// Fails if too many file descriptors were already open and a pipe
     /// could not be created.
     pub fn receive(&self, mime_type: String) -> std::io::Result<ReadPipe> {
-        use rustix::pipe::{pipe_with, PipeFlags};
-        // create a pipe
-        let (readfd, writefd) = pipe_with(PipeFlags::CLOEXEC)?;
-
-        self.receive_to_fd(mime_type, writefd);
-
-        Ok(ReadPipe::from(readfd))
+        data_offer::receive(&self.offer, mime_type)
     }
 
     /// Request to receive the data of a given mime type, writen to `writefd`.
@@ -49,7 +52,13 @@ impl PrimarySelectionOffer {
     /// The provided file destructor must be a valid FD for writing, and will be closed
     /// once the contents are written.
     pub fn receive_to_fd(&self, mime_type: String, writefd: OwnedFd) {
-        self.offer.receive(mime_type, writefd.as_fd());
+        data_offer::receive_to_fd(&self.offer, mime_type, writefd);
+    }
+}
+
+impl ReceiveOffer for ZwpPrimarySelectionOfferV1 {
+    fn receive_fd(&self, mime_type: String, writefd: BorrowedFd<'_>) {
+        self.receive(mime_type, writefd);
     }
 }
 
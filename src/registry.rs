@@ -340,7 +340,7 @@ where
 ///
 /// This helper is intended to simplify the implementation of [RegistryHandler] for state objects
 /// that cache a bound global.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum GlobalProxy<I> {
     /// The requested global was not present after a complete enumeration.
     NotPresent,
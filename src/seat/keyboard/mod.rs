@@ -363,6 +363,10 @@ pub struct KeyboardData<D, U> {
     #[cfg(feature = "calloop")]
     repeat_data: Arc<Mutex<Option<RepeatData<D>>>>,
     focus: Mutex<Option<wl_surface::WlSurface>>,
+    /// The serial of the latest enter event for the keyboard
+    latest_enter: Mutex<Option<u32>>,
+    /// The serial of the latest key event for the keyboard
+    latest_key: Mutex<Option<u32>>,
     _phantom_data: PhantomData<D>,
     udata: U,
 }
@@ -391,6 +395,8 @@ impl<T, U> KeyboardData<T, U> {
             #[cfg(feature = "calloop")]
             repeat_data: Arc::new(Mutex::new(None)),
             focus: Mutex::new(None),
+            latest_enter: Mutex::new(None),
+            latest_key: Mutex::new(None),
             _phantom_data: PhantomData,
             udata,
         };
@@ -412,6 +418,16 @@ impl<T, U> KeyboardData<T, U> {
         &self.seat
     }
 
+    /// Serial from the latest [`KeyboardHandler::enter`] event.
+    pub fn latest_enter_serial(&self) -> Option<u32> {
+        *self.latest_enter.lock().unwrap()
+    }
+
+    /// Serial from the latest key press and release events.
+    pub fn latest_key_serial(&self) -> Option<u32> {
+        *self.latest_key.lock().unwrap()
+    }
+
     pub fn from_rmlvo(
         seat: wl_seat::WlSeat,
         rmlvo: RMLVO,
@@ -444,6 +460,8 @@ impl<T, U> KeyboardData<T, U> {
             #[cfg(feature = "calloop")]
             repeat_data: Arc::new(Mutex::new(None)),
             focus: Mutex::new(None),
+            latest_enter: Mutex::new(None),
+            latest_key: Mutex::new(None),
             _phantom_data: PhantomData,
             udata,
         };
@@ -580,6 +598,7 @@ where
             }
 
             wl_keyboard::Event::Enter { serial, surface, keys } => {
+                self.latest_enter.lock().unwrap().replace(serial);
                 let state_guard = self.xkb_state.lock().unwrap();
 
                 if let Some(guard) = state_guard.as_ref() {
@@ -632,6 +651,7 @@ where
 
             wl_keyboard::Event::Key { serial, time, key, state } => match state {
                 WEnum::Value(state) => {
+                    self.latest_key.lock().unwrap().replace(serial);
                     let state_guard = self.xkb_state.lock().unwrap();
 
                     if let Some(guard) = state_guard.as_ref() {
@@ -14,9 +14,16 @@ use crate::reexports::client::{
 };
 use crate::reexports::protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::WpCursorShapeDeviceV1;
 use crate::reexports::protocols::wp::cursor_shape::v1::client::wp_cursor_shape_manager_v1::WpCursorShapeManagerV1;
+use crate::reexports::protocols::wp::pointer_gestures::zv1::client::{
+    zwp_pointer_gesture_hold_v1::ZwpPointerGestureHoldV1,
+    zwp_pointer_gesture_pinch_v1::ZwpPointerGesturePinchV1,
+    zwp_pointer_gesture_swipe_v1::ZwpPointerGestureSwipeV1,
+    zwp_pointer_gestures_v1::ZwpPointerGesturesV1,
+};
 use crate::{
     compositor::SurfaceData,
     dispatch2::Dispatch2,
+    error::GlobalError,
     globals::GlobalData,
     registry::{ProvidesRegistryState, RegistryHandler},
 };
@@ -28,11 +35,18 @@ pub mod keyboard;
 pub mod keyboard_filter;
 pub mod pointer;
 pub mod pointer_constraints;
+pub mod pointer_gestures;
 pub mod relative_pointer;
+pub mod tablet;
+pub mod text_input;
 pub mod touch;
 
+#[cfg(test)]
+mod testing;
+
 use pointer::cursor_shape::CursorShapeManager;
 use pointer::{PointerData, PointerHandler, ThemeSpec, ThemedPointer, Themes};
+use pointer_gestures::{PointerGestureData, PointerGestures, PointerGesturesState};
 use touch::{TouchData, TouchHandler};
 
 #[non_exhaustive]
@@ -71,6 +85,7 @@ pub struct SeatState {
     // (name, seat)
     seats: Vec<SeatInner>,
     cursor_shape_manager_state: CursorShapeManagerState,
+    pointer_gestures_state: PointerGesturesManagerState,
 }
 
 #[derive(Debug)]
@@ -80,6 +95,13 @@ enum CursorShapeManagerState {
     Bound(CursorShapeManager),
 }
 
+#[derive(Debug)]
+enum PointerGesturesManagerState {
+    NotPresent,
+    Pending { registry: WlRegistry, global: Global },
+    Bound(PointerGesturesState),
+}
+
 impl SeatState {
     pub fn new<D: Dispatch<wl_seat::WlSeat, SeatData> + 'static>(
         global_list: &GlobalList,
@@ -110,8 +132,22 @@ impl SeatState {
             )
         });
 
-        let mut state =
-            SeatState { seats: vec![], cursor_shape_manager_state: cursor_shape_manager };
+        let pointer_gestures = global_list.contents().with_list(|globals| {
+            globals
+                .iter()
+                .find(|global| global.interface == ZwpPointerGesturesV1::interface().name)
+                .map(|global| PointerGesturesManagerState::Pending {
+                    registry: global_list.registry().clone(),
+                    global: global.clone(),
+                })
+                .unwrap_or(PointerGesturesManagerState::NotPresent)
+        });
+
+        let mut state = SeatState {
+            seats: vec![],
+            cursor_shape_manager_state: cursor_shape_manager,
+            pointer_gestures_state: pointer_gestures,
+        };
 
         for seat in seats {
             let data = seat.data::<SeatData>().unwrap().clone();
@@ -277,6 +313,51 @@ impl SeatState {
         })
     }
 
+    /// Creates the gesture objects of a pointer created from a seat.
+    ///
+    /// This binds the `zwp_pointer_gestures_v1` global the first time it is called, like the
+    /// cursor shape manager of [`SeatState::get_pointer_with_theme`]. The gestures are reported to
+    /// [`PointerGestureHandler`](pointer_gestures::PointerGestureHandler).
+    ///
+    /// ## Errors
+    ///
+    /// This will return [`GlobalError::MissingGlobal`] if the compositor does not support pointer
+    /// gestures.
+    pub fn get_pointer_gestures<D>(
+        &mut self,
+        qh: &QueueHandle<D>,
+        pointer: &wl_pointer::WlPointer,
+    ) -> Result<PointerGestures, GlobalError>
+    where
+        D: Dispatch<ZwpPointerGesturesV1, GlobalData>
+            + Dispatch<ZwpPointerGestureSwipeV1, PointerGestureData>
+            + Dispatch<ZwpPointerGesturePinchV1, PointerGestureData>
+            + Dispatch<ZwpPointerGestureHoldV1, PointerGestureData>
+            + 'static,
+    {
+        if let PointerGesturesManagerState::Pending { registry, global } =
+            &self.pointer_gestures_state
+        {
+            self.pointer_gestures_state = match crate::registry::bind_one(
+                registry,
+                slice::from_ref(global),
+                qh,
+                1..=3,
+                GlobalData,
+            ) {
+                Ok(bound) => {
+                    PointerGesturesManagerState::Bound(PointerGesturesState::from_existing(bound))
+                }
+                Err(_) => PointerGesturesManagerState::NotPresent,
+            }
+        }
+
+        match &self.pointer_gestures_state {
+            PointerGesturesManagerState::Bound(state) => state.get_pointer_gestures(pointer, qh),
+            _ => Err(GlobalError::MissingGlobal(ZwpPointerGesturesV1::interface().name)),
+        }
+    }
+
     /// Creates a touch handle from a seat.
     ///
     /// ## Errors
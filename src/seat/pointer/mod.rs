@@ -23,6 +23,8 @@ use crate::{compositor::SurfaceData, dispatch2::Dispatch2, error::GlobalError};
 pub use cursor_icon::{CursorIcon, ParseError as CursorIconParseError};
 
 pub mod cursor_shape;
+pub mod scroll;
+pub mod tracker;
 
 use cursor_shape::cursor_icon_to_shape;
 
@@ -0,0 +1,400 @@
+//! Normalization of scroll events, and kinetic scrolling.
+//!
+//! A [`ScrollNormalizer`] turns the [`PointerEventKind::Axis`] events of a pointer into
+//! [`ScrollEvent`]s: exact pixels for fingers and continuous sources, and whole steps for wheels.
+//!
+//! When fingers are lifted from a touchpad while scrolling, [`ScrollNormalizer::kinetic_scroll`]
+//! starts a [`KineticScroll`] which keeps scrolling and slows down, and can be driven by a calloop
+//! timer with [`KineticScroll::run`].
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use wayland_client::protocol::wl_pointer::AxisSource;
+
+use super::{AxisScroll, PointerEvent, PointerEventKind};
+
+/// The value of a wheel step in `value120` units.
+const STEP: i32 = 120;
+
+/// How long finger scroll events are used to compute the velocity when the fingers are lifted.
+const VELOCITY_WINDOW_MS: u32 = 100;
+
+/// The amount of a scroll along both axes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollDelta {
+    /// A scroll in surface-local pixels.
+    Pixels { x: f64, y: f64 },
+
+    /// A scroll in whole logical steps, such as wheel clicks.
+    Steps { x: i32, y: i32 },
+}
+
+/// The phase of a scroll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScrollPhase {
+    /// The scroll moved.
+    Update,
+
+    /// A continuous scroll ended, for example when the fingers were lifted from a touchpad.
+    End,
+
+    /// The scroll moved on its own after the fingers were lifted.
+    Kinetic,
+
+    /// The kinetic scroll stopped.
+    KineticEnd,
+}
+
+/// A normalized scroll event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollEvent {
+    pub delta: ScrollDelta,
+    pub phase: ScrollPhase,
+
+    /// The source of the scroll, if the compositor sent it.
+    pub source: Option<AxisSource>,
+}
+
+/// Converts axis events into [`ScrollEvent`]s.
+///
+/// Wheel scrolls are accumulated in `value120` units until they add up to whole steps, so high
+/// resolution wheels scroll by steps too.
+#[derive(Debug, Default)]
+pub struct ScrollNormalizer {
+    /// The partial wheel steps along both axes.
+    value120: (i32, i32),
+
+    /// The recent finger scrolls, as their time and pixels.
+    samples: VecDeque<(u32, f64, f64)>,
+
+    /// The velocity of the last finger scroll when the fingers were lifted, in pixels per second.
+    release_velocity: Option<(f64, f64)>,
+}
+
+impl ScrollNormalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Normalize a pointer event, which is `None` if it isn't a scroll.
+    pub fn process(&mut self, event: &PointerEvent) -> Option<ScrollEvent> {
+        match event.kind {
+            PointerEventKind::Axis { time, horizontal, vertical, source } => {
+                self.process_axis(time, horizontal, vertical, source)
+            }
+            _ => None,
+        }
+    }
+
+    /// Start a kinetic scroll with the velocity of the finger scroll that just ended.
+    ///
+    /// This is `None` unless the last event was the [`ScrollPhase::End`] of a finger scroll fast
+    /// enough for `config`.
+    pub fn kinetic_scroll(&self, config: KineticConfig) -> Option<KineticScroll> {
+        let velocity = self.release_velocity?;
+        (velocity.0.hypot(velocity.1) >= config.min_velocity)
+            .then(|| KineticScroll::new(velocity, config))
+    }
+
+    fn process_axis(
+        &mut self,
+        time: u32,
+        horizontal: AxisScroll,
+        vertical: AxisScroll,
+        source: Option<AxisSource>,
+    ) -> Option<ScrollEvent> {
+        self.release_velocity = None;
+
+        let is_wheel = match source {
+            Some(AxisSource::Wheel | AxisSource::WheelTilt) => true,
+            Some(_) => false,
+            // Older compositors don't send the source, but only wheels have steps.
+            None => {
+                [horizontal, vertical].iter().any(|axis| axis.value120 != 0 || axis.discrete != 0)
+            }
+        };
+
+        if is_wheel {
+            let x = accumulate_steps(&mut self.value120.0, &horizontal);
+            let y = accumulate_steps(&mut self.value120.1, &vertical);
+            if x == 0 && y == 0 {
+                return None;
+            }
+            return Some(ScrollEvent {
+                delta: ScrollDelta::Steps { x, y },
+                phase: ScrollPhase::Update,
+                source,
+            });
+        }
+
+        let (x, y) = (horizontal.absolute, vertical.absolute);
+        let stop = horizontal.stop || vertical.stop;
+
+        if source == Some(AxisSource::Finger) {
+            if stop {
+                self.release_velocity = Some(self.velocity(time));
+                self.samples.clear();
+            } else {
+                self.samples.push_back((time, x, y));
+                while self
+                    .samples
+                    .front()
+                    .is_some_and(|&(sample, ..)| time.wrapping_sub(sample) > VELOCITY_WINDOW_MS)
+                {
+                    self.samples.pop_front();
+                }
+            }
+        }
+
+        if !stop && x == 0. && y == 0. {
+            return None;
+        }
+        Some(ScrollEvent {
+            delta: ScrollDelta::Pixels { x, y },
+            phase: if stop { ScrollPhase::End } else { ScrollPhase::Update },
+            source,
+        })
+    }
+
+    /// The velocity of the recent finger scrolls at `time`, in pixels per second.
+    fn velocity(&self, time: u32) -> (f64, f64) {
+        let recent = self
+            .samples
+            .iter()
+            .filter(|&&(sample, ..)| time.wrapping_sub(sample) <= VELOCITY_WINDOW_MS);
+        let Some(&(first, ..)) = recent.clone().next() else {
+            return (0., 0.);
+        };
+
+        let (x, y) = recent.fold((0., 0.), |(x, y), &(_, dx, dy)| (x + dx, y + dy));
+        // Scrolls sent at the same time as the stop still have a duration of one event.
+        let seconds = f64::from(time.wrapping_sub(first).max(1)) / 1000.;
+        (x / seconds, y / seconds)
+    }
+}
+
+/// Add the `value120` of a wheel scroll to the partial steps, and take the whole steps.
+fn accumulate_steps(value120: &mut i32, axis: &AxisScroll) -> i32 {
+    let value = if axis.value120 != 0 { axis.value120 } else { axis.discrete * STEP };
+
+    // Scrolling in the other direction drops the partial step.
+    if value.signum() == -value120.signum() {
+        *value120 = 0;
+    }
+    *value120 += value;
+
+    let steps = *value120 / STEP;
+    *value120 %= STEP;
+    steps
+}
+
+/// The behavior of kinetic scrolling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KineticConfig {
+    /// The velocity under which kinetic scrolling doesn't start or stops, in pixels per second.
+    pub min_velocity: f64,
+
+    /// How fast the velocity decreases, as an exponential decay rate per second.
+    pub deceleration: f64,
+
+    /// The interval between kinetic scroll events.
+    pub interval: Duration,
+}
+
+impl Default for KineticConfig {
+    fn default() -> Self {
+        Self { min_velocity: 50., deceleration: 4., interval: Duration::from_millis(16) }
+    }
+}
+
+/// A scroll which continues after the fingers were lifted, and slows down until it stops.
+#[derive(Debug, Clone)]
+pub struct KineticScroll {
+    velocity: (f64, f64),
+    config: KineticConfig,
+    finished: bool,
+}
+
+impl KineticScroll {
+    /// Start a kinetic scroll with an initial `velocity`, in pixels per second.
+    pub fn new(velocity: (f64, f64), config: KineticConfig) -> Self {
+        Self { velocity, config, finished: false }
+    }
+
+    /// The current velocity, in pixels per second.
+    pub fn velocity(&self) -> (f64, f64) {
+        self.velocity
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Advance the scroll by `elapsed`, returning how far it moved.
+    ///
+    /// The event has the [`ScrollPhase::KineticEnd`] phase once the velocity is too low, and the
+    /// scroll is finished.
+    pub fn step(&mut self, elapsed: Duration) -> ScrollEvent {
+        let decay = (-self.config.deceleration * elapsed.as_secs_f64()).exp();
+
+        // The distance is the integral of the velocity over the elapsed time.
+        let distance = |velocity: f64| match self.config.deceleration {
+            deceleration if deceleration > 0. => velocity * (1. - decay) / deceleration,
+            _ => velocity * elapsed.as_secs_f64(),
+        };
+        let delta =
+            ScrollDelta::Pixels { x: distance(self.velocity.0), y: distance(self.velocity.1) };
+
+        self.velocity = (self.velocity.0 * decay, self.velocity.1 * decay);
+        self.finished = self.velocity.0.hypot(self.velocity.1) < self.config.min_velocity;
+
+        ScrollEvent {
+            delta,
+            phase: if self.finished { ScrollPhase::KineticEnd } else { ScrollPhase::Kinetic },
+            source: Some(AxisSource::Finger),
+        }
+    }
+}
+
+#[cfg(feature = "calloop")]
+mod kinetic_loop {
+    use std::{cell::Cell, io, rc::Rc, time::Instant};
+
+    use calloop::{
+        timer::{TimeoutAction, Timer},
+        LoopHandle, RegistrationToken,
+    };
+
+    use super::{KineticScroll, ScrollEvent};
+
+    impl KineticScroll {
+        /// Run the scroll on a timer of the event loop, calling `callback` with each event until
+        /// it is finished or cancelled.
+        pub fn run<D, F>(
+            mut self,
+            loop_handle: &LoopHandle<'static, D>,
+            mut callback: F,
+        ) -> io::Result<KineticScrollHandle<D>>
+        where
+            D: 'static,
+            F: FnMut(&mut D, ScrollEvent) + 'static,
+        {
+            let interval = self.config.interval;
+            let running = Rc::new(Cell::new(true));
+            let mut last = Instant::now();
+
+            let timer_running = running.clone();
+            let token = loop_handle
+                .insert_source(Timer::from_duration(interval), move |deadline, _, state| {
+                    let event = self.step(deadline.saturating_duration_since(last));
+                    last = deadline;
+                    callback(state, event);
+
+                    if self.is_finished() {
+                        timer_running.set(false);
+                        TimeoutAction::Drop
+                    } else {
+                        TimeoutAction::ToDuration(interval)
+                    }
+                })
+                .map_err(|err| err.error)?;
+
+            Ok(KineticScrollHandle { loop_handle: loop_handle.clone(), token, running })
+        }
+    }
+
+    /// A kinetic scroll running on an event loop.
+    ///
+    /// The scroll should be cancelled when a new scroll starts.
+    #[derive(Debug)]
+    pub struct KineticScrollHandle<D> {
+        loop_handle: LoopHandle<'static, D>,
+        token: RegistrationToken,
+        running: Rc<Cell<bool>>,
+    }
+
+    impl<D> KineticScrollHandle<D> {
+        /// Whether the scroll is still running.
+        pub fn is_running(&self) -> bool {
+            self.running.get()
+        }
+
+        /// Stop the scroll without a [`ScrollPhase::KineticEnd`](super::ScrollPhase::KineticEnd)
+        /// event.
+        pub fn cancel(self) {
+            if self.running.replace(false) {
+                self.loop_handle.remove(self.token);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "calloop")]
+pub use kinetic_loop::KineticScrollHandle;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn wheel(value120: i32) -> AxisScroll {
+        AxisScroll { value120, absolute: f64::from(value120) / 8., ..Default::default() }
+    }
+
+    fn finger(absolute: f64, stop: bool) -> AxisScroll {
+        AxisScroll { absolute, stop, ..Default::default() }
+    }
+
+    #[test]
+    fn wheel_steps() {
+        let mut normalizer = ScrollNormalizer::new();
+        let source = Some(AxisSource::Wheel);
+
+        assert_eq!(normalizer.process_axis(0, wheel(0), wheel(60), source), None);
+        let event = normalizer.process_axis(0, wheel(0), wheel(90), source).unwrap();
+        assert_eq!(event.delta, ScrollDelta::Steps { x: 0, y: 1 });
+
+        // The partial step is dropped when scrolling back.
+        let event = normalizer.process_axis(0, wheel(0), wheel(-120), source).unwrap();
+        assert_eq!(event.delta, ScrollDelta::Steps { x: 0, y: -1 });
+
+        // Discrete steps are used without a source or `value120`.
+        let axis = AxisScroll { discrete: 2, absolute: 20., ..Default::default() };
+        let event = normalizer.process_axis(0, axis, AxisScroll::default(), None).unwrap();
+        assert_eq!(event.delta, ScrollDelta::Steps { x: 2, y: 0 });
+    }
+
+    #[test]
+    fn finger_release() {
+        let mut normalizer = ScrollNormalizer::new();
+        let source = Some(AxisSource::Finger);
+        let none = AxisScroll::default();
+
+        for time in [0, 10, 20] {
+            let event = normalizer.process_axis(time, none, finger(5., false), source).unwrap();
+            assert_eq!(event.delta, ScrollDelta::Pixels { x: 0., y: 5. });
+            assert_eq!(event.phase, ScrollPhase::Update);
+        }
+        let event = normalizer.process_axis(30, none, finger(0., true), source).unwrap();
+        assert_eq!(event.phase, ScrollPhase::End);
+
+        let config = KineticConfig::default();
+        let mut kinetic = normalizer.kinetic_scroll(config).unwrap();
+        assert_eq!(kinetic.velocity(), (0., 500.));
+
+        let event = kinetic.step(Duration::from_millis(16));
+        let ScrollDelta::Pixels { x, y } = event.delta else { panic!() };
+        assert_eq!(x, 0.);
+        assert!(y > 7. && y < 8.);
+        assert_eq!(event.phase, ScrollPhase::Kinetic);
+
+        let event = kinetic.step(Duration::from_secs(1));
+        assert_eq!(event.phase, ScrollPhase::KineticEnd);
+        assert!(kinetic.is_finished());
+
+        // Lifting the fingers after they stopped doesn't scroll.
+        normalizer.process_axis(100, none, finger(5., false), source);
+        normalizer.process_axis(300, none, finger(0., true), source);
+        assert!(normalizer.kinetic_scroll(config).is_none());
+    }
+}
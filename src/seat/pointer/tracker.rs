@@ -0,0 +1,344 @@
+//! Tracking of pointer buttons, to count clicks and recognize drags.
+//!
+//! A [`PointerTracker`] is given the events of a pointer, from
+//! [`PointerHandler::pointer_frame`](super::PointerHandler::pointer_frame), and reports them as
+//! higher level [`PointerAction`]s: the surface under the pointer, presses and clicks with their
+//! click count for double and triple clicks, and drags once the pointer moved far enough with a
+//! button held.
+//!
+//! Each pointer should have its own tracker.
+
+use std::time::Duration;
+
+use wayland_client::protocol::wl_surface::WlSurface;
+
+use super::{PointerEvent, PointerEventKind};
+
+/// The thresholds used to count clicks and recognize drags.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClickConfig {
+    /// The maximum time between the presses of a double click.
+    pub double_click_time: Duration,
+
+    /// The maximum distance between the presses of a double click, in surface-local coordinates.
+    pub double_click_distance: f64,
+
+    /// The distance the pointer must move with a button held to start a drag, in surface-local
+    /// coordinates.
+    pub drag_threshold: f64,
+}
+
+impl Default for ClickConfig {
+    fn default() -> Self {
+        Self {
+            double_click_time: Duration::from_millis(400),
+            double_click_distance: 5.,
+            drag_threshold: 8.,
+        }
+    }
+}
+
+/// A higher level pointer event.
+///
+/// Positions are surface-local.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointerAction {
+    /// The pointer entered a surface.
+    Enter { surface: WlSurface, position: (f64, f64) },
+
+    /// The pointer left a surface.
+    Leave { surface: WlSurface },
+
+    /// A button was pressed, as the `count`th click in a row, such as `2` for a double click.
+    Press { surface: WlSurface, position: (f64, f64), button: u32, count: u32 },
+
+    /// A button was released without dragging.
+    Click { surface: WlSurface, position: (f64, f64), button: u32, count: u32 },
+
+    /// The pointer moved far enough with a button held to start a drag from `start`.
+    DragStart { surface: WlSurface, button: u32, start: (f64, f64), position: (f64, f64) },
+
+    /// The pointer moved during a drag.
+    DragMove { surface: WlSurface, button: u32, start: (f64, f64), position: (f64, f64) },
+
+    /// The button of a drag was released, or the pointer left the surface.
+    DragEnd { surface: WlSurface, button: u32, start: (f64, f64), position: (f64, f64) },
+}
+
+/// The state of a pointer, for click counting and drags.
+#[derive(Debug)]
+pub struct PointerTracker {
+    config: ClickConfig,
+    focus: Option<(WlSurface, (f64, f64))>,
+    buttons: Vec<HeldButton>,
+    last_press: Option<LastPress>,
+}
+
+#[derive(Debug)]
+struct HeldButton {
+    button: u32,
+    surface: WlSurface,
+    start: (f64, f64),
+    count: u32,
+    dragging: bool,
+}
+
+#[derive(Debug)]
+struct LastPress {
+    button: u32,
+    surface: WlSurface,
+    position: (f64, f64),
+    time: u32,
+    count: u32,
+}
+
+impl PointerTracker {
+    pub fn new(config: ClickConfig) -> Self {
+        Self { config, focus: None, buttons: Vec::new(), last_press: None }
+    }
+
+    pub fn config(&self) -> &ClickConfig {
+        &self.config
+    }
+
+    /// The surface under the pointer, and the position of the pointer on it.
+    pub fn hovered(&self) -> Option<(&WlSurface, (f64, f64))> {
+        self.focus.as_ref().map(|(surface, position)| (surface, *position))
+    }
+
+    /// Whether `button` is held.
+    pub fn is_pressed(&self, button: u32) -> bool {
+        self.buttons.iter().any(|held| held.button == button)
+    }
+
+    /// The buttons held, in the order they were pressed.
+    pub fn pressed_buttons(&self) -> impl Iterator<Item = u32> + '_ {
+        self.buttons.iter().map(|held| held.button)
+    }
+
+    /// Whether a drag is in progress.
+    pub fn is_dragging(&self) -> bool {
+        self.buttons.iter().any(|held| held.dragging)
+    }
+
+    /// Process the events of a pointer frame.
+    pub fn process_frame(&mut self, events: &[PointerEvent]) -> Vec<PointerAction> {
+        let mut actions = Vec::new();
+        for event in events {
+            self.process(event, &mut actions);
+        }
+        actions
+    }
+
+    fn process(&mut self, event: &PointerEvent, actions: &mut Vec<PointerAction>) {
+        let surface = &event.surface;
+        let position = event.position;
+
+        match event.kind {
+            PointerEventKind::Enter { .. } => {
+                self.focus = Some((surface.clone(), position));
+                actions.push(PointerAction::Enter { surface: surface.clone(), position });
+            }
+            PointerEventKind::Leave { .. } => {
+                // Buttons released outside of the surface are not reported to it.
+                let last_position =
+                    self.focus.take().map(|(_, position)| position).unwrap_or(position);
+                self.buttons.retain(|held| {
+                    if held.surface != *surface {
+                        return true;
+                    }
+                    if held.dragging {
+                        actions.push(PointerAction::DragEnd {
+                            surface: surface.clone(),
+                            button: held.button,
+                            start: held.start,
+                            position: last_position,
+                        });
+                    }
+                    false
+                });
+                actions.push(PointerAction::Leave { surface: surface.clone() });
+            }
+            PointerEventKind::Motion { .. } => {
+                self.focus = Some((surface.clone(), position));
+                for held in &mut self.buttons {
+                    let start_drag = !held.dragging
+                        && distance(held.start, position) > self.config.drag_threshold;
+                    if start_drag {
+                        held.dragging = true;
+                        // A press after a drag starts a new series of clicks.
+                        self.last_press = None;
+                        actions.push(PointerAction::DragStart {
+                            surface: held.surface.clone(),
+                            button: held.button,
+                            start: held.start,
+                            position,
+                        });
+                    } else if held.dragging {
+                        actions.push(PointerAction::DragMove {
+                            surface: held.surface.clone(),
+                            button: held.button,
+                            start: held.start,
+                            position,
+                        });
+                    }
+                }
+            }
+            PointerEventKind::Press { time, button, .. } => {
+                let count = match &self.last_press {
+                    Some(last)
+                        if last.button == button
+                            && last.surface == *surface
+                            && time.wrapping_sub(last.time) as u128
+                                <= self.config.double_click_time.as_millis()
+                            && distance(last.position, position)
+                                <= self.config.double_click_distance =>
+                    {
+                        last.count + 1
+                    }
+                    _ => 1,
+                };
+                self.last_press =
+                    Some(LastPress { button, surface: surface.clone(), position, time, count });
+
+                self.buttons.retain(|held| held.button != button);
+                self.buttons.push(HeldButton {
+                    button,
+                    surface: surface.clone(),
+                    start: position,
+                    count,
+                    dragging: false,
+                });
+                actions.push(PointerAction::Press {
+                    surface: surface.clone(),
+                    position,
+                    button,
+                    count,
+                });
+            }
+            PointerEventKind::Release { button, .. } => {
+                let Some(index) = self.buttons.iter().position(|held| held.button == button) else {
+                    return;
+                };
+                let held = self.buttons.remove(index);
+                if held.dragging {
+                    actions.push(PointerAction::DragEnd {
+                        surface: held.surface,
+                        button,
+                        start: held.start,
+                        position,
+                    });
+                } else {
+                    actions.push(PointerAction::Click {
+                        surface: held.surface,
+                        position,
+                        button,
+                        count: held.count,
+                    });
+                }
+            }
+            PointerEventKind::Axis { .. } => {}
+        }
+    }
+}
+
+impl Default for PointerTracker {
+    fn default() -> Self {
+        Self::new(ClickConfig::default())
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::BTN_LEFT;
+    use super::*;
+    use crate::seat::testing::{pointer_event, press, release, surface};
+
+    fn counts(actions: &[PointerAction]) -> Vec<(bool, u32)> {
+        actions
+            .iter()
+            .filter_map(|action| match action {
+                PointerAction::Press { count, .. } => Some((true, *count)),
+                PointerAction::Click { count, .. } => Some((false, *count)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn click_count() {
+        let (surface, _server) = surface();
+        let mut tracker = PointerTracker::default();
+
+        let actions = tracker.process_frame(&[
+            press(&surface, (10., 10.), 0),
+            release(&surface, (10., 10.), 50),
+            press(&surface, (12., 10.), 200),
+            release(&surface, (12., 10.), 250),
+            press(&surface, (12., 10.), 500),
+            release(&surface, (12., 10.), 550),
+        ]);
+        assert_eq!(
+            counts(&actions),
+            [(true, 1), (false, 1), (true, 2), (false, 2), (true, 3), (false, 3)]
+        );
+
+        // Too late, then too far.
+        let actions = tracker.process_frame(&[
+            press(&surface, (12., 10.), 1000),
+            release(&surface, (12., 10.), 1050),
+            press(&surface, (30., 10.), 1100),
+        ]);
+        assert_eq!(counts(&actions), [(true, 1), (false, 1), (true, 1)]);
+        assert!(tracker.is_pressed(BTN_LEFT));
+    }
+
+    #[test]
+    fn drag() {
+        let (surface, _server) = surface();
+        let mut tracker = PointerTracker::default();
+        let motion =
+            |position| pointer_event(&surface, position, PointerEventKind::Motion { time: 0 });
+
+        let actions = tracker.process_frame(&[
+            press(&surface, (0., 0.), 0),
+            motion((5., 0.)),
+            motion((20., 0.)),
+            motion((30., 0.)),
+            release(&surface, (30., 0.), 100),
+        ]);
+        let start = (0., 0.);
+        assert_eq!(
+            actions[1..],
+            [
+                PointerAction::DragStart {
+                    surface: surface.clone(),
+                    button: BTN_LEFT,
+                    start,
+                    position: (20., 0.)
+                },
+                PointerAction::DragMove {
+                    surface: surface.clone(),
+                    button: BTN_LEFT,
+                    start,
+                    position: (30., 0.)
+                },
+                PointerAction::DragEnd {
+                    surface: surface.clone(),
+                    button: BTN_LEFT,
+                    start,
+                    position: (30., 0.)
+                },
+            ]
+        );
+        assert!(!tracker.is_dragging());
+
+        // A quick press back at the start of the drag is not a double click.
+        let actions = tracker.process_frame(&[press(&surface, (0., 0.), 150)]);
+        assert_eq!(counts(&actions), [(true, 1)]);
+    }
+}
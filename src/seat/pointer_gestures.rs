@@ -0,0 +1,294 @@
+//! Touchpad gestures, using the `pointer-gestures-unstable-v1` protocol.
+//!
+//! Gestures are reported for a `wl_pointer`, and are created with
+//! [`SeatState::get_pointer_gestures`](crate::seat::SeatState::get_pointer_gestures) once the
+//! pointer has been created from the seat. [`PointerGesturesState`] may be used instead to bind the
+//! global separately.
+
+use wayland_client::{
+    globals::GlobalList,
+    protocol::{wl_pointer, wl_surface},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols::wp::pointer_gestures::zv1::client::{
+    zwp_pointer_gesture_hold_v1, zwp_pointer_gesture_pinch_v1, zwp_pointer_gesture_swipe_v1,
+    zwp_pointer_gestures_v1,
+};
+
+use crate::{
+    dispatch2::Dispatch2,
+    error::GlobalError,
+    globals::{GlobalData, ProvidesBoundGlobal},
+    registry::GlobalProxy,
+};
+
+#[derive(Debug)]
+pub struct PointerGesturesState {
+    pointer_gestures: GlobalProxy<zwp_pointer_gestures_v1::ZwpPointerGesturesV1>,
+}
+
+impl PointerGesturesState {
+    /// Bind `zwp_pointer_gestures_v1` global, if it exists
+    pub fn bind<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<zwp_pointer_gestures_v1::ZwpPointerGesturesV1, GlobalData> + 'static,
+    {
+        let pointer_gestures = GlobalProxy::from(globals.bind(qh, 1..=3, GlobalData));
+        Self { pointer_gestures }
+    }
+
+    pub(crate) fn from_existing(
+        pointer_gestures: zwp_pointer_gestures_v1::ZwpPointerGesturesV1,
+    ) -> Self {
+        Self { pointer_gestures: GlobalProxy::Bound(pointer_gestures) }
+    }
+
+    /// Get the gestures of a pointer.
+    ///
+    /// Hold gestures are only available if the compositor supports version 3 of the protocol.
+    pub fn get_pointer_gestures<D>(
+        &self,
+        pointer: &wl_pointer::WlPointer,
+        qh: &QueueHandle<D>,
+    ) -> Result<PointerGestures, GlobalError>
+    where
+        D: Dispatch<zwp_pointer_gesture_swipe_v1::ZwpPointerGestureSwipeV1, PointerGestureData>
+            + Dispatch<zwp_pointer_gesture_pinch_v1::ZwpPointerGesturePinchV1, PointerGestureData>
+            + Dispatch<zwp_pointer_gesture_hold_v1::ZwpPointerGestureHoldV1, PointerGestureData>
+            + 'static,
+    {
+        let pointer_gestures = self.pointer_gestures.get()?;
+        let udata = || PointerGestureData { pointer: pointer.clone() };
+
+        let swipe = pointer_gestures.get_swipe_gesture(pointer, qh, udata());
+        let pinch = pointer_gestures.get_pinch_gesture(pointer, qh, udata());
+        let hold = (pointer_gestures.version() >= 3)
+            .then(|| pointer_gestures.get_hold_gesture(pointer, qh, udata()));
+
+        Ok(PointerGestures { swipe, pinch, hold })
+    }
+}
+
+impl ProvidesBoundGlobal<zwp_pointer_gestures_v1::ZwpPointerGesturesV1, 3>
+    for PointerGesturesState
+{
+    fn bound_global(&self) -> Result<zwp_pointer_gestures_v1::ZwpPointerGesturesV1, GlobalError> {
+        self.pointer_gestures.get().cloned()
+    }
+}
+
+/// The gesture objects of a pointer.
+///
+/// This destroys the gesture objects on drop.
+#[derive(Debug)]
+pub struct PointerGestures {
+    swipe: zwp_pointer_gesture_swipe_v1::ZwpPointerGestureSwipeV1,
+    pinch: zwp_pointer_gesture_pinch_v1::ZwpPointerGesturePinchV1,
+    hold: Option<zwp_pointer_gesture_hold_v1::ZwpPointerGestureHoldV1>,
+}
+
+impl PointerGestures {
+    pub fn swipe(&self) -> &zwp_pointer_gesture_swipe_v1::ZwpPointerGestureSwipeV1 {
+        &self.swipe
+    }
+
+    pub fn pinch(&self) -> &zwp_pointer_gesture_pinch_v1::ZwpPointerGesturePinchV1 {
+        &self.pinch
+    }
+
+    pub fn hold(&self) -> Option<&zwp_pointer_gesture_hold_v1::ZwpPointerGestureHoldV1> {
+        self.hold.as_ref()
+    }
+}
+
+impl Drop for PointerGestures {
+    fn drop(&mut self) {
+        self.swipe.destroy();
+        self.pinch.destroy();
+        if let Some(hold) = &self.hold {
+            hold.destroy();
+        }
+    }
+}
+
+/// A touchpad gesture event.
+#[derive(Debug, Clone)]
+pub enum GestureEvent {
+    Swipe(SwipeEvent),
+    Pinch(PinchEvent),
+    Hold(HoldEvent),
+}
+
+/// A multi-finger swipe.
+#[derive(Debug, Clone)]
+pub enum SwipeEvent {
+    Begin {
+        serial: u32,
+        time: u32,
+        surface: wl_surface::WlSurface,
+        fingers: u32,
+    },
+    Update {
+        time: u32,
+        /// Motion of the logical center of the gesture, in surface coordinates.
+        delta: (f64, f64),
+    },
+    End {
+        serial: u32,
+        time: u32,
+        /// The gesture was cancelled, and should be undone.
+        cancelled: bool,
+    },
+}
+
+/// A multi-finger pinch and rotate.
+#[derive(Debug, Clone)]
+pub enum PinchEvent {
+    Begin {
+        serial: u32,
+        time: u32,
+        surface: wl_surface::WlSurface,
+        fingers: u32,
+    },
+    Update {
+        time: u32,
+        /// Motion of the logical center of the gesture, in surface coordinates.
+        delta: (f64, f64),
+        /// Absolute scale compared to the start of the gesture.
+        scale: f64,
+        /// Rotation in degrees clockwise, relative to the previous update.
+        rotation: f64,
+    },
+    End {
+        serial: u32,
+        time: u32,
+        /// The gesture was cancelled, and should be undone.
+        cancelled: bool,
+    },
+}
+
+/// Fingers resting on the touchpad without moving.
+///
+/// A hold which is cancelled was interrupted by another gesture or by movement of the pointer,
+/// which is useful to stop kinetic scrolling.
+#[derive(Debug, Clone)]
+pub enum HoldEvent {
+    Begin { serial: u32, time: u32, surface: wl_surface::WlSurface, fingers: u32 },
+    End { serial: u32, time: u32, cancelled: bool },
+}
+
+pub trait PointerGestureHandler: Sized {
+    fn pointer_gesture(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        pointer: &wl_pointer::WlPointer,
+        event: GestureEvent,
+    );
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct PointerGestureData {
+    pointer: wl_pointer::WlPointer,
+}
+
+impl<D> Dispatch2<zwp_pointer_gestures_v1::ZwpPointerGesturesV1, D> for GlobalData
+where
+    D: PointerGestureHandler,
+{
+    fn event(
+        &self,
+        _: &mut D,
+        _: &zwp_pointer_gestures_v1::ZwpPointerGesturesV1,
+        _: zwp_pointer_gestures_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("zwp_pointer_gestures_v1 has no events")
+    }
+}
+
+impl<D> Dispatch2<zwp_pointer_gesture_swipe_v1::ZwpPointerGestureSwipeV1, D> for PointerGestureData
+where
+    D: PointerGestureHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        _: &zwp_pointer_gesture_swipe_v1::ZwpPointerGestureSwipeV1,
+        event: zwp_pointer_gesture_swipe_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        let event = match event {
+            zwp_pointer_gesture_swipe_v1::Event::Begin { serial, time, surface, fingers } => {
+                SwipeEvent::Begin { serial, time, surface, fingers }
+            }
+            zwp_pointer_gesture_swipe_v1::Event::Update { time, dx, dy } => {
+                SwipeEvent::Update { time, delta: (dx, dy) }
+            }
+            zwp_pointer_gesture_swipe_v1::Event::End { serial, time, cancelled } => {
+                SwipeEvent::End { serial, time, cancelled: cancelled != 0 }
+            }
+            _ => unreachable!(),
+        };
+
+        state.pointer_gesture(conn, qh, &self.pointer, GestureEvent::Swipe(event));
+    }
+}
+
+impl<D> Dispatch2<zwp_pointer_gesture_pinch_v1::ZwpPointerGesturePinchV1, D> for PointerGestureData
+where
+    D: PointerGestureHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        _: &zwp_pointer_gesture_pinch_v1::ZwpPointerGesturePinchV1,
+        event: zwp_pointer_gesture_pinch_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        let event = match event {
+            zwp_pointer_gesture_pinch_v1::Event::Begin { serial, time, surface, fingers } => {
+                PinchEvent::Begin { serial, time, surface, fingers }
+            }
+            zwp_pointer_gesture_pinch_v1::Event::Update { time, dx, dy, scale, rotation } => {
+                PinchEvent::Update { time, delta: (dx, dy), scale, rotation }
+            }
+            zwp_pointer_gesture_pinch_v1::Event::End { serial, time, cancelled } => {
+                PinchEvent::End { serial, time, cancelled: cancelled != 0 }
+            }
+            _ => unreachable!(),
+        };
+
+        state.pointer_gesture(conn, qh, &self.pointer, GestureEvent::Pinch(event));
+    }
+}
+
+impl<D> Dispatch2<zwp_pointer_gesture_hold_v1::ZwpPointerGestureHoldV1, D> for PointerGestureData
+where
+    D: PointerGestureHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        _: &zwp_pointer_gesture_hold_v1::ZwpPointerGestureHoldV1,
+        event: zwp_pointer_gesture_hold_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        let event = match event {
+            zwp_pointer_gesture_hold_v1::Event::Begin { serial, time, surface, fingers } => {
+                HoldEvent::Begin { serial, time, surface, fingers }
+            }
+            zwp_pointer_gesture_hold_v1::Event::End { serial, time, cancelled } => {
+                HoldEvent::End { serial, time, cancelled: cancelled != 0 }
+            }
+            _ => unreachable!(),
+        };
+
+        state.pointer_gesture(conn, qh, &self.pointer, GestureEvent::Hold(event));
+    }
+}
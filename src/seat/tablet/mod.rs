@@ -0,0 +1,253 @@
+//! Graphics tablets, using the `tablet-v2` protocol.
+//!
+//! Tablets are not advertised as a capability of the `wl_seat`. Instead, a tablet seat is created
+//! for each seat with [`TabletState::get_tablet_seat`], and the compositor announces the tablets,
+//! tools and pads of the seat through it.
+//!
+//! - A tablet ([`ZwpTabletV2`]) is the device the tools are used on, and is reported to
+//!   [`TabletHandler`].
+//! - A tool ([`ZwpTabletToolV2`]) is a pen, eraser, mouse, etc. used on a tablet, and is reported to
+//!   [`TabletToolHandler`](tool::TabletToolHandler).
+//! - A pad ([`ZwpTabletPadV2`]) is the set of buttons, rings and strips on a tablet, and is reported
+//!   to [`TabletPadHandler`](pad::TabletPadHandler).
+//!
+//! The objects announced by the compositor are destroyed automatically when they are removed.
+
+use std::sync::Mutex;
+
+use wayland_client::{
+    globals::GlobalList, protocol::wl_seat::WlSeat, Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols::wp::tablet::zv2::client::{
+    zwp_tablet_manager_v2::{self, ZwpTabletManagerV2},
+    zwp_tablet_pad_v2::ZwpTabletPadV2,
+    zwp_tablet_seat_v2::{self, ZwpTabletSeatV2},
+    zwp_tablet_tool_v2::ZwpTabletToolV2,
+    zwp_tablet_v2::{self, ZwpTabletV2},
+};
+
+use crate::{
+    dispatch2::Dispatch2,
+    error::GlobalError,
+    globals::{GlobalData, ProvidesBoundGlobal},
+    registry::GlobalProxy,
+};
+
+pub mod pad;
+pub mod tool;
+
+use pad::TabletPadData;
+use tool::TabletToolData;
+
+#[derive(Debug)]
+pub struct TabletState {
+    tablet_manager: GlobalProxy<ZwpTabletManagerV2>,
+}
+
+impl TabletState {
+    /// Bind `zwp_tablet_manager_v2` global, if it exists
+    pub fn bind<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<ZwpTabletManagerV2, GlobalData> + 'static,
+    {
+        let tablet_manager = GlobalProxy::from(globals.bind(qh, 1..=1, GlobalData));
+        Self { tablet_manager }
+    }
+
+    /// Get the tablet seat of a seat.
+    ///
+    /// The tablets, tools and pads of the seat will be announced through the tablet seat.
+    ///
+    /// Returns error if `zwp_tablet_manager_v2` global is not present.
+    pub fn get_tablet_seat<D>(
+        &self,
+        qh: &QueueHandle<D>,
+        seat: &WlSeat,
+    ) -> Result<ZwpTabletSeatV2, GlobalError>
+    where
+        D: Dispatch<ZwpTabletSeatV2, TabletSeatData> + 'static,
+    {
+        let udata = TabletSeatData { seat: seat.clone() };
+        Ok(self.tablet_manager.get()?.get_tablet_seat(seat, qh, udata))
+    }
+}
+
+impl ProvidesBoundGlobal<ZwpTabletManagerV2, 1> for TabletState {
+    fn bound_global(&self) -> Result<ZwpTabletManagerV2, GlobalError> {
+        self.tablet_manager.get().cloned()
+    }
+}
+
+/// Description of a tablet.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone)]
+pub struct TabletInfo {
+    /// A descriptive name of the tablet.
+    pub name: Option<String>,
+
+    /// The USB vendor and product id of the tablet.
+    pub id: Option<(u32, u32)>,
+
+    /// System-specific device paths of the tablet, such as `/dev/input/event0`.
+    pub paths: Vec<String>,
+}
+
+pub trait TabletHandler: Sized {
+    /// A tablet has been added to a seat.
+    fn tablet_added(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        seat: &WlSeat,
+        tablet: &ZwpTabletV2,
+        info: &TabletInfo,
+    );
+
+    /// A tablet has been removed.
+    ///
+    /// The tablet is destroyed after this returns.
+    fn tablet_removed(&mut self, conn: &Connection, qh: &QueueHandle<Self>, tablet: &ZwpTabletV2);
+}
+
+#[derive(Debug)]
+pub struct TabletSeatData {
+    seat: WlSeat,
+}
+
+impl TabletSeatData {
+    /// The seat associated with this tablet seat.
+    pub fn seat(&self) -> &WlSeat {
+        &self.seat
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TabletData {
+    inner: Mutex<TabletDataInner>,
+}
+
+#[derive(Debug, Default)]
+struct TabletDataInner {
+    seat: Option<WlSeat>,
+    info: TabletInfo,
+}
+
+impl TabletData {
+    /// The seat the tablet belongs to.
+    pub fn seat(&self) -> Option<WlSeat> {
+        self.inner.lock().unwrap().seat.clone()
+    }
+
+    /// The description of the tablet.
+    pub fn info(&self) -> TabletInfo {
+        self.inner.lock().unwrap().info.clone()
+    }
+}
+
+impl<D> Dispatch2<ZwpTabletManagerV2, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ZwpTabletManagerV2,
+        _: zwp_tablet_manager_v2::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("zwp_tablet_manager_v2 has no events")
+    }
+}
+
+impl<D> Dispatch2<ZwpTabletSeatV2, D> for TabletSeatData
+where
+    D: Dispatch<ZwpTabletV2, TabletData>
+        + Dispatch<ZwpTabletToolV2, TabletToolData>
+        + Dispatch<ZwpTabletPadV2, TabletPadData>
+        + 'static,
+{
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ZwpTabletSeatV2,
+        event: zwp_tablet_seat_v2::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        // The new objects are announced to the handlers once their description is done.
+        match event {
+            zwp_tablet_seat_v2::Event::TabletAdded { id } => {
+                if let Some(data) = id.data::<TabletData>() {
+                    data.inner.lock().unwrap().seat = Some(self.seat.clone());
+                }
+            }
+
+            zwp_tablet_seat_v2::Event::ToolAdded { id } => {
+                if let Some(data) = id.data::<TabletToolData>() {
+                    data.set_seat(self.seat.clone());
+                }
+            }
+
+            zwp_tablet_seat_v2::Event::PadAdded { id } => {
+                if let Some(data) = id.data::<TabletPadData>() {
+                    data.set_seat(self.seat.clone());
+                }
+            }
+
+            _ => unreachable!(),
+        }
+    }
+
+    wayland_client::event_created_child!(D, ZwpTabletSeatV2, [
+        zwp_tablet_seat_v2::EVT_TABLET_ADDED_OPCODE => (ZwpTabletV2, Default::default()),
+        zwp_tablet_seat_v2::EVT_TOOL_ADDED_OPCODE => (ZwpTabletToolV2, Default::default()),
+        zwp_tablet_seat_v2::EVT_PAD_ADDED_OPCODE => (ZwpTabletPadV2, Default::default()),
+    ]);
+}
+
+impl<D> Dispatch2<ZwpTabletV2, D> for TabletData
+where
+    D: TabletHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        tablet: &ZwpTabletV2,
+        event: zwp_tablet_v2::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        match event {
+            zwp_tablet_v2::Event::Name { name } => {
+                self.inner.lock().unwrap().info.name = Some(name);
+            }
+
+            zwp_tablet_v2::Event::Id { vid, pid } => {
+                self.inner.lock().unwrap().info.id = Some((vid, pid));
+            }
+
+            zwp_tablet_v2::Event::Path { path } => {
+                self.inner.lock().unwrap().info.paths.push(path);
+            }
+
+            zwp_tablet_v2::Event::Done => {
+                let (seat, info) = {
+                    let inner = self.inner.lock().unwrap();
+                    (inner.seat.clone(), inner.info.clone())
+                };
+
+                match seat {
+                    Some(seat) => state.tablet_added(conn, qh, &seat, tablet, &info),
+                    None => {
+                        log::warn!(target: "sctk", "{}: tablet without a seat", tablet.id());
+                    }
+                }
+            }
+
+            zwp_tablet_v2::Event::Removed => {
+                state.tablet_removed(conn, qh, tablet);
+                tablet.destroy();
+            }
+
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,501 @@
+//! Tablet pads, the buttons, rings and strips of a tablet.
+//!
+//! The controls of a pad are organized in groups. Each group has a number of modes, and the
+//! compositor tells the client when the user switches the mode of a group, so the controls of the
+//! group can be mapped to different actions in each mode.
+
+use std::{mem, sync::Mutex};
+
+use wayland_client::{
+    protocol::{wl_seat::WlSeat, wl_surface::WlSurface},
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols::wp::tablet::zv2::client::{
+    zwp_tablet_pad_group_v2::{self, ZwpTabletPadGroupV2},
+    zwp_tablet_pad_ring_v2::{self, ZwpTabletPadRingV2},
+    zwp_tablet_pad_strip_v2::{self, ZwpTabletPadStripV2},
+    zwp_tablet_pad_v2::{self, ZwpTabletPadV2},
+    zwp_tablet_v2::ZwpTabletV2,
+};
+
+use crate::dispatch2::Dispatch2;
+
+#[doc(inline)]
+pub use zwp_tablet_pad_ring_v2::Source as RingSource;
+#[doc(inline)]
+pub use zwp_tablet_pad_strip_v2::Source as StripSource;
+
+/// Description of a pad.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone)]
+pub struct TabletPadInfo {
+    /// System-specific device paths of the pad, such as `/dev/input/event0`.
+    pub paths: Vec<String>,
+
+    /// The number of buttons on the pad.
+    pub buttons: u32,
+
+    /// The groups of controls on the pad.
+    pub groups: Vec<TabletPadGroupInfo>,
+}
+
+/// Description of a group of controls on a pad.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct TabletPadGroupInfo {
+    pub group: ZwpTabletPadGroupV2,
+
+    /// The indices of the pad buttons in this group.
+    pub buttons: Vec<u32>,
+
+    pub rings: Vec<ZwpTabletPadRingV2>,
+
+    pub strips: Vec<ZwpTabletPadStripV2>,
+
+    /// The number of modes of the group.
+    pub modes: u32,
+}
+
+/// An event from a pad.
+#[derive(Debug, Clone)]
+pub enum TabletPadEvent {
+    /// The pad is focused on a surface.
+    Enter {
+        serial: u32,
+        tablet: ZwpTabletV2,
+        surface: WlSurface,
+    },
+    /// The pad is no longer focused on a surface.
+    Leave {
+        serial: u32,
+        surface: WlSurface,
+    },
+    Press {
+        time: u32,
+        button: u32,
+    },
+    Release {
+        time: u32,
+        button: u32,
+    },
+    /// The mode of a group changed.
+    ///
+    /// The feedback of the controls in the group should be updated with this serial.
+    ModeSwitch {
+        group: ZwpTabletPadGroupV2,
+        time: u32,
+        serial: u32,
+        mode: u32,
+    },
+    /// A frame of ring events.
+    Ring {
+        ring: ZwpTabletPadRingV2,
+        time: u32,
+        source: Option<RingSource>,
+        /// The angle of the ring, in degrees clockwise from its logical north.
+        angle: Option<f64>,
+        /// The interaction with the ring stopped.
+        stop: bool,
+    },
+    /// A frame of strip events.
+    Strip {
+        strip: ZwpTabletPadStripV2,
+        time: u32,
+        source: Option<StripSource>,
+        /// The position on the strip, normalized between `0` and `65535`.
+        position: Option<u32>,
+        /// The interaction with the strip stopped.
+        stop: bool,
+    },
+}
+
+pub trait TabletPadHandler: Sized {
+    /// A pad has been added to a seat.
+    fn pad_added(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        seat: &WlSeat,
+        pad: &ZwpTabletPadV2,
+        info: &TabletPadInfo,
+    );
+
+    /// A pad has been removed.
+    ///
+    /// The pad and its groups, rings and strips are destroyed after this returns.
+    fn pad_removed(&mut self, conn: &Connection, qh: &QueueHandle<Self>, pad: &ZwpTabletPadV2);
+
+    fn pad_event(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        pad: &ZwpTabletPadV2,
+        event: TabletPadEvent,
+    );
+}
+
+#[derive(Debug, Default)]
+pub struct TabletPadData {
+    inner: Mutex<TabletPadDataInner>,
+}
+
+#[derive(Debug, Default)]
+struct TabletPadDataInner {
+    seat: Option<WlSeat>,
+    paths: Vec<String>,
+    buttons: u32,
+    groups: Vec<ZwpTabletPadGroupV2>,
+}
+
+impl TabletPadData {
+    pub(crate) fn set_seat(&self, seat: WlSeat) {
+        self.inner.lock().unwrap().seat = Some(seat);
+    }
+
+    /// The seat the pad belongs to.
+    pub fn seat(&self) -> Option<WlSeat> {
+        self.inner.lock().unwrap().seat.clone()
+    }
+
+    /// The description of the pad.
+    pub fn info(&self) -> TabletPadInfo {
+        let inner = self.inner.lock().unwrap();
+        TabletPadInfo {
+            paths: inner.paths.clone(),
+            buttons: inner.buttons,
+            groups: inner
+                .groups
+                .iter()
+                .filter_map(|group| Some(group.data::<TabletPadGroupData>()?.info(group)))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TabletPadGroupData {
+    inner: Mutex<TabletPadGroupDataInner>,
+}
+
+#[derive(Debug, Default)]
+struct TabletPadGroupDataInner {
+    pad: Option<ZwpTabletPadV2>,
+    buttons: Vec<u32>,
+    rings: Vec<ZwpTabletPadRingV2>,
+    strips: Vec<ZwpTabletPadStripV2>,
+    modes: u32,
+    mode: u32,
+    latest_mode_switch: Option<u32>,
+}
+
+impl TabletPadGroupData {
+    fn info(&self, group: &ZwpTabletPadGroupV2) -> TabletPadGroupInfo {
+        let inner = self.inner.lock().unwrap();
+        TabletPadGroupInfo {
+            group: group.clone(),
+            buttons: inner.buttons.clone(),
+            rings: inner.rings.clone(),
+            strips: inner.strips.clone(),
+            modes: inner.modes,
+        }
+    }
+
+    /// The current mode of the group.
+    pub fn mode(&self) -> u32 {
+        self.inner.lock().unwrap().mode
+    }
+
+    /// Serial from the latest [`TabletPadEvent::ModeSwitch`] event of the group.
+    ///
+    /// This is the serial needed to set the feedback of the controls in the group.
+    pub fn latest_mode_switch_serial(&self) -> Option<u32> {
+        self.inner.lock().unwrap().latest_mode_switch
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TabletPadRingData {
+    inner: Mutex<TabletPadRingDataInner>,
+}
+
+#[derive(Debug, Default)]
+struct TabletPadRingDataInner {
+    pad: Option<ZwpTabletPadV2>,
+    source: Option<RingSource>,
+    angle: Option<f64>,
+    stop: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct TabletPadStripData {
+    inner: Mutex<TabletPadStripDataInner>,
+}
+
+#[derive(Debug, Default)]
+struct TabletPadStripDataInner {
+    pad: Option<ZwpTabletPadV2>,
+    source: Option<StripSource>,
+    position: Option<u32>,
+    stop: bool,
+}
+
+impl<D> Dispatch2<ZwpTabletPadV2, D> for TabletPadData
+where
+    D: Dispatch<ZwpTabletPadGroupV2, TabletPadGroupData> + TabletPadHandler + 'static,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        pad: &ZwpTabletPadV2,
+        event: zwp_tablet_pad_v2::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        let event = match event {
+            // Description of the pad
+            zwp_tablet_pad_v2::Event::Group { pad_group } => {
+                if let Some(data) = pad_group.data::<TabletPadGroupData>() {
+                    data.inner.lock().unwrap().pad = Some(pad.clone());
+                }
+                self.inner.lock().unwrap().groups.push(pad_group);
+                return;
+            }
+
+            zwp_tablet_pad_v2::Event::Path { path } => {
+                self.inner.lock().unwrap().paths.push(path);
+                return;
+            }
+
+            zwp_tablet_pad_v2::Event::Buttons { buttons } => {
+                self.inner.lock().unwrap().buttons = buttons;
+                return;
+            }
+
+            zwp_tablet_pad_v2::Event::Done => {
+                let seat = self.seat();
+                let info = self.info();
+
+                match seat {
+                    Some(seat) => state.pad_added(conn, qh, &seat, pad, &info),
+                    None => {
+                        log::warn!(target: "sctk", "{}: tablet pad without a seat", pad.id());
+                    }
+                }
+                return;
+            }
+
+            zwp_tablet_pad_v2::Event::Removed => {
+                state.pad_removed(conn, qh, pad);
+
+                let groups = mem::take(&mut self.inner.lock().unwrap().groups);
+                for group in groups {
+                    if let Some(data) = group.data::<TabletPadGroupData>() {
+                        let mut inner = data.inner.lock().unwrap();
+                        inner.rings.drain(..).for_each(|ring| ring.destroy());
+                        inner.strips.drain(..).for_each(|strip| strip.destroy());
+                    }
+                    group.destroy();
+                }
+                pad.destroy();
+                return;
+            }
+
+            // Pad events
+            zwp_tablet_pad_v2::Event::Button { time, button, state } => match state {
+                WEnum::Value(zwp_tablet_pad_v2::ButtonState::Pressed) => {
+                    TabletPadEvent::Press { time, button }
+                }
+                WEnum::Value(zwp_tablet_pad_v2::ButtonState::Released) => {
+                    TabletPadEvent::Release { time, button }
+                }
+                WEnum::Value(_) => unreachable!(),
+                WEnum::Unknown(unknown) => {
+                    log::warn!(target: "sctk", "{}: invalid pad button state: {:x}", pad.id(), unknown);
+                    return;
+                }
+            },
+
+            zwp_tablet_pad_v2::Event::Enter { serial, tablet, surface } => {
+                TabletPadEvent::Enter { serial, tablet, surface }
+            }
+
+            zwp_tablet_pad_v2::Event::Leave { serial, surface } => {
+                TabletPadEvent::Leave { serial, surface }
+            }
+
+            _ => unreachable!(),
+        };
+
+        state.pad_event(conn, qh, pad, event);
+    }
+
+    wayland_client::event_created_child!(D, ZwpTabletPadV2, [
+        zwp_tablet_pad_v2::EVT_GROUP_OPCODE => (ZwpTabletPadGroupV2, Default::default()),
+    ]);
+}
+
+impl<D> Dispatch2<ZwpTabletPadGroupV2, D> for TabletPadGroupData
+where
+    D: Dispatch<ZwpTabletPadRingV2, TabletPadRingData>
+        + Dispatch<ZwpTabletPadStripV2, TabletPadStripData>
+        + TabletPadHandler
+        + 'static,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        group: &ZwpTabletPadGroupV2,
+        event: zwp_tablet_pad_group_v2::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        match event {
+            zwp_tablet_pad_group_v2::Event::Buttons { buttons } => {
+                inner.buttons = buttons
+                    .chunks_exact(4)
+                    .flat_map(TryInto::<[u8; 4]>::try_into)
+                    .map(u32::from_ne_bytes)
+                    .collect();
+            }
+
+            zwp_tablet_pad_group_v2::Event::Ring { ring } => {
+                if let Some(data) = ring.data::<TabletPadRingData>() {
+                    data.inner.lock().unwrap().pad = inner.pad.clone();
+                }
+                inner.rings.push(ring);
+            }
+
+            zwp_tablet_pad_group_v2::Event::Strip { strip } => {
+                if let Some(data) = strip.data::<TabletPadStripData>() {
+                    data.inner.lock().unwrap().pad = inner.pad.clone();
+                }
+                inner.strips.push(strip);
+            }
+
+            zwp_tablet_pad_group_v2::Event::Modes { modes } => {
+                inner.modes = modes;
+            }
+
+            // The group is announced as part of the pad.
+            zwp_tablet_pad_group_v2::Event::Done => {}
+
+            zwp_tablet_pad_group_v2::Event::ModeSwitch { time, serial, mode } => {
+                inner.mode = mode;
+                inner.latest_mode_switch = Some(serial);
+
+                if let Some(pad) = inner.pad.clone() {
+                    drop(inner);
+                    let event =
+                        TabletPadEvent::ModeSwitch { group: group.clone(), time, serial, mode };
+                    state.pad_event(conn, qh, &pad, event);
+                }
+            }
+
+            _ => unreachable!(),
+        }
+    }
+
+    wayland_client::event_created_child!(D, ZwpTabletPadGroupV2, [
+        zwp_tablet_pad_group_v2::EVT_RING_OPCODE => (ZwpTabletPadRingV2, Default::default()),
+        zwp_tablet_pad_group_v2::EVT_STRIP_OPCODE => (ZwpTabletPadStripV2, Default::default()),
+    ]);
+}
+
+impl<D> Dispatch2<ZwpTabletPadRingV2, D> for TabletPadRingData
+where
+    D: TabletPadHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        ring: &ZwpTabletPadRingV2,
+        event: zwp_tablet_pad_ring_v2::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        match event {
+            zwp_tablet_pad_ring_v2::Event::Source { source } => match source {
+                WEnum::Value(source) => inner.source = Some(source),
+                WEnum::Unknown(unknown) => {
+                    log::warn!(target: "sctk", "{}: unknown ring source: {:x}", ring.id(), unknown);
+                }
+            },
+
+            zwp_tablet_pad_ring_v2::Event::Angle { degrees } => {
+                inner.angle = Some(degrees);
+            }
+
+            zwp_tablet_pad_ring_v2::Event::Stop => {
+                inner.stop = true;
+            }
+
+            zwp_tablet_pad_ring_v2::Event::Frame { time } => {
+                let event = TabletPadEvent::Ring {
+                    ring: ring.clone(),
+                    time,
+                    source: inner.source.take(),
+                    angle: inner.angle.take(),
+                    stop: mem::take(&mut inner.stop),
+                };
+
+                if let Some(pad) = inner.pad.clone() {
+                    drop(inner);
+                    state.pad_event(conn, qh, &pad, event);
+                }
+            }
+
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch2<ZwpTabletPadStripV2, D> for TabletPadStripData
+where
+    D: TabletPadHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        strip: &ZwpTabletPadStripV2,
+        event: zwp_tablet_pad_strip_v2::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        match event {
+            zwp_tablet_pad_strip_v2::Event::Source { source } => match source {
+                WEnum::Value(source) => inner.source = Some(source),
+                WEnum::Unknown(unknown) => {
+                    log::warn!(target: "sctk", "{}: unknown strip source: {:x}", strip.id(), unknown);
+                }
+            },
+
+            zwp_tablet_pad_strip_v2::Event::Position { position } => {
+                inner.position = Some(position);
+            }
+
+            zwp_tablet_pad_strip_v2::Event::Stop => {
+                inner.stop = true;
+            }
+
+            zwp_tablet_pad_strip_v2::Event::Frame { time } => {
+                let event = TabletPadEvent::Strip {
+                    strip: strip.clone(),
+                    time,
+                    source: inner.source.take(),
+                    position: inner.position.take(),
+                    stop: mem::take(&mut inner.stop),
+                };
+
+                if let Some(pad) = inner.pad.clone() {
+                    drop(inner);
+                    state.pad_event(conn, qh, &pad, event);
+                }
+            }
+
+            _ => unreachable!(),
+        }
+    }
+}
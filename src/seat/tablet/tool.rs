@@ -0,0 +1,370 @@
+//! Tablet tools, such as pens, erasers and tablet mice.
+
+use std::{mem, sync::Mutex};
+
+use bitflags::bitflags;
+use wayland_client::{
+    backend::smallvec::SmallVec,
+    protocol::{wl_seat::WlSeat, wl_surface::WlSurface},
+    Connection, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols::wp::tablet::zv2::client::{
+    zwp_tablet_tool_v2::{self, ZwpTabletToolV2},
+    zwp_tablet_v2::ZwpTabletV2,
+};
+
+use crate::dispatch2::Dispatch2;
+
+#[doc(inline)]
+pub use zwp_tablet_tool_v2::Type as ToolType;
+
+bitflags! {
+    /// The axes a tool supports, in addition to the position.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ToolCapabilities: u32 {
+        /// The tool reports tilt with [`TabletToolEventKind::Tilt`].
+        const TILT = 1;
+
+        /// The tool reports pressure with [`TabletToolEventKind::Pressure`].
+        const PRESSURE = 2;
+
+        /// The tool reports distance with [`TabletToolEventKind::Distance`].
+        const DISTANCE = 4;
+
+        /// The tool reports rotation with [`TabletToolEventKind::Rotation`].
+        const ROTATION = 8;
+
+        /// The tool reports a slider position with [`TabletToolEventKind::Slider`].
+        const SLIDER = 16;
+
+        /// The tool reports wheel movement with [`TabletToolEventKind::Wheel`].
+        const WHEEL = 32;
+    }
+}
+
+/// Description of a tool.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone)]
+pub struct TabletToolInfo {
+    /// The physical type of the tool.
+    pub tool_type: Option<ToolType>,
+
+    /// The unique serial number of the tool, if the tablet can identify it.
+    pub hardware_serial: Option<u64>,
+
+    /// The Wacom-specific hardware id of the tool.
+    pub hardware_id_wacom: Option<u64>,
+
+    /// The axes supported by the tool.
+    pub capabilities: ToolCapabilities,
+}
+
+/// A single tool event.
+#[derive(Debug, Clone)]
+pub struct TabletToolEvent {
+    /// The surface the tool is over.
+    pub surface: WlSurface,
+    /// The position of the tool in surface-local coordinates, at the end of the frame.
+    pub position: (f64, f64),
+    /// The time of the frame the event is part of, with millisecond granularity.
+    pub time: u32,
+    pub kind: TabletToolEventKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum TabletToolEventKind {
+    /// The tool came into proximity of the surface.
+    ProximityIn {
+        serial: u32,
+        tablet: ZwpTabletV2,
+    },
+    /// The tool left proximity, or is no longer over the surface.
+    ProximityOut,
+    /// The tool made contact with the tablet.
+    Down {
+        serial: u32,
+    },
+    /// The tool stopped making contact with the tablet.
+    Up,
+    /// The tool moved to [`TabletToolEvent::position`].
+    Motion,
+    /// The pressure of the tool, normalized between `0` and `65535`.
+    Pressure(u32),
+    /// The distance of the tool from the tablet, normalized between `0` and `65535`.
+    Distance(u32),
+    /// The tilt of the tool from the Z axis, in degrees.
+    Tilt {
+        x: f64,
+        y: f64,
+    },
+    /// The rotation of the tool around the Z axis, in degrees clockwise from its natural position.
+    Rotation(f64),
+    /// The position of the slider, normalized between `-65535` and `65535`.
+    Slider(i32),
+    /// The wheel of the tool moved.
+    ///
+    /// `degrees` follows the orientation of vertical pointer scrolling, while `clicks` is in
+    /// discrete logical steps.
+    Wheel {
+        degrees: f64,
+        clicks: i32,
+    },
+    Press {
+        button: u32,
+        serial: u32,
+    },
+    Release {
+        button: u32,
+        serial: u32,
+    },
+}
+
+pub trait TabletToolHandler: Sized {
+    /// A tool has been added to a seat.
+    fn tool_added(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        seat: &WlSeat,
+        tool: &ZwpTabletToolV2,
+        info: &TabletToolInfo,
+    );
+
+    /// A tool has been removed.
+    ///
+    /// The tool is destroyed after this returns.
+    fn tool_removed(&mut self, conn: &Connection, qh: &QueueHandle<Self>, tool: &ZwpTabletToolV2);
+
+    /// One or more tool events are available.
+    ///
+    /// All events of a frame describe a single hardware state change.
+    fn tool_frame(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        tool: &ZwpTabletToolV2,
+        events: &[TabletToolEvent],
+    );
+}
+
+#[derive(Debug, Default)]
+pub struct TabletToolData {
+    inner: Mutex<TabletToolDataInner>,
+}
+
+#[derive(Debug, Default)]
+struct TabletToolDataInner {
+    seat: Option<WlSeat>,
+    info: TabletToolInfo,
+
+    /// Surface the tool is over
+    surface: Option<WlSurface>,
+    /// Position relative to the surface
+    position: (f64, f64),
+
+    /// List of pending events, completed by the frame event
+    pending: SmallVec<[(TabletToolEventKind, Option<WlSurface>); 4]>,
+
+    /// The serial of the latest proximity in event
+    latest_proximity_in: Option<u32>,
+}
+
+impl TabletToolData {
+    pub(crate) fn set_seat(&self, seat: WlSeat) {
+        self.inner.lock().unwrap().seat = Some(seat);
+    }
+
+    /// The seat the tool belongs to.
+    pub fn seat(&self) -> Option<WlSeat> {
+        self.inner.lock().unwrap().seat.clone()
+    }
+
+    /// The description of the tool.
+    pub fn info(&self) -> TabletToolInfo {
+        self.inner.lock().unwrap().info.clone()
+    }
+
+    /// Serial from the latest [`TabletToolEventKind::ProximityIn`] event.
+    ///
+    /// This is the serial needed to set the cursor of the tool.
+    pub fn latest_proximity_in_serial(&self) -> Option<u32> {
+        self.inner.lock().unwrap().latest_proximity_in
+    }
+}
+
+impl<D> Dispatch2<ZwpTabletToolV2, D> for TabletToolData
+where
+    D: TabletToolHandler,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        tool: &ZwpTabletToolV2,
+        event: zwp_tablet_tool_v2::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        let mut guard = self.inner.lock().unwrap();
+        let mut leave_surface = None;
+        let kind = match event {
+            // Description of the tool
+            zwp_tablet_tool_v2::Event::Type { tool_type } => {
+                match tool_type {
+                    WEnum::Value(tool_type) => guard.info.tool_type = Some(tool_type),
+                    WEnum::Unknown(unknown) => {
+                        log::warn!(target: "sctk", "{}: unknown tool type: {:x}", tool.id(), unknown);
+                    }
+                }
+                return;
+            }
+
+            zwp_tablet_tool_v2::Event::HardwareSerial {
+                hardware_serial_hi,
+                hardware_serial_lo,
+            } => {
+                guard.info.hardware_serial =
+                    Some((hardware_serial_hi as u64) << 32 | hardware_serial_lo as u64);
+                return;
+            }
+
+            zwp_tablet_tool_v2::Event::HardwareIdWacom { hardware_id_hi, hardware_id_lo } => {
+                guard.info.hardware_id_wacom =
+                    Some((hardware_id_hi as u64) << 32 | hardware_id_lo as u64);
+                return;
+            }
+
+            zwp_tablet_tool_v2::Event::Capability { capability } => {
+                let capability = match capability {
+                    WEnum::Value(zwp_tablet_tool_v2::Capability::Tilt) => ToolCapabilities::TILT,
+                    WEnum::Value(zwp_tablet_tool_v2::Capability::Pressure) => {
+                        ToolCapabilities::PRESSURE
+                    }
+                    WEnum::Value(zwp_tablet_tool_v2::Capability::Distance) => {
+                        ToolCapabilities::DISTANCE
+                    }
+                    WEnum::Value(zwp_tablet_tool_v2::Capability::Rotation) => {
+                        ToolCapabilities::ROTATION
+                    }
+                    WEnum::Value(zwp_tablet_tool_v2::Capability::Slider) => {
+                        ToolCapabilities::SLIDER
+                    }
+                    WEnum::Value(zwp_tablet_tool_v2::Capability::Wheel) => ToolCapabilities::WHEEL,
+                    WEnum::Value(_) => unreachable!(),
+                    WEnum::Unknown(unknown) => {
+                        log::warn!(target: "sctk", "{}: unknown tool capability: {:x}", tool.id(), unknown);
+                        return;
+                    }
+                };
+                guard.info.capabilities |= capability;
+                return;
+            }
+
+            zwp_tablet_tool_v2::Event::Done => {
+                let (seat, info) = (guard.seat.clone(), guard.info.clone());
+                drop(guard);
+
+                match seat {
+                    Some(seat) => state.tool_added(conn, qh, &seat, tool, &info),
+                    None => {
+                        log::warn!(target: "sctk", "{}: tablet tool without a seat", tool.id());
+                    }
+                }
+                return;
+            }
+
+            zwp_tablet_tool_v2::Event::Removed => {
+                drop(guard);
+                state.tool_removed(conn, qh, tool);
+                tool.destroy();
+                return;
+            }
+
+            // Tool events
+            zwp_tablet_tool_v2::Event::ProximityIn { serial, tablet, surface } => {
+                guard.surface = Some(surface);
+                guard.latest_proximity_in = Some(serial);
+
+                TabletToolEventKind::ProximityIn { serial, tablet }
+            }
+
+            zwp_tablet_tool_v2::Event::ProximityOut => {
+                leave_surface = guard.surface.take();
+
+                TabletToolEventKind::ProximityOut
+            }
+
+            zwp_tablet_tool_v2::Event::Down { serial } => TabletToolEventKind::Down { serial },
+
+            zwp_tablet_tool_v2::Event::Up => TabletToolEventKind::Up,
+
+            zwp_tablet_tool_v2::Event::Motion { x, y } => {
+                guard.position = (x, y);
+
+                TabletToolEventKind::Motion
+            }
+
+            zwp_tablet_tool_v2::Event::Pressure { pressure } => {
+                TabletToolEventKind::Pressure(pressure)
+            }
+
+            zwp_tablet_tool_v2::Event::Distance { distance } => {
+                TabletToolEventKind::Distance(distance)
+            }
+
+            zwp_tablet_tool_v2::Event::Tilt { tilt_x, tilt_y } => {
+                TabletToolEventKind::Tilt { x: tilt_x, y: tilt_y }
+            }
+
+            zwp_tablet_tool_v2::Event::Rotation { degrees } => {
+                TabletToolEventKind::Rotation(degrees)
+            }
+
+            zwp_tablet_tool_v2::Event::Slider { position } => TabletToolEventKind::Slider(position),
+
+            zwp_tablet_tool_v2::Event::Wheel { degrees, clicks } => {
+                TabletToolEventKind::Wheel { degrees, clicks }
+            }
+
+            zwp_tablet_tool_v2::Event::Button { serial, button, state } => match state {
+                WEnum::Value(zwp_tablet_tool_v2::ButtonState::Pressed) => {
+                    TabletToolEventKind::Press { button, serial }
+                }
+                WEnum::Value(zwp_tablet_tool_v2::ButtonState::Released) => {
+                    TabletToolEventKind::Release { button, serial }
+                }
+                WEnum::Value(_) => unreachable!(),
+                WEnum::Unknown(unknown) => {
+                    log::warn!(target: "sctk", "{}: invalid tool button state: {:x}", tool.id(), unknown);
+                    return;
+                }
+            },
+
+            zwp_tablet_tool_v2::Event::Frame { time } => {
+                let pending = mem::take(&mut guard.pending);
+                let position = guard.position;
+                drop(guard);
+
+                let events = pending
+                    .into_iter()
+                    .filter_map(|(kind, surface)| match surface {
+                        Some(surface) => Some(TabletToolEvent { surface, position, time, kind }),
+                        None => {
+                            log::warn!(target: "sctk", "{}: got tool event {:?} without a surface", tool.id(), kind);
+                            None
+                        }
+                    })
+                    .collect::<SmallVec<[_; 4]>>();
+
+                if !events.is_empty() {
+                    state.tool_frame(conn, qh, tool, &events);
+                }
+                return;
+            }
+
+            _ => unreachable!(),
+        };
+
+        let surface = leave_surface.or_else(|| guard.surface.clone());
+        guard.pending.push((kind, surface));
+    }
+}
@@ -0,0 +1,58 @@
+//! Fixtures for the tests of the input helpers.
+
+use std::os::unix::net::UnixStream;
+
+use wayland_client::{protocol::wl_surface::WlSurface, Connection, Proxy};
+
+use super::{
+    pointer::{PointerEvent, PointerEventKind, BTN_LEFT},
+    touch::{TouchEvent, TouchEventKind},
+};
+
+/// An inert surface, along with the server end of its connection which must be kept alive.
+pub(crate) fn surface() -> (WlSurface, UnixStream) {
+    let (client, server) = UnixStream::pair().unwrap();
+    let conn = Connection::from_socket(client).unwrap();
+    (WlSurface::inert(conn.backend().downgrade()), server)
+}
+
+pub(crate) fn pointer_event(
+    surface: &WlSurface,
+    position: (f64, f64),
+    kind: PointerEventKind,
+) -> PointerEvent {
+    PointerEvent { surface: surface.clone(), position, kind }
+}
+
+pub(crate) fn press(surface: &WlSurface, position: (f64, f64), time: u32) -> PointerEvent {
+    pointer_event(surface, position, PointerEventKind::Press { time, button: BTN_LEFT, serial: 0 })
+}
+
+pub(crate) fn release(surface: &WlSurface, position: (f64, f64), time: u32) -> PointerEvent {
+    pointer_event(
+        surface,
+        position,
+        PointerEventKind::Release { time, button: BTN_LEFT, serial: 0 },
+    )
+}
+
+pub(crate) fn touch_event(
+    surface: &WlSurface,
+    id: i32,
+    position: (f64, f64),
+    kind: TouchEventKind,
+) -> TouchEvent {
+    TouchEvent { surface: surface.clone(), id, position, kind }
+}
+
+pub(crate) fn down(surface: &WlSurface, id: i32, position: (f64, f64)) -> TouchEvent {
+    touch_event(surface, id, position, TouchEventKind::Down { serial: 0, time: 0 })
+}
+
+pub(crate) fn motion(surface: &WlSurface, id: i32, position: (f64, f64)) -> TouchEvent {
+    touch_event(surface, id, position, TouchEventKind::Motion { time: 0 })
+}
+
+pub(crate) fn up(surface: &WlSurface, id: i32, position: (f64, f64)) -> TouchEvent {
+    touch_event(surface, id, position, TouchEventKind::Up { serial: 0, time: 0 })
+}
@@ -0,0 +1,362 @@
+//! Implementation of the `text-input-unstable-v3` protocol.
+//!
+//! This protocol lets applications receive text from an input method, such as composed CJK text or
+//! the output of an on-screen keyboard. It is the application side of the input method protocols.
+//!
+//! ### Implementation status
+//! Only version 1 of the protocol is supported.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use wayland_client::globals::{BindError, GlobalList};
+use wayland_client::protocol::{wl_seat::WlSeat, wl_surface::WlSurface};
+use wayland_client::{Connection, Dispatch, QueueHandle};
+use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_manager_v3::{
+    self, ZwpTextInputManagerV3,
+};
+use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_v3;
+pub use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_v3::{
+    ChangeCause, ContentHint, ContentPurpose, ZwpTextInputV3,
+};
+
+use crate::compositor::Rect;
+use crate::dispatch2::Dispatch2;
+use crate::globals::GlobalData;
+
+pub use super::input_method::CursorPosition;
+
+#[derive(Debug)]
+pub struct TextInputManager {
+    manager: ZwpTextInputManagerV3,
+}
+
+impl TextInputManager {
+    /// Bind `zwp_text_input_manager_v3` global, if it exists
+    pub fn bind<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Result<Self, BindError>
+    where
+        D: Dispatch<ZwpTextInputManagerV3, GlobalData> + 'static,
+    {
+        let manager = globals.bind(qh, 1..=1, GlobalData)?;
+        Ok(Self { manager })
+    }
+
+    /// Request a new zwp_text_input_v3 object associated with a given seat.
+    pub fn get_text_input<State>(&self, qh: &QueueHandle<State>, seat: &WlSeat) -> TextInput
+    where
+        State: Dispatch<ZwpTextInputV3, TextInputData<()>, State> + 'static,
+    {
+        self.get_text_input_with_data(qh, seat, ())
+    }
+
+    pub fn get_text_input_with_data<State, U>(
+        &self,
+        qh: &QueueHandle<State>,
+        seat: &WlSeat,
+        udata: U,
+    ) -> TextInput
+    where
+        State: Dispatch<ZwpTextInputV3, TextInputData<U>, State> + 'static,
+        U: Send + Sync + 'static,
+    {
+        let data = TextInputData::new(seat.clone(), udata);
+        let commit_count = data.commit_count.clone();
+        TextInput { text_input: self.manager.get_text_input(seat, qh, data), commit_count }
+    }
+
+    pub fn zwp_text_input_manager_v3(&self) -> &ZwpTextInputManagerV3 {
+        &self.manager
+    }
+}
+
+impl<D> Dispatch2<ZwpTextInputManagerV3, D> for GlobalData
+where
+    D: TextInputHandler,
+{
+    fn event(
+        &self,
+        _data: &mut D,
+        _manager: &ZwpTextInputManagerV3,
+        _event: zwp_text_input_manager_v3::Event,
+        _conn: &Connection,
+        _qh: &QueueHandle<D>,
+    ) {
+        unreachable!("zwp_text_input_manager_v3 has no events")
+    }
+}
+
+/// A text input associated with a seat.
+///
+/// The state set through this type is double-buffered and only applied by
+/// [`commit`](Self::commit). The text input is destroyed on drop.
+#[derive(Debug)]
+pub struct TextInput {
+    text_input: ZwpTextInputV3,
+    commit_count: Arc<AtomicU32>,
+}
+
+impl TextInput {
+    /// Enable text input on the focused surface.
+    ///
+    /// This must be sent every time the focused text field changes, and resets all the state
+    /// previously set on the text input.
+    pub fn enable(&self) {
+        self.text_input.enable()
+    }
+
+    /// Disable text input on the focused surface.
+    pub fn disable(&self) {
+        self.text_input.disable()
+    }
+
+    /// Set the plain text around the cursor, excluding any preedit text.
+    ///
+    /// `cursor` and `anchor` are byte offsets into `text`. The anchor is the other end of the
+    /// selection, and is equal to the cursor if nothing is selected.
+    pub fn set_surrounding_text(&self, text: String, cursor: usize, anchor: usize) {
+        self.text_input.set_surrounding_text(
+            text,
+            cursor.try_into().unwrap_or(i32::MAX),
+            anchor.try_into().unwrap_or(i32::MAX),
+        )
+    }
+
+    /// Set the reason of the last change of the surrounding text.
+    pub fn set_text_change_cause(&self, cause: ChangeCause) {
+        self.text_input.set_text_change_cause(cause)
+    }
+
+    pub fn set_content_type(&self, hint: ContentHint, purpose: ContentPurpose) {
+        self.text_input.set_content_type(hint, purpose)
+    }
+
+    /// Set the area around the cursor, in surface local coordinates.
+    ///
+    /// The compositor uses this to place input method popups without covering the edited text.
+    pub fn set_cursor_rectangle(&self, rect: Rect<i32>) {
+        self.text_input.set_cursor_rectangle(rect.x, rect.y, rect.width, rect.height)
+    }
+
+    /// Apply the pending state.
+    pub fn commit(&self) {
+        self.commit_count.fetch_add(1, Ordering::Relaxed);
+        self.text_input.commit()
+    }
+
+    pub fn zwp_text_input_v3(&self) -> &ZwpTextInputV3 {
+        &self.text_input
+    }
+}
+
+impl Drop for TextInput {
+    fn drop(&mut self) {
+        self.text_input.destroy()
+    }
+}
+
+#[derive(Debug)]
+pub struct TextInputData<U> {
+    seat: WlSeat,
+    pending: Mutex<TextInputEvent>,
+    commit_count: Arc<AtomicU32>,
+    udata: U,
+}
+
+impl<U> TextInputData<U> {
+    /// Create the new text input data associated with the given seat.
+    pub fn new(seat: WlSeat, udata: U) -> Self {
+        Self {
+            seat,
+            pending: Mutex::new(TextInputEvent::default()),
+            commit_count: Arc::new(AtomicU32::new(0)),
+            udata,
+        }
+    }
+
+    pub fn data(&self) -> &U {
+        &self.udata
+    }
+
+    pub fn data_mut(&mut self) -> &mut U {
+        &mut self.udata
+    }
+
+    /// Get the associated seat from the data.
+    pub fn seat(&self) -> &WlSeat {
+        &self.seat
+    }
+}
+
+/// Changes to apply to the focused text field, collected until the `done` event.
+///
+/// The changes must be applied in the following order:
+///
+/// 1. Remove the current preedit text.
+/// 2. Delete the surrounding text given by [`delete_surrounding_text`](Self::delete_surrounding_text).
+/// 3. Insert [`commit_string`](Self::commit_string) with the cursor at its end.
+/// 4. Insert the new [`preedit`](Self::preedit) text at the cursor position.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TextInputEvent {
+    /// The new preedit text, or [`None`] if there is none.
+    pub preedit: Option<Preedit>,
+    /// Text to insert at the cursor position.
+    pub commit_string: Option<String>,
+    /// Text to delete around the cursor position.
+    pub delete_surrounding_text: Option<DeleteSurroundingText>,
+    /// The number of commits known to the compositor when it sent these changes.
+    pub serial: u32,
+    /// Whether the compositor knew about all commits made on the text input.
+    ///
+    /// If this is `false`, the changes must still be applied, but the state of the text input
+    /// should only be sent again after an up to date `done` event.
+    pub up_to_date: bool,
+}
+
+/// Text being composed by the input method.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preedit {
+    pub text: String,
+    /// The cursor inside the preedit text, as byte offsets into `text`.
+    pub cursor: CursorPosition,
+}
+
+/// The number of bytes to delete before and after the cursor.
+///
+/// If there is preedit text, `before_length` is counted from its beginning and `after_length`
+/// from its end.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DeleteSurroundingText {
+    pub before_length: u32,
+    pub after_length: u32,
+}
+
+pub trait TextInputHandler: Sized {
+    /// The text input focus of the seat entered a surface.
+    ///
+    /// All state of the text input has to be sent again after enabling it.
+    fn enter(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        text_input: &ZwpTextInputV3,
+        surface: &WlSurface,
+    );
+
+    /// The text input focus of the seat left a surface.
+    ///
+    /// Any preedit text should be removed.
+    fn leave(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        text_input: &ZwpTextInputV3,
+        surface: &WlSurface,
+    );
+
+    /// The input method sent changes to apply to the focused text field.
+    fn done(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        text_input: &ZwpTextInputV3,
+        event: TextInputEvent,
+    );
+}
+
+impl<D, U> Dispatch2<ZwpTextInputV3, D> for TextInputData<U>
+where
+    D: TextInputHandler,
+{
+    fn event(
+        &self,
+        data: &mut D,
+        text_input: &ZwpTextInputV3,
+        event: zwp_text_input_v3::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        use zwp_text_input_v3::Event;
+
+        match event {
+            Event::Enter { surface } => data.enter(conn, qh, text_input, &surface),
+            Event::Leave { surface } => data.leave(conn, qh, text_input, &surface),
+            Event::PreeditString { text, cursor_begin, cursor_end } => {
+                let cursor = match (usize::try_from(cursor_begin), usize::try_from(cursor_end)) {
+                    (Ok(start), Ok(end)) => CursorPosition::Visible { start, end },
+                    _ => CursorPosition::Hidden,
+                };
+                self.pending.lock().unwrap().preedit = text.map(|text| Preedit { text, cursor });
+            }
+            Event::CommitString { text } => {
+                self.pending.lock().unwrap().commit_string = text;
+            }
+            Event::DeleteSurroundingText { before_length, after_length } => {
+                self.pending.lock().unwrap().delete_surrounding_text =
+                    Some(DeleteSurroundingText { before_length, after_length });
+            }
+            Event::Done { serial } => {
+                let mut event = std::mem::take(&mut *self.pending.lock().unwrap());
+                event.serial = serial;
+                event.up_to_date = self.commit_count.load(Ordering::Relaxed) == serial;
+                data.done(conn, qh, text_input, event);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Handler {}
+
+    impl TextInputHandler for Handler {
+        fn enter(
+            &mut self,
+            _conn: &Connection,
+            _qh: &QueueHandle<Self>,
+            _text_input: &ZwpTextInputV3,
+            _surface: &WlSurface,
+        ) {
+        }
+
+        fn leave(
+            &mut self,
+            _conn: &Connection,
+            _qh: &QueueHandle<Self>,
+            _text_input: &ZwpTextInputV3,
+            _surface: &WlSurface,
+        ) {
+        }
+
+        fn done(
+            &mut self,
+            _conn: &Connection,
+            _qh: &QueueHandle<Self>,
+            _text_input: &ZwpTextInputV3,
+            _event: TextInputEvent,
+        ) {
+        }
+    }
+
+    crate::delegate_dispatch2!(Handler);
+
+    fn assert_is_manager_delegate<T>()
+    where
+        T: wayland_client::Dispatch<ZwpTextInputManagerV3, crate::globals::GlobalData>,
+    {
+    }
+
+    fn assert_is_delegate<T>()
+    where
+        T: wayland_client::Dispatch<ZwpTextInputV3, TextInputData<()>>,
+    {
+    }
+
+    #[test]
+    fn test_valid_assignment() {
+        assert_is_manager_delegate::<Handler>();
+        assert_is_delegate::<Handler>();
+    }
+}
@@ -0,0 +1,494 @@
+//! Recognition of touch gestures.
+//!
+//! A [`GestureRecognizer`] is given the events of each touch frame, from
+//! [`TouchHandler::touch_frame`](super::TouchHandler::touch_frame), and the cancellation of touch
+//! sequences, and reports taps, long presses, pans, pinches and rotations as [`Gesture`]s.
+//!
+//! Long presses happen while no touch event is received, so the recognizer should also be given
+//! the time once [`GestureRecognizer::next_timeout`] is reached, for example through a calloop
+//! timer.
+
+use std::f64::consts::PI;
+use std::time::{Duration, Instant};
+
+use wayland_client::protocol::wl_surface::WlSurface;
+
+use super::{TouchEvent, TouchEventKind};
+
+/// The thresholds used to recognize gestures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureConfig {
+    /// The maximum duration of a tap.
+    pub tap_timeout: Duration,
+
+    /// The duration a single touch point must be held still for a long press.
+    pub long_press_timeout: Duration,
+
+    /// The distance a touch point may move before the touch becomes a pan, in surface-local
+    /// coordinates.
+    pub pan_threshold: f64,
+
+    /// The relative change of the distance between two touch points before the touch becomes a
+    /// pinch.
+    pub pinch_threshold: f64,
+
+    /// The angle two touch points must rotate by before the touch becomes a rotation, in radians.
+    pub rotate_threshold: f64,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            tap_timeout: Duration::from_millis(300),
+            long_press_timeout: Duration::from_millis(500),
+            pan_threshold: 10.,
+            pinch_threshold: 0.1,
+            rotate_threshold: 0.2,
+        }
+    }
+}
+
+/// The phase of a continuous gesture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GesturePhase {
+    Begin,
+    Update,
+    /// All the touch points were released.
+    End,
+    /// The touch sequence was cancelled by the compositor.
+    Cancel,
+}
+
+/// A recognized gesture.
+///
+/// Positions are surface-local, on the surface the first touch point of the gesture went down on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gesture {
+    /// Touch points were released quickly, without moving.
+    Tap { surface: WlSurface, position: (f64, f64), fingers: u32 },
+
+    /// A single touch point was held still.
+    LongPress { surface: WlSurface, position: (f64, f64) },
+
+    /// The center of the touch points moved by `delta` since the previous update.
+    Pan { surface: WlSurface, phase: GesturePhase, position: (f64, f64), delta: (f64, f64) },
+
+    /// Two touch points moved apart or together, by `scale` since the gesture began.
+    Pinch { surface: WlSurface, phase: GesturePhase, center: (f64, f64), scale: f64 },
+
+    /// Two touch points rotated clockwise around each other, by `angle` radians since the gesture
+    /// began.
+    Rotate { surface: WlSurface, phase: GesturePhase, center: (f64, f64), angle: f64 },
+}
+
+/// A recognizer of touch gestures.
+///
+/// Pans, pinches and rotations may happen at the same time. When touch points are added or
+/// released during a gesture, it continues from the remaining touch points.
+#[derive(Debug)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    points: Vec<Point>,
+    session: Option<Session>,
+}
+
+#[derive(Debug)]
+struct Point {
+    id: i32,
+    start: (f64, f64),
+    position: (f64, f64),
+}
+
+/// The state of a touch sequence, from the first touch point down to the last one up.
+#[derive(Debug)]
+struct Session {
+    surface: WlSurface,
+    start: Instant,
+    origin: (f64, f64),
+    fingers: u32,
+
+    /// Whether the touch may still be a tap or a long press.
+    still: bool,
+    panning: bool,
+    pinching: bool,
+    rotating: bool,
+
+    centroid: (f64, f64),
+    pair: Option<(f64, f64)>,
+    scale: f64,
+    angle: f64,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        Self { config, points: Vec::new(), session: None }
+    }
+
+    pub fn config(&self) -> &GestureConfig {
+        &self.config
+    }
+
+    /// Process the events of a touch frame received at `now`.
+    pub fn process_frame(&mut self, events: &[TouchEvent], now: Instant) -> Vec<Gesture> {
+        let mut gestures = self.process_timeout(now);
+        let ids = self.points.iter().map(|point| point.id).collect::<Vec<_>>();
+
+        for event in events {
+            match event.kind {
+                TouchEventKind::Down { .. } => {
+                    let session = self.session.get_or_insert_with(|| {
+                        Session::new(event.surface.clone(), now, event.position)
+                    });
+                    let point =
+                        Point { id: event.id, start: event.position, position: event.position };
+                    match self.points.iter_mut().find(|point| point.id == event.id) {
+                        Some(existing) => *existing = point,
+                        None => self.points.push(point),
+                    }
+                    session.fingers = session.fingers.max(self.points.len() as u32);
+                }
+                TouchEventKind::Motion { .. } => {
+                    if let Some(point) = self.points.iter_mut().find(|point| point.id == event.id) {
+                        point.position = event.position;
+                    }
+                }
+                TouchEventKind::Up { .. } => {
+                    self.points.retain(|point| point.id != event.id);
+                }
+                TouchEventKind::Shape { .. } | TouchEventKind::Orientation { .. } => {}
+            }
+        }
+
+        let Some(session) = &mut self.session else {
+            return gestures;
+        };
+
+        if self.points.is_empty() {
+            let elapsed = now.saturating_duration_since(session.start);
+            if session.still && elapsed <= self.config.tap_timeout {
+                gestures.push(Gesture::Tap {
+                    surface: session.surface.clone(),
+                    position: session.origin,
+                    fingers: session.fingers,
+                });
+            }
+            session.end(GesturePhase::End, &mut gestures);
+            self.session = None;
+            return gestures;
+        }
+
+        let centroid = centroid(&self.points);
+        let pair = pair(&self.points);
+
+        // Continue from the new touch points without jumping.
+        if !self.points.iter().map(|point| point.id).eq(ids) {
+            if pair.is_none() {
+                session.end_pair(GesturePhase::End, &mut gestures);
+            }
+            session.centroid = centroid;
+            session.pair = pair;
+            return gestures;
+        }
+
+        let moved = self
+            .points
+            .iter()
+            .any(|point| distance(point.start, point.position) > self.config.pan_threshold);
+        let mut phase = GesturePhase::Update;
+        if moved && !session.panning {
+            session.still = false;
+            session.panning = true;
+            phase = GesturePhase::Begin;
+        }
+        if session.panning {
+            let delta = (centroid.0 - session.centroid.0, centroid.1 - session.centroid.1);
+            if phase == GesturePhase::Begin || delta != (0., 0.) {
+                gestures.push(Gesture::Pan {
+                    surface: session.surface.clone(),
+                    phase,
+                    position: centroid,
+                    delta,
+                });
+            }
+        }
+        session.centroid = centroid;
+
+        if let (Some((distance, angle)), Some((prev_distance, prev_angle))) = (pair, session.pair) {
+            if prev_distance > 0. {
+                session.scale *= distance / prev_distance;
+            }
+            session.angle += normalize_angle(angle - prev_angle);
+
+            let mut phase = GesturePhase::Update;
+            if !session.pinching && (session.scale - 1.).abs() > self.config.pinch_threshold {
+                session.still = false;
+                session.pinching = true;
+                phase = GesturePhase::Begin;
+            }
+            if session.pinching && (phase == GesturePhase::Begin || distance != prev_distance) {
+                gestures.push(Gesture::Pinch {
+                    surface: session.surface.clone(),
+                    phase,
+                    center: centroid,
+                    scale: session.scale,
+                });
+            }
+
+            let mut phase = GesturePhase::Update;
+            if !session.rotating && session.angle.abs() > self.config.rotate_threshold {
+                session.still = false;
+                session.rotating = true;
+                phase = GesturePhase::Begin;
+            }
+            if session.rotating && (phase == GesturePhase::Begin || angle != prev_angle) {
+                gestures.push(Gesture::Rotate {
+                    surface: session.surface.clone(),
+                    phase,
+                    center: centroid,
+                    angle: session.angle,
+                });
+            }
+        }
+        session.pair = pair;
+
+        gestures
+    }
+
+    /// Process the cancellation of the touch sequence by the compositor.
+    pub fn cancel(&mut self) -> Vec<Gesture> {
+        let mut gestures = Vec::new();
+        if let Some(session) = self.session.take() {
+            session.end(GesturePhase::Cancel, &mut gestures);
+        }
+        self.points.clear();
+        gestures
+    }
+
+    /// When a long press happens if the touch point stays still, to call
+    /// [`GestureRecognizer::process_timeout`].
+    pub fn next_timeout(&self) -> Option<Instant> {
+        self.session
+            .as_ref()
+            .filter(|session| session.still && session.fingers == 1 && self.points.len() == 1)
+            .map(|session| session.start + self.config.long_press_timeout)
+    }
+
+    /// Recognize a long press, once [`GestureRecognizer::next_timeout`] is reached.
+    pub fn process_timeout(&mut self, now: Instant) -> Vec<Gesture> {
+        if self.next_timeout().is_some_and(|timeout| now >= timeout) {
+            let session = self.session.as_mut().unwrap();
+            session.still = false;
+            return vec![Gesture::LongPress {
+                surface: session.surface.clone(),
+                position: self.points[0].position,
+            }];
+        }
+        Vec::new()
+    }
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::new(GestureConfig::default())
+    }
+}
+
+impl Session {
+    fn new(surface: WlSurface, start: Instant, origin: (f64, f64)) -> Self {
+        Self {
+            surface,
+            start,
+            origin,
+            fingers: 0,
+            still: true,
+            panning: false,
+            pinching: false,
+            rotating: false,
+            centroid: origin,
+            pair: None,
+            scale: 1.,
+            angle: 0.,
+        }
+    }
+
+    fn end(&self, phase: GesturePhase, gestures: &mut Vec<Gesture>) {
+        if self.panning {
+            gestures.push(Gesture::Pan {
+                surface: self.surface.clone(),
+                phase,
+                position: self.centroid,
+                delta: (0., 0.),
+            });
+        }
+        self.end_pair_gestures(phase, gestures);
+    }
+
+    fn end_pair(&mut self, phase: GesturePhase, gestures: &mut Vec<Gesture>) {
+        self.end_pair_gestures(phase, gestures);
+        self.pinching = false;
+        self.rotating = false;
+        self.scale = 1.;
+        self.angle = 0.;
+    }
+
+    fn end_pair_gestures(&self, phase: GesturePhase, gestures: &mut Vec<Gesture>) {
+        if self.pinching {
+            gestures.push(Gesture::Pinch {
+                surface: self.surface.clone(),
+                phase,
+                center: self.centroid,
+                scale: self.scale,
+            });
+        }
+        if self.rotating {
+            gestures.push(Gesture::Rotate {
+                surface: self.surface.clone(),
+                phase,
+                center: self.centroid,
+                angle: self.angle,
+            });
+        }
+    }
+}
+
+fn centroid(points: &[Point]) -> (f64, f64) {
+    let (x, y) =
+        points.iter().fold((0., 0.), |(x, y), point| (x + point.position.0, y + point.position.1));
+    let count = points.len() as f64;
+    (x / count, y / count)
+}
+
+/// The distance and angle between the first two touch points.
+fn pair(points: &[Point]) -> Option<(f64, f64)> {
+    match points {
+        [first, second, ..] => {
+            let (dx, dy) =
+                (second.position.0 - first.position.0, second.position.1 - first.position.1);
+            Some((dx.hypot(dy), dy.atan2(dx)))
+        }
+        _ => None,
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+/// Normalize an angle difference to the -π to π range.
+fn normalize_angle(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(2. * PI);
+    if angle > PI {
+        angle - 2. * PI
+    } else {
+        angle
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::seat::testing::{down, motion, surface, up};
+
+    #[test]
+    fn tap_and_long_press() {
+        let (surface, _server) = surface();
+        let mut recognizer = GestureRecognizer::default();
+        let start = Instant::now();
+
+        assert!(recognizer.process_frame(&[down(&surface, 0, (5., 5.))], start).is_empty());
+        let gestures = recognizer
+            .process_frame(&[up(&surface, 0, (6., 5.))], start + Duration::from_millis(100));
+        assert_eq!(
+            gestures,
+            vec![Gesture::Tap { surface: surface.clone(), position: (5., 5.), fingers: 1 }]
+        );
+
+        recognizer.process_frame(&[down(&surface, 1, (5., 5.))], start);
+        let timeout = recognizer.next_timeout().unwrap();
+        assert_eq!(timeout, start + Duration::from_millis(500));
+        let gestures = recognizer.process_timeout(timeout);
+        assert_eq!(
+            gestures,
+            vec![Gesture::LongPress { surface: surface.clone(), position: (5., 5.) }]
+        );
+        assert_eq!(recognizer.next_timeout(), None);
+        assert!(recognizer.process_frame(&[up(&surface, 1, (5., 5.))], timeout).is_empty());
+    }
+
+    #[test]
+    fn pan_and_pinch() {
+        let (surface, _server) = surface();
+        let mut recognizer = GestureRecognizer::default();
+        let now = Instant::now();
+
+        recognizer.process_frame(&[down(&surface, 0, (0., 0.)), down(&surface, 1, (10., 0.))], now);
+        let gestures = recognizer
+            .process_frame(&[motion(&surface, 0, (-5., 0.)), motion(&surface, 1, (25., 0.))], now);
+        assert_eq!(
+            gestures,
+            vec![
+                Gesture::Pan {
+                    surface: surface.clone(),
+                    phase: GesturePhase::Begin,
+                    position: (10., 0.),
+                    delta: (5., 0.),
+                },
+                Gesture::Pinch {
+                    surface: surface.clone(),
+                    phase: GesturePhase::Begin,
+                    center: (10., 0.),
+                    scale: 3.,
+                },
+            ]
+        );
+
+        // Releasing a touch point ends the pinch, but not the pan.
+        let gestures = recognizer.process_frame(&[up(&surface, 1, (25., 0.))], now);
+        assert_eq!(
+            gestures,
+            vec![Gesture::Pinch {
+                surface: surface.clone(),
+                phase: GesturePhase::End,
+                center: (10., 0.),
+                scale: 3.,
+            }]
+        );
+
+        let gestures = recognizer.cancel();
+        assert_eq!(
+            gestures,
+            vec![Gesture::Pan {
+                surface: surface.clone(),
+                phase: GesturePhase::Cancel,
+                position: (-5., 0.),
+                delta: (0., 0.),
+            }]
+        );
+    }
+
+    #[test]
+    fn rotate() {
+        let (surface, _server) = surface();
+        let mut recognizer = GestureRecognizer::default();
+        let now = Instant::now();
+
+        recognizer
+            .process_frame(&[down(&surface, 0, (-10., 0.)), down(&surface, 1, (10., 0.))], now);
+        let gestures = recognizer
+            .process_frame(&[motion(&surface, 0, (0., -10.)), motion(&surface, 1, (0., 10.))], now);
+        assert_eq!(gestures.len(), 2);
+        let Gesture::Rotate { phase, angle, .. } = &gestures[1] else { panic!() };
+        assert_eq!(*phase, GesturePhase::Begin);
+        assert!((angle - PI / 2.).abs() < 1e-9);
+
+        // The angle doesn't wrap around when the touch points cross the -π to π boundary.
+        let gestures = recognizer
+            .process_frame(&[motion(&surface, 0, (10., 0.)), motion(&surface, 1, (-10., 0.))], now);
+        let Gesture::Rotate { angle, .. } = gestures.last().unwrap() else { panic!() };
+        assert!((angle - PI).abs() < 1e-9);
+        let gestures = recognizer
+            .process_frame(&[motion(&surface, 0, (0., 10.)), motion(&surface, 1, (0., -10.))], now);
+        let Gesture::Rotate { angle, .. } = gestures.last().unwrap() else { panic!() };
+        assert!((angle - 3. * PI / 2.).abs() < 1e-9);
+    }
+}
@@ -3,11 +3,31 @@ use std::sync::Mutex;
 use wayland_client::protocol::wl_seat::WlSeat;
 
 use wayland_client::protocol::wl_surface::WlSurface;
-use wayland_client::protocol::wl_touch::{Event as TouchEvent, WlTouch};
+use wayland_client::protocol::wl_touch::{self, WlTouch};
 use wayland_client::{Connection, QueueHandle};
 
 use crate::dispatch2::Dispatch2;
 
+pub mod gesture;
+
+/// A touch event, with the surface and the latest position of its touch point.
+#[derive(Debug, Clone)]
+pub struct TouchEvent {
+    pub surface: WlSurface,
+    pub id: i32,
+    pub position: (f64, f64),
+    pub kind: TouchEventKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum TouchEventKind {
+    Down { serial: u32, time: u32 },
+    Up { serial: u32, time: u32 },
+    Motion { time: u32 },
+    Shape { major: f64, minor: f64 },
+    Orientation { orientation: f64 },
+}
+
 #[derive(Debug)]
 pub struct TouchData<U> {
     seat: WlSeat,
@@ -43,12 +63,19 @@ impl<U> TouchData<U> {
 #[derive(Debug, Default)]
 pub(crate) struct TouchDataInner {
     events: Vec<TouchEvent>,
-    active_touch_points: Vec<i32>,
+    active_touch_points: Vec<TouchPoint>,
 
     /// The serial of the latest touch down event
     latest_down: Option<u32>,
 }
 
+#[derive(Debug)]
+struct TouchPoint {
+    id: i32,
+    surface: WlSurface,
+    position: (f64, f64),
+}
+
 pub trait TouchHandler: Sized {
     /// New touch point.
     ///
@@ -132,6 +159,22 @@ pub trait TouchHandler: Sized {
     /// This indicates that the compositor has cancelled the active touch sequence, for example due
     /// to detection of a touch gesture.
     fn cancel(&mut self, conn: &Connection, qh: &QueueHandle<Self>, touch: &WlTouch);
+
+    /// All the events of a touch frame, after they were reported one by one.
+    ///
+    /// This matches [`PointerHandler::pointer_frame`](crate::seat::pointer::PointerHandler::pointer_frame),
+    /// and the events can be given to a [`GestureRecognizer`](gesture::GestureRecognizer).
+    ///
+    /// The default implementation does nothing.
+    fn touch_frame(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        touch: &WlTouch,
+        events: &[TouchEvent],
+    ) {
+        let _ = (conn, qh, touch, events);
+    }
 }
 
 impl<D, U> Dispatch2<WlTouch, D> for TouchData<U>
@@ -142,64 +185,90 @@ where
         &self,
         data: &mut D,
         touch: &WlTouch,
-        event: TouchEvent,
+        event: wl_touch::Event,
         conn: &Connection,
         qh: &QueueHandle<D>,
     ) {
         let mut guard: std::sync::MutexGuard<'_, TouchDataInner> = self.inner.lock().unwrap();
 
-        let mut save_event = false;
         let mut process_events = false;
 
-        match &event {
-            // Buffer events until frame is received.
-            TouchEvent::Down { serial, id, .. } => {
-                guard.latest_down = Some(*serial);
-                save_event = true;
-                if let Err(insert_pos) = guard.active_touch_points.binary_search(id) {
-                    guard.active_touch_points.insert(insert_pos, *id);
+        // Buffer events until frame is received.
+        let point_event = match event {
+            wl_touch::Event::Down { serial, time, surface, id, x, y } => {
+                guard.latest_down = Some(serial);
+                let point = TouchPoint { id, surface, position: (x, y) };
+                match guard.active_touch_points.binary_search_by_key(&id, |point| point.id) {
+                    Ok(pos) => guard.active_touch_points[pos] = point,
+                    Err(insert_pos) => guard.active_touch_points.insert(insert_pos, point),
                 }
+                Some((id, TouchEventKind::Down { serial, time }))
             }
-            TouchEvent::Up { id, .. } => {
-                save_event = true;
-                if let Ok(remove_pos) = guard.active_touch_points.binary_search(id) {
-                    guard.active_touch_points.remove(remove_pos);
-                }
-
-                // Weston doesn't always send a frame even after the last touch point was released:
-                // https://gitlab.freedesktop.org/wayland/weston/-/issues/44
-                // Work around this by processing pending events when there are no more touch points
-                // active.
-                if guard.active_touch_points.is_empty() {
-                    process_events = true;
+            wl_touch::Event::Up { serial, time, id } => {
+                Some((id, TouchEventKind::Up { serial, time }))
+            }
+            wl_touch::Event::Motion { time, id, x, y } => {
+                if let Some(point) = guard.active_touch_points.iter_mut().find(|p| p.id == id) {
+                    point.position = (x, y);
                 }
+                Some((id, TouchEventKind::Motion { time }))
+            }
+            wl_touch::Event::Shape { id, major, minor } => {
+                Some((id, TouchEventKind::Shape { major, minor }))
             }
-            TouchEvent::Motion { .. }
-            | TouchEvent::Shape { .. }
-            | TouchEvent::Orientation { .. } => {
-                save_event = true;
+            wl_touch::Event::Orientation { id, orientation } => {
+                Some((id, TouchEventKind::Orientation { orientation }))
             }
             // Process all buffered events.
-            TouchEvent::Frame => {
+            wl_touch::Event::Frame => {
                 process_events = true;
+                None
             }
-            TouchEvent::Cancel => {
+            wl_touch::Event::Cancel => {
                 guard.events.clear();
                 guard.active_touch_points.clear();
 
+                drop(guard);
                 data.cancel(conn, qh, touch);
+                return;
             }
             _ => unreachable!(),
-        }
+        };
+
+        if let Some((id, kind)) = point_event {
+            if let Ok(pos) = guard.active_touch_points.binary_search_by_key(&id, |point| point.id) {
+                let point = &guard.active_touch_points[pos];
+                let event = TouchEvent {
+                    surface: point.surface.clone(),
+                    id,
+                    position: point.position,
+                    kind,
+                };
 
-        if save_event {
-            guard.events.push(event);
+                if let TouchEventKind::Up { .. } = event.kind {
+                    guard.active_touch_points.remove(pos);
+
+                    // Weston doesn't always send a frame even after the last touch point was released:
+                    // https://gitlab.freedesktop.org/wayland/weston/-/issues/44
+                    // Work around this by processing pending events when there are no more touch points
+                    // active.
+                    if guard.active_touch_points.is_empty() {
+                        process_events = true;
+                    }
+                }
+
+                guard.events.push(event);
+            }
         }
 
-        if process_events {
-            for event in guard.events.drain(..) {
+        if process_events && !guard.events.is_empty() {
+            let events = std::mem::take(&mut guard.events);
+            drop(guard);
+
+            for event in &events {
                 process_framed_event(data, touch, conn, qh, event);
             }
+            data.touch_frame(conn, qh, touch, &events);
         }
     }
 }
@@ -210,27 +279,26 @@ fn process_framed_event<D>(
     touch: &WlTouch,
     conn: &Connection,
     qh: &QueueHandle<D>,
-    event: TouchEvent,
+    event: &TouchEvent,
 ) where
     D: TouchHandler,
 {
-    match event {
-        TouchEvent::Down { serial, time, surface, id, x, y } => {
-            data.down(conn, qh, touch, serial, time, surface, id, (x, y));
+    let id = event.id;
+    match event.kind {
+        TouchEventKind::Down { serial, time } => {
+            data.down(conn, qh, touch, serial, time, event.surface.clone(), id, event.position);
         }
-        TouchEvent::Up { serial, time, id } => {
+        TouchEventKind::Up { serial, time } => {
             data.up(conn, qh, touch, serial, time, id);
         }
-        TouchEvent::Motion { time, id, x, y } => {
-            data.motion(conn, qh, touch, time, id, (x, y));
+        TouchEventKind::Motion { time } => {
+            data.motion(conn, qh, touch, time, id, event.position);
         }
-        TouchEvent::Shape { id, major, minor } => {
+        TouchEventKind::Shape { major, minor } => {
             data.shape(conn, qh, touch, id, major, minor);
         }
-        TouchEvent::Orientation { id, orientation } => {
+        TouchEventKind::Orientation { orientation } => {
             data.orientation(conn, qh, touch, id, orientation);
         }
-        // No other events should be frame-buffered.
-        _ => unreachable!(),
     }
 }
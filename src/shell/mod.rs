@@ -43,10 +43,12 @@
 //! [`Layer`]: self::layer::LayerSurface
 
 use wayland_client::{
-    protocol::{wl_buffer, wl_output, wl_region, wl_surface},
-    Proxy,
+    protocol::{wl_buffer, wl_callback, wl_output, wl_region, wl_surface},
+    Dispatch, Proxy, QueueHandle,
 };
 
+use crate::compositor::{FrameCallbackData, Rect};
+
 pub mod wlr_layer;
 pub mod xdg;
 
@@ -72,9 +74,36 @@ pub trait WaylandSurface: Sized {
         }
     }
 
-    // TODO: Damage (Buffer and Surface-local)
+    /// Mark a region of the attached buffer as damaged, in buffer coordinates.
+    ///
+    /// Before version 4 of `wl_surface`, the region is converted to surface-local coordinates with
+    /// the buffer `scale`, assuming the buffer isn't transformed.
+    fn damage_buffer(&self, rect: Rect<i32>, scale: i32) {
+        if self.wl_surface().version() >= 4 {
+            self.wl_surface().damage_buffer(rect.x, rect.y, rect.width, rect.height);
+        } else {
+            let rect = buffer_damage_to_surface(rect, scale);
+            self.wl_surface().damage(rect.x, rect.y, rect.width, rect.height);
+        }
+    }
 
-    // TODO: Frame (a nice helper for this could exist).
+    /// Mark a region of the surface as damaged, in surface-local coordinates.
+    fn damage(&self, rect: Rect<i32>) {
+        self.wl_surface().damage(rect.x, rect.y, rect.width, rect.height);
+    }
+
+    /// Request a frame callback, reported to
+    /// [`CompositorHandler::frame`](crate::compositor::CompositorHandler::frame).
+    ///
+    /// The callback is requested for the next commit.
+    /// [`FrameScheduler`](crate::compositor::FrameScheduler) may be used instead to merge repeated
+    /// redraw requests.
+    fn request_frame<D>(&self, qh: &QueueHandle<D>)
+    where
+        D: Dispatch<wl_callback::WlCallback, FrameCallbackData> + 'static,
+    {
+        self.wl_surface().frame(qh, FrameCallbackData(self.wl_surface().clone()));
+    }
 
     fn set_opaque_region(&self, region: Option<&wl_region::WlRegion>) {
         self.wl_surface().set_opaque_region(region);
@@ -132,3 +161,37 @@ pub trait WaylandSurface: Sized {
         self.wl_surface().commit();
     }
 }
+
+/// Convert buffer damage to surface-local damage, rounding outwards.
+fn buffer_damage_to_surface(rect: Rect<i32>, scale: i32) -> Rect<i32> {
+    // Damage is often `i32::MAX` wide to damage everything, so this can't be done in `i32`.
+    let scale = i64::from(scale.max(1));
+    let (x, y) = (i64::from(rect.x).div_euclid(scale), i64::from(rect.y).div_euclid(scale));
+    let right = (i64::from(rect.x) + i64::from(rect.width) + scale - 1).div_euclid(scale);
+    let bottom = (i64::from(rect.y) + i64::from(rect.height) + scale - 1).div_euclid(scale);
+
+    let clamp = |value: i64| value.clamp(i32::MIN.into(), i32::MAX.into()) as i32;
+    Rect::new(clamp(x), clamp(y), clamp(right - x), clamp(bottom - y))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn buffer_damage() {
+        assert_eq!(buffer_damage_to_surface(Rect::new(3, 4, 5, 6), 1), Rect::new(3, 4, 5, 6));
+        assert_eq!(buffer_damage_to_surface(Rect::new(3, 4, 5, 6), 2), Rect::new(1, 2, 3, 3));
+
+        let everything = Rect::new(0, 0, i32::MAX, i32::MAX);
+        assert_eq!(buffer_damage_to_surface(everything, 1), everything);
+        assert_eq!(
+            buffer_damage_to_surface(everything, 2),
+            Rect::new(0, 0, i32::MAX / 2 + 1, i32::MAX / 2 + 1)
+        );
+        assert_eq!(
+            buffer_damage_to_surface(Rect::new(10, 10, i32::MAX, i32::MAX), 3),
+            Rect::new(3, 3, 715_827_883, 715_827_883)
+        );
+    }
+}
@@ -140,6 +140,8 @@ impl Dialog {
                     xdg_toplevel,
                     toplevel_decoration,
                     pending_configure: Mutex::new(Default::default()),
+                    decorations,
+                    frame: Mutex::new(Default::default()),
                 },
             }
         });
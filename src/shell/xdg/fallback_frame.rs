@@ -1,5 +1,6 @@
 //! The default fallback frame which is intended to show some very basic derocations.
 
+use std::fmt;
 use std::mem;
 use std::sync::Arc;
 use std::time::Duration;
@@ -14,14 +15,14 @@ use crate::reexports::csd_frame::{
 };
 
 use crate::{
-    compositor::SurfaceData,
+    compositor::{Rect, Region, SurfaceData},
     seat::pointer::CursorIcon,
     shell::WaylandSurface,
     shm::{slot::SlotPool, Shm},
     subcompositor::{SubcompositorState, SubsurfaceData},
 };
 
-use wayland_client::backend::ObjectId;
+use wayland_client::backend::{smallvec::SmallVec, ObjectId};
 
 /// The size of the header bar.
 const HEADER_SIZE: u32 = 24;
@@ -41,9 +42,209 @@ const BTN_HOVER_BG: u32 = 0xFF808080;
 const PRIMARY_COLOR_ACTIVE: u32 = 0xFF3A3A3A;
 const PRIMARY_COLOR_INACTIVE: u32 = 0xFF242424;
 
+const TITLE_COLOR_ACTIVE: u32 = 0xFFCCCCCC;
+const TITLE_COLOR_INACTIVE: u32 = 0xFF808080;
+
+/// The horizontal space kept free around the title.
+const TITLE_PADDING: u32 = 8;
+
+/// How far the resize handles extend into the shadow.
+const SHADOW_RESIZE_MARGIN: u32 = 8;
+
+/// The opacity of the shadow next to the frame.
+const SHADOW_ALPHA: f64 = 0.3;
+
+/// A part of the frame, each part is drawn on its own subsurface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FramePartKind {
+    /// The header bar, above the window.
+    Header,
+    /// The border above the header bar.
+    TopBorder,
+    /// The border right of the window and the header bar.
+    RightBorder,
+    /// The border below the window.
+    BottomBorder,
+    /// The border left of the window and the header bar.
+    LeftBorder,
+}
+
+impl FramePartKind {
+    fn from_index(idx: usize) -> Self {
+        match idx {
+            HEADER => Self::Header,
+            TOP_BORDER => Self::TopBorder,
+            RIGHT_BORDER => Self::RightBorder,
+            BOTTOM_BORDER => Self::BottomBorder,
+            _ => Self::LeftBorder,
+        }
+    }
+}
+
+/// The state of a header bar button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ButtonState {
+    pub button: FrameButton,
+
+    /// The area of the button in the header bar, in logical pixels.
+    pub rect: Rect<u32>,
+
+    /// Whether the pointer is over the button.
+    pub hovered: bool,
+
+    /// Whether the button is pressed.
+    pub pressed: bool,
+}
+
+/// Everything needed to draw a part of the frame.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy)]
+pub struct FramePartContext<'a> {
+    /// The part to draw.
+    pub part: FramePartKind,
+
+    /// The width of the part in logical pixels.
+    pub width: u32,
+
+    /// The height of the part in logical pixels.
+    pub height: u32,
+
+    /// The scale of the buffer.
+    pub scale: u32,
+
+    /// The state of the window.
+    pub state: WindowState,
+
+    /// The title of the window.
+    pub title: Option<&'a str>,
+
+    /// The buttons shown in the header bar, only set for [`FramePartKind::Header`].
+    pub buttons: &'a [ButtonState],
+
+    /// The area of the whole frame without its shadow, relative to this part, in logical pixels.
+    ///
+    /// Everything outside of it is shadow.
+    pub frame_rect: Rect<i32>,
+
+    /// The size of the shadow, which is `0` when the window is maximized or tiled.
+    pub shadow_size: u32,
+
+    /// The radius of the corners of [`FramePartContext::frame_rect`], which is `0` when the window is
+    /// maximized or tiled.
+    pub corner_radius: u32,
+}
+
+/// Draws the parts of a [`FallbackFrame`].
+///
+/// The frame takes care of the layout of the parts, hit testing and resizing, so a renderer only
+/// decides how the frame looks.
+///
+/// The frame marks [`FramePartContext::frame_rect`] as opaque, except for its rounded corners, so the
+/// renderer must draw it without transparency.
+pub trait FrameRenderer {
+    /// Draw a part of the frame.
+    ///
+    /// The `canvas` is an [`Argb8888`](wl_shm::Format::Argb8888) buffer of `width * scale` by
+    /// `height * scale` pixels, without padding between rows.
+    fn draw_part(&mut self, canvas: &mut [u8], context: &FramePartContext<'_>);
+
+    /// The size of the shadow around the frame, in logical pixels.
+    ///
+    /// The shadow is drawn on the borders, and is not part of the window geometry.
+    fn shadow_size(&self) -> u32 {
+        0
+    }
+
+    /// The radius of the corners of the frame, in logical pixels.
+    fn corner_radius(&self) -> u32 {
+        0
+    }
+}
+
+/// A rasterized text.
+///
+/// The pixels are premultiplied [`Argb8888`](wl_shm::Format::Argb8888), without padding between
+/// rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TitleImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// How the title should be rasterized.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TitleStyle {
+    /// The height available for the text, in buffer pixels.
+    pub max_height: u32,
+
+    /// The scale of the buffer the text is drawn on.
+    ///
+    /// With fractional scaling this is the preferred scale rounded up, and the compositor scales the
+    /// frame down.
+    pub scale: u32,
+
+    /// The color of the text, as `0xAARRGGBB`.
+    pub color: u32,
+}
+
+/// Rasterizes the title drawn by [`DefaultFrameRenderer`].
+///
+/// This is implemented for closures taking the text and the [`TitleStyle`].
+pub trait TitleRasterizer: Send + Sync {
+    /// Rasterize a single line of text.
+    ///
+    /// The returned image may be wider than the header bar, in which case the title is truncated.
+    fn rasterize(&self, text: &str, style: &TitleStyle) -> Option<TitleImage>;
+}
+
+impl<F> TitleRasterizer for F
+where
+    F: Fn(&str, &TitleStyle) -> Option<TitleImage> + Send + Sync,
+{
+    fn rasterize(&self, text: &str, style: &TitleStyle) -> Option<TitleImage> {
+        self(text, style)
+    }
+}
+
+/// The default ugly look of [`FallbackFrame`].
+///
+/// The title is only drawn when a [`TitleRasterizer`] is provided.
+#[derive(Default, Clone)]
+pub struct DefaultFrameRenderer {
+    title_rasterizer: Option<Arc<dyn TitleRasterizer>>,
+
+    shadow_size: u32,
+
+    corner_radius: u32,
+
+    /// The latest rasterized title.
+    title_cache: Option<TitleCache>,
+}
+
+impl fmt::Debug for DefaultFrameRenderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DefaultFrameRenderer")
+            .field("title_rasterizer", &self.title_rasterizer.is_some())
+            .field("shadow_size", &self.shadow_size)
+            .field("corner_radius", &self.corner_radius)
+            .field("title_cache", &self.title_cache)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TitleCache {
+    title: String,
+    style: TitleStyle,
+    max_width: u32,
+    image: Option<TitleImage>,
+}
+
 /// The default ugly frame.
 #[derive(Debug)]
-pub struct FallbackFrame<State> {
+pub struct FallbackFrame<State, R = DefaultFrameRenderer> {
     /// The parent surface.
     parent: WlSurface,
 
@@ -86,7 +287,19 @@ pub struct FallbackFrame<State> {
     subcompositor: Arc<SubcompositorState>,
 
     /// Buttons state.
-    buttons: [Option<UIButton>; 3],
+    buttons: [Option<FrameButton>; 3],
+
+    /// The button being pressed.
+    pressed_button: Option<FrameButton>,
+
+    /// The window title.
+    title: Option<String>,
+
+    /// The size of the window inside of the frame.
+    size: (u32, u32),
+
+    /// The renderer drawing the frame.
+    renderer: R,
 }
 
 impl<State> FallbackFrame<State>
@@ -98,6 +311,23 @@ where
         shm: &Shm,
         subcompositor: Arc<SubcompositorState>,
         queue_handle: QueueHandle<State>,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::with_renderer(parent, shm, subcompositor, queue_handle, DefaultFrameRenderer::new())
+    }
+}
+
+impl<State, R> FallbackFrame<State, R>
+where
+    State: Dispatch<WlSurface, SurfaceData<()>> + Dispatch<WlSubsurface, SubsurfaceData> + 'static,
+    R: FrameRenderer,
+{
+    /// Create a frame drawn by a custom renderer.
+    pub fn with_renderer(
+        parent: &impl WaylandSurface,
+        shm: &Shm,
+        subcompositor: Arc<SubcompositorState>,
+        queue_handle: QueueHandle<State>,
+        renderer: R,
     ) -> Result<Self, Box<dyn Error>> {
         let parent = parent.wl_surface().clone();
         let pool = SlotPool::new(1, shm)?;
@@ -119,23 +349,180 @@ where
             mouse_location: Location::None,
             mouse_coords: (0, 0),
             buttons: Self::supported_buttons(wm_capabilities),
+            pressed_button: None,
+            title: None,
+            size: (0, 0),
+            renderer,
         })
     }
 
-    fn supported_buttons(wm_capabilities: WindowManagerCapabilities) -> [Option<UIButton>; 3] {
+    /// The surfaces of the frame parts.
+    pub(crate) fn surfaces(&self) -> impl Iterator<Item = &WlSurface> {
+        self.render_data
+            .iter()
+            .flat_map(|render_data| render_data.parts.iter().map(|part| &part.surface))
+    }
+
+    /// The renderer drawing the frame.
+    pub fn renderer(&self) -> &R {
+        &self.renderer
+    }
+
+    /// Mutable access to the renderer drawing the frame.
+    ///
+    /// The frame is marked as dirty, since the renderer may draw differently afterwards.
+    pub fn renderer_mut(&mut self) -> &mut R {
+        self.dirty = true;
+        self.should_sync = true;
+        &mut self.renderer
+    }
+
+    /// Whether the window is drawn without shadows and rounded corners.
+    fn is_snapped(&self) -> bool {
+        self.state.intersects(WindowState::MAXIMIZED | WindowState::TILED)
+    }
+
+    /// The size of the shadow, which is not drawn when the window is maximized or tiled.
+    fn shadow_size(&self) -> u32 {
+        if self.is_snapped() {
+            0
+        } else {
+            self.renderer.shadow_size()
+        }
+    }
+
+    /// The radius of the corners, which are square when the window is maximized or tiled.
+    fn corner_radius(&self) -> u32 {
+        if self.is_snapped() {
+            0
+        } else {
+            self.renderer.corner_radius()
+        }
+    }
+
+    /// The area of the frame without the shadow, relative to the window.
+    fn frame_rect(&self) -> Rect<i32> {
+        let (width, height) = self.size;
+        Rect::new(
+            -(BORDER_SIZE as i32),
+            -((HEADER_SIZE + BORDER_SIZE) as i32),
+            (width + 2 * BORDER_SIZE) as i32,
+            (height + HEADER_SIZE + 2 * BORDER_SIZE) as i32,
+        )
+    }
+
+    /// Position the parts around the window, with room for the shadow on the borders.
+    fn update_layout(&mut self) {
+        let border = BORDER_SIZE + self.shadow_size();
+        let (width, height) = self.size;
+        let parts = match self.render_data.as_mut() {
+            Some(render_data) => &mut render_data.parts,
+            None => return,
+        };
+
+        parts[HEADER].width = width;
+        parts[HEADER].height = HEADER_SIZE;
+        parts[HEADER].pos = (0, -(HEADER_SIZE as i32));
+
+        parts[TOP_BORDER].width = width + 2 * border;
+        parts[TOP_BORDER].height = border;
+        parts[TOP_BORDER].pos = (-(border as i32), -((HEADER_SIZE + border) as i32));
+
+        parts[RIGHT_BORDER].width = border;
+        parts[RIGHT_BORDER].height = height + HEADER_SIZE;
+        parts[RIGHT_BORDER].pos = (width as i32, -(HEADER_SIZE as i32));
+
+        parts[BOTTOM_BORDER].width = width + 2 * border;
+        parts[BOTTOM_BORDER].height = border;
+        parts[BOTTOM_BORDER].pos = (-(border as i32), height as i32);
+
+        parts[LEFT_BORDER].width = border;
+        parts[LEFT_BORDER].height = height + HEADER_SIZE;
+        parts[LEFT_BORDER].pos = (-(border as i32), -(HEADER_SIZE as i32));
+    }
+
+    /// Update the input and opaque regions of the parts.
+    ///
+    /// The resize handles extend into the shadow, and everything but the shadow and the rounded
+    /// corners is opaque.
+    fn update_regions(&self, render_data: &FrameRenderData) {
+        let frame_rect = self.frame_rect();
+        let margin = self.shadow_size().min(SHADOW_RESIZE_MARGIN) as i32;
+        let radius = self.corner_radius() as i32;
+
+        for part in &render_data.parts {
+            let rect = Rect::new(
+                frame_rect.x - part.pos.0,
+                frame_rect.y - part.pos.1,
+                frame_rect.width,
+                frame_rect.height,
+            );
+
+            if let Ok(input) = Region::new(&*self.subcompositor) {
+                input.add(
+                    rect.x - margin,
+                    rect.y - margin,
+                    rect.width + 2 * margin,
+                    rect.height + 2 * margin,
+                );
+                part.surface.set_input_region(Some(input.wl_region()));
+            }
+
+            if let Ok(opaque) = Region::new(&*self.subcompositor) {
+                opaque.add(rect.x, rect.y, rect.width, rect.height);
+                if radius > 0 {
+                    let right = rect.x + rect.width - radius;
+                    let bottom = rect.y + rect.height - radius;
+                    for (x, y) in
+                        [(rect.x, rect.y), (right, rect.y), (rect.x, bottom), (right, bottom)]
+                    {
+                        opaque.subtract(x, y, radius, radius);
+                    }
+                }
+                part.surface.set_opaque_region(Some(opaque.wl_region()));
+            }
+        }
+    }
+
+    fn supported_buttons(wm_capabilities: WindowManagerCapabilities) -> [Option<FrameButton>; 3] {
         let maximize = wm_capabilities
             .contains(WindowManagerCapabilities::MAXIMIZE)
-            .then_some(UIButton::Maximize);
+            .then_some(FrameButton::Maximize);
         let minimize = wm_capabilities
             .contains(WindowManagerCapabilities::MINIMIZE)
-            .then_some(UIButton::Minimize);
-        [Some(UIButton::Close), maximize, minimize]
+            .then_some(FrameButton::Minimize);
+        [Some(FrameButton::Close), maximize, minimize]
+    }
+
+    /// The state of the buttons which fit in a header bar of the given width.
+    fn button_states(&self, width: u32) -> SmallVec<[ButtonState; 3]> {
+        self.buttons
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|&(idx, _)| width >= (idx as u32 + 1) * HEADER_SIZE)
+            .map(|(idx, &button)| {
+                let hovered = self.mouse_location == Location::Button(button);
+                ButtonState {
+                    button,
+                    rect: Rect::new(
+                        width - (idx as u32 + 1) * HEADER_SIZE,
+                        0,
+                        HEADER_SIZE,
+                        HEADER_SIZE,
+                    ),
+                    hovered,
+                    pressed: hovered && self.pressed_button == Some(button),
+                }
+            })
+            .collect()
     }
 
     fn precise_location(
-        buttons: &[Option<UIButton>],
+        buttons: &[Option<FrameButton>],
         old: Location,
         width: u32,
+        border: u32,
         x: f64,
         y: f64,
     ) -> Location {
@@ -143,9 +530,9 @@ where
             Location::Head | Location::Button(_) => Self::find_button(buttons, x, y, width),
 
             Location::Top | Location::TopLeft | Location::TopRight => {
-                if x <= f64::from(BORDER_SIZE) {
+                if x <= f64::from(border) {
                     Location::TopLeft
-                } else if x >= f64::from(width - BORDER_SIZE) {
+                } else if x >= f64::from(width - border) {
                     Location::TopRight
                 } else {
                     Location::Top
@@ -153,9 +540,9 @@ where
             }
 
             Location::Bottom | Location::BottomLeft | Location::BottomRight => {
-                if x <= f64::from(BORDER_SIZE) {
+                if x <= f64::from(border) {
                     Location::BottomLeft
-                } else if x >= f64::from(width - BORDER_SIZE) {
+                } else if x >= f64::from(width - border) {
                     Location::BottomRight
                 } else {
                     Location::Bottom
@@ -166,7 +553,7 @@ where
         }
     }
 
-    fn find_button(buttons: &[Option<UIButton>], x: f64, y: f64, w: u32) -> Location {
+    fn find_button(buttons: &[Option<FrameButton>], x: f64, y: f64, w: u32) -> Location {
         for (idx, &button) in buttons.iter().flatten().enumerate() {
             let idx = idx as u32;
             if w >= (idx + 1) * HEADER_SIZE
@@ -186,36 +573,189 @@ where
     fn part_index_for_surface(&mut self, surface_id: &ObjectId) -> Option<usize> {
         self.render_data.as_ref()?.parts.iter().position(|part| &part.surface.id() == surface_id)
     }
+}
+
+impl FrameRenderer for DefaultFrameRenderer {
+    fn draw_part(&mut self, canvas: &mut [u8], context: &FramePartContext<'_>) {
+        let is_active = context.state.contains(WindowState::ACTIVATED);
+        let fill_color =
+            if is_active { PRIMARY_COLOR_ACTIVE } else { PRIMARY_COLOR_INACTIVE }.to_le_bytes();
+
+        if context.shadow_size == 0 && context.corner_radius == 0 {
+            // Fill the canvas.
+            for pixel in canvas.chunks_exact_mut(4) {
+                pixel[0] = fill_color[0];
+                pixel[1] = fill_color[1];
+                pixel[2] = fill_color[2];
+                pixel[3] = fill_color[3];
+            }
+        } else {
+            Self::draw_rounded_frame(canvas, context, fill_color);
+        }
+
+        if context.part == FramePartKind::Header {
+            if let Some(title) = context.title {
+                self.draw_title(canvas, context, title, is_active);
+            }
+
+            // Draw the buttons for the header.
+            Self::draw_buttons(context.buttons, canvas, context.width, context.scale, is_active);
+        }
+    }
+
+    fn shadow_size(&self) -> u32 {
+        self.shadow_size
+    }
+
+    fn corner_radius(&self) -> u32 {
+        self.corner_radius
+    }
+}
+
+impl DefaultFrameRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw the window title, using the given rasterizer.
+    pub fn with_title_rasterizer(rasterizer: impl TitleRasterizer + 'static) -> Self {
+        Self { title_rasterizer: Some(Arc::new(rasterizer)), ..Self::default() }
+    }
+
+    /// Set the size of the shadow drawn around the frame, in logical pixels.
+    pub fn set_shadow_size(&mut self, shadow_size: u32) {
+        self.shadow_size = shadow_size;
+    }
+
+    /// Set the radius of the corners of the frame, in logical pixels.
+    pub fn set_corner_radius(&mut self, corner_radius: u32) {
+        self.corner_radius = corner_radius;
+    }
+
+    /// Fill the frame with rounded corners, and the shadow around it.
+    fn draw_rounded_frame(canvas: &mut [u8], context: &FramePartContext<'_>, fill_color: [u8; 4]) {
+        let scale = f64::from(context.scale);
+        let width = (context.width * context.scale) as usize;
+        let rect = context.frame_rect;
+        let radius = f64::from(context.corner_radius);
+        let shadow_size = f64::from(context.shadow_size);
+
+        for (idx, pixel) in canvas.chunks_exact_mut(4).enumerate() {
+            // Sample the center of the pixel.
+            let x = ((idx % width) as f64 + 0.5) / scale;
+            let y = ((idx / width) as f64 + 0.5) / scale;
+            let distance = rounded_rect_distance(rect, radius, x, y);
+
+            // Antialias the edge of the frame over one buffer pixel.
+            let coverage = (0.5 - distance * scale).clamp(0., 1.);
+            let shadow = if shadow_size > 0. && distance > 0. {
+                SHADOW_ALPHA * (1. - distance / shadow_size).clamp(0., 1.).powi(2)
+            } else {
+                0.
+            };
+
+            // The shadow is black, so it only contributes to the alpha.
+            for channel in 0..3 {
+                pixel[channel] = (f64::from(fill_color[channel]) * coverage).round() as u8;
+            }
+            pixel[3] = ((f64::from(fill_color[3]) * coverage + 255. * shadow * (1. - coverage))
+                .round())
+            .min(255.) as u8;
+        }
+    }
+
+    fn draw_title(
+        &mut self,
+        canvas: &mut [u8],
+        context: &FramePartContext<'_>,
+        title: &str,
+        is_active: bool,
+    ) {
+        let scale = context.scale;
+        let canvas_width = context.width * scale;
+        let canvas_height = context.height * scale;
+
+        // Keep the title between the left edge and the buttons.
+        let left = TITLE_PADDING * scale;
+        let right = (context.width.saturating_sub(context.buttons.len() as u32 * HEADER_SIZE))
+            .saturating_sub(TITLE_PADDING)
+            * scale;
+        if right <= left {
+            return;
+        }
+
+        let style = TitleStyle {
+            max_height: canvas_height,
+            scale,
+            color: if is_active { TITLE_COLOR_ACTIVE } else { TITLE_COLOR_INACTIVE },
+        };
+        let image = match self.title_image(title, style, right - left) {
+            Some(image) => image,
+            None => return,
+        };
+
+        // Center the title in the header, unless it would overlap the buttons.
+        let x = (canvas_width.saturating_sub(image.width) / 2)
+            .min(right.saturating_sub(image.width))
+            .max(left);
+        let y = canvas_height.saturating_sub(image.height) / 2;
+
+        blend_image(canvas, canvas_width, canvas_height, image, x, y);
+    }
+
+    /// The rasterized title, which is only rasterized again when something changed.
+    fn title_image(
+        &mut self,
+        title: &str,
+        style: TitleStyle,
+        max_width: u32,
+    ) -> Option<&TitleImage> {
+        let rasterizer = self.title_rasterizer.as_ref()?;
+
+        let up_to_date = self.title_cache.as_ref().is_some_and(|cache| {
+            cache.title == title && cache.style == style && cache.max_width == max_width
+        });
+
+        if !up_to_date {
+            self.title_cache = Some(TitleCache {
+                title: title.to_owned(),
+                style,
+                max_width,
+                image: rasterize_title(&**rasterizer, title, &style, max_width),
+            });
+        }
+
+        self.title_cache.as_ref()?.image.as_ref()
+    }
 
     fn draw_buttons(
-        buttons: &[Option<UIButton>],
+        buttons: &[ButtonState],
         canvas: &mut [u8],
         width: u32,
         scale: u32,
         is_active: bool,
-        mouse_location: &Location,
     ) {
         let scale = scale as usize;
-        for (idx, &button) in buttons.iter().flatten().enumerate() {
-            if width >= (idx + 1) as u32 * HEADER_SIZE {
-                if is_active && mouse_location == &Location::Button(button) {
-                    Self::draw_button(
-                        canvas,
-                        idx * HEADER_SIZE as usize,
-                        scale,
-                        width as usize,
-                        BTN_HOVER_BG.to_le_bytes(),
-                    );
-                }
-                Self::draw_icon(
+        for button in buttons {
+            // Offset of the button from the right edge of the header.
+            let x_offset = (width - button.rect.x - button.rect.width) as usize;
+            if is_active && button.hovered {
+                Self::draw_button(
                     canvas,
-                    width as usize,
-                    idx * HEADER_SIZE as usize,
+                    x_offset,
                     scale,
-                    BTN_ICON_COLOR.to_le_bytes(),
-                    button,
+                    width as usize,
+                    BTN_HOVER_BG.to_le_bytes(),
                 );
             }
+            Self::draw_icon(
+                canvas,
+                width as usize,
+                x_offset,
+                scale,
+                BTN_ICON_COLOR.to_le_bytes(),
+                button.button,
+            );
         }
     }
 
@@ -247,14 +787,14 @@ where
         x_offset: usize,
         scale: usize,
         icon_color: [u8; 4],
-        icon: UIButton,
+        icon: FrameButton,
     ) {
         let h = HEADER_SIZE as usize;
         let sh = scale * h;
         let x_start = width - h - x_offset;
 
         match icon {
-            UIButton::Close => {
+            FrameButton::Close => {
                 // Draw black rectangle
                 for y in sh / 4..3 * sh / 4 {
                     let line = &mut canvas[(x_start + y * width + h / 4) * 4 * scale
@@ -267,7 +807,7 @@ where
                     }
                 }
             }
-            UIButton::Maximize => {
+            FrameButton::Maximize => {
                 // Draw an empty rectangle
                 for y in 2 * sh / 8..3 * sh / 8 {
                     let line = &mut canvas[(x_start + y * width + h / 4) * 4 * scale
@@ -308,7 +848,7 @@ where
                     }
                 }
             }
-            UIButton::Minimize => {
+            FrameButton::Minimize => {
                 // Draw an underline
                 for y in 5 * sh / 8..3 * sh / 4 {
                     let line = &mut canvas[(x_start + y * width + h / 4) * 4 * scale
@@ -325,9 +865,10 @@ where
     }
 }
 
-impl<State> DecorationsFrame for FallbackFrame<State>
+impl<State, R> DecorationsFrame for FallbackFrame<State, R>
 where
     State: Dispatch<WlSurface, SurfaceData<()>> + Dispatch<WlSubsurface, SubsurfaceData> + 'static,
+    R: FrameRenderer,
 {
     fn set_scaling_factor(&mut self, scale_factor: f64) {
         self.scale_factor = scale_factor;
@@ -355,17 +896,25 @@ where
             };
         }
 
+        // Track the pressed button, so it can be drawn as such.
+        let pressed_button = match self.mouse_location {
+            Location::Button(button) if pressed => Some(button),
+            _ => None,
+        };
+        self.dirty |= self.pressed_button != pressed_button;
+        self.pressed_button = pressed_button;
+
         let resize = pressed && self.resizable;
         match self.mouse_location {
             Location::Head if pressed => Some(FrameAction::Move),
-            Location::Button(UIButton::Close) if !pressed => Some(FrameAction::Close),
-            Location::Button(UIButton::Minimize) if !pressed => Some(FrameAction::Minimize),
-            Location::Button(UIButton::Maximize)
+            Location::Button(FrameButton::Close) if !pressed => Some(FrameAction::Close),
+            Location::Button(FrameButton::Minimize) if !pressed => Some(FrameAction::Minimize),
+            Location::Button(FrameButton::Maximize)
                 if !pressed && !self.state.contains(WindowState::MAXIMIZED) =>
             {
                 Some(FrameAction::Maximize)
             }
-            Location::Button(UIButton::Maximize)
+            Location::Button(FrameButton::Maximize)
                 if !pressed && self.state.contains(WindowState::MAXIMIZED) =>
             {
                 Some(FrameAction::UnMaximize)
@@ -404,6 +953,7 @@ where
             &self.buttons,
             location,
             self.render_data.as_ref().unwrap().parts[part_index].width,
+            BORDER_SIZE + self.shadow_size(),
             x,
             y,
         );
@@ -428,6 +978,7 @@ where
 
     fn click_point_left(&mut self) {
         self.mouse_location = Location::None;
+        self.pressed_button = None;
         self.dirty = true;
     }
 
@@ -442,6 +993,8 @@ where
             let _ = self.pool.resize(1);
             self.render_data =
                 Some(FrameRenderData::new(&self.parent, &self.subcompositor, &self.queue_handle));
+            self.update_layout();
+            self.should_sync = true;
         }
     }
 
@@ -451,29 +1004,25 @@ where
 
     fn update_state(&mut self, state: WindowState) {
         let difference = self.state.symmetric_difference(state);
+        let was_snapped = self.is_snapped();
         self.state = state;
         self.dirty |= !difference
             .intersection(WindowState::ACTIVATED | WindowState::FULLSCREEN | WindowState::MAXIMIZED)
             .is_empty();
+
+        // The shadow and the rounded corners depend on the state.
+        if was_snapped != self.is_snapped() {
+            self.update_layout();
+            self.dirty = true;
+            self.should_sync = true;
+        }
     }
 
     fn resize(&mut self, width: NonZeroU32, height: NonZeroU32) {
-        let parts = &mut self.render_data.as_mut().expect("trying to resize hidden frame").parts;
-
-        let width = width.get();
-        let height = height.get();
-
-        parts[HEADER].width = width;
-
-        parts[TOP_BORDER].width = width + 2 * BORDER_SIZE;
+        assert!(!self.is_hidden(), "trying to resize hidden frame");
 
-        parts[BOTTOM_BORDER].width = width + 2 * BORDER_SIZE;
-        parts[BOTTOM_BORDER].pos.1 = height as i32;
-
-        parts[LEFT_BORDER].height = height + HEADER_SIZE;
-
-        parts[RIGHT_BORDER].height = parts[LEFT_BORDER].height;
-        parts[RIGHT_BORDER].pos.0 = width as i32;
+        self.size = (width.get(), height.get());
+        self.update_layout();
 
         self.dirty = true;
         self.should_sync = true;
@@ -510,7 +1059,9 @@ where
         if self.state.contains(WindowState::FULLSCREEN) || self.is_hidden() {
             (0, 0)
         } else {
-            self.render_data.as_ref().unwrap().parts[TOP_BORDER].pos
+            // The shadow is not part of the window geometry.
+            let frame_rect = self.frame_rect();
+            (frame_rect.x, frame_rect.y)
         }
     }
 
@@ -519,7 +1070,12 @@ where
     }
 
     fn draw(&mut self) -> bool {
-        let render_data = match self.render_data.as_mut() {
+        // The renderer may have changed the size of the shadow.
+        if self.should_sync {
+            self.update_layout();
+        }
+
+        let render_data = match self.render_data.as_ref() {
             Some(render_data) => render_data,
             None => return false,
         };
@@ -537,13 +1093,17 @@ where
             return should_sync;
         }
 
-        let is_active = self.state.contains(WindowState::ACTIVATED);
-        let fill_color =
-            if is_active { PRIMARY_COLOR_ACTIVE } else { PRIMARY_COLOR_INACTIVE }.to_le_bytes();
+        if should_sync {
+            self.update_regions(render_data);
+        }
+
+        let buttons = self.button_states(render_data.parts[HEADER].width);
+        let frame_rect = self.frame_rect();
+        let shadow_size = self.shadow_size();
+        let corner_radius = self.corner_radius();
 
         for (idx, part) in render_data.parts.iter().enumerate() {
-            // We don't support fractinal scaling here, so round up.
-            let scale = self.scale_factor.ceil() as i32;
+            let scale = buffer_scale(self.scale_factor) as i32;
 
             let (buffer, canvas) = match self.pool.create_buffer(
                 part.width as i32 * scale,
@@ -555,25 +1115,25 @@ where
                 Err(_) => continue,
             };
 
-            // Fill the canvas.
-            for pixel in canvas.chunks_exact_mut(4) {
-                pixel[0] = fill_color[0];
-                pixel[1] = fill_color[1];
-                pixel[2] = fill_color[2];
-                pixel[3] = fill_color[3];
-            }
-
-            // Draw the buttons for the header.
-            if idx == HEADER {
-                Self::draw_buttons(
-                    &self.buttons,
-                    canvas,
-                    part.width,
-                    scale as u32,
-                    is_active,
-                    &self.mouse_location,
-                );
-            }
+            let part_kind = FramePartKind::from_index(idx);
+            let context = FramePartContext {
+                part: part_kind,
+                width: part.width,
+                height: part.height,
+                scale: scale as u32,
+                state: self.state,
+                title: self.title.as_deref(),
+                buttons: if part_kind == FramePartKind::Header { &buttons } else { &[] },
+                frame_rect: Rect::new(
+                    frame_rect.x - part.pos.0,
+                    frame_rect.y - part.pos.1,
+                    frame_rect.width,
+                    frame_rect.height,
+                ),
+                shadow_size,
+                corner_radius,
+            };
+            self.renderer.draw_part(canvas, &context);
 
             part.surface.set_buffer_scale(scale);
             if should_sync {
@@ -604,7 +1164,92 @@ where
         self.buttons = Self::supported_buttons(capabilities);
     }
 
-    fn set_title(&mut self, _: impl Into<String>) {}
+    fn set_title(&mut self, title: impl Into<String>) {
+        let title = title.into();
+        self.dirty |= self.title.as_ref() != Some(&title);
+        self.title = Some(title);
+    }
+}
+
+/// The buffer scale the frame is drawn at.
+///
+/// We don't support fractional scaling here, so round up and let the compositor scale it down.
+fn buffer_scale(scale_factor: f64) -> u32 {
+    scale_factor.ceil() as u32
+}
+
+/// Rasterize the title, truncating it with an ellipsis to fit `max_width`.
+fn rasterize_title(
+    rasterizer: &dyn TitleRasterizer,
+    title: &str,
+    style: &TitleStyle,
+    max_width: u32,
+) -> Option<TitleImage> {
+    let image = rasterizer.rasterize(title, style)?;
+    if image.width <= max_width {
+        return Some(image);
+    }
+
+    // Find the longest prefix which fits along with the ellipsis.
+    let boundaries = title.char_indices().map(|(idx, _)| idx).collect::<Vec<_>>();
+    let (mut low, mut high) = (0, boundaries.len());
+    let mut truncated = None;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let text = format!("{}…", title[..boundaries[mid]].trim_end());
+        match rasterizer.rasterize(&text, style) {
+            Some(image) if image.width <= max_width => {
+                truncated = Some(image);
+                low = mid + 1;
+            }
+            _ => high = mid,
+        }
+    }
+
+    truncated
+}
+
+/// The signed distance from a point to a rectangle with rounded corners, negative inside of it.
+fn rounded_rect_distance(rect: Rect<i32>, radius: f64, x: f64, y: f64) -> f64 {
+    let half_width = f64::from(rect.width) / 2.;
+    let half_height = f64::from(rect.height) / 2.;
+    let radius = radius.min(half_width).min(half_height);
+
+    // Distance from the rectangle shrunk by the radius, which is then rounded by the radius.
+    let dx = (x - f64::from(rect.x) - half_width).abs() - (half_width - radius);
+    let dy = (y - f64::from(rect.y) - half_height).abs() - (half_height - radius);
+    let outside = dx.max(0.).hypot(dy.max(0.));
+    let inside = dx.max(dy).min(0.);
+    outside + inside - radius
+}
+
+/// Draw a premultiplied image over the canvas at the given position.
+fn blend_image(
+    canvas: &mut [u8],
+    canvas_width: u32,
+    canvas_height: u32,
+    image: &TitleImage,
+    x: u32,
+    y: u32,
+) {
+    if image.pixels.len() < image.width as usize * image.height as usize * 4 {
+        log::warn!(target: "sctk", "title image is smaller than its size");
+        return;
+    }
+
+    let width = image.width.min(canvas_width.saturating_sub(x)) as usize;
+    let height = image.height.min(canvas_height.saturating_sub(y)) as usize;
+    for row in 0..height {
+        let src = &image.pixels[row * image.width as usize * 4..][..width * 4];
+        let dst_start = ((y as usize + row) * canvas_width as usize + x as usize) * 4;
+        let dst = &mut canvas[dst_start..][..width * 4];
+        for (dst, src) in dst.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+            let alpha = src[3] as u32;
+            for (dst, &src) in dst.iter_mut().zip(src) {
+                *dst = (src as u32 + *dst as u32 * (255 - alpha) / 255).min(255) as u8;
+            }
+        }
+    }
 }
 
 /// Inner state to simplify dropping.
@@ -724,12 +1369,12 @@ enum Location {
     /// Top left corner.
     TopLeft,
     /// One of the buttons.
-    Button(UIButton),
+    Button(FrameButton),
 }
 
-/// The frame button.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum UIButton {
+/// A button of the header bar.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FrameButton {
     /// The minimize button, the left most.
     Minimize,
     /// The maximize button, in the middle.
@@ -737,3 +1382,167 @@ enum UIButton {
     /// The close botton, the right most.
     Close,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The width of each character drawn by [`rasterize`], in logical pixels.
+    const CHAR_WIDTH: u32 = 10;
+
+    /// Rasterize each character, including the ellipsis, as an opaque square.
+    fn rasterize(text: &str, style: &TitleStyle) -> Option<TitleImage> {
+        let width = text.chars().count() as u32 * CHAR_WIDTH * style.scale;
+        let height = CHAR_WIDTH * style.scale;
+        Some(TitleImage { width, height, pixels: vec![0xFF; (width * height * 4) as usize] })
+    }
+
+    fn style() -> TitleStyle {
+        TitleStyle { max_height: HEADER_SIZE, scale: 1, color: TITLE_COLOR_ACTIVE }
+    }
+
+    fn title_width(title: &str, max_width: u32) -> Option<u32> {
+        rasterize_title(&rasterize, title, &style(), max_width).map(|image| image.width)
+    }
+
+    /// Draw the title in a header bar, and return the first and last columns it covers.
+    fn title_columns(title: &str, width: u32, scale: u32, buttons: usize) -> Option<(u32, u32)> {
+        let buttons = vec![
+            ButtonState {
+                button: FrameButton::Close,
+                rect: Rect::new(0, 0, HEADER_SIZE, HEADER_SIZE),
+                hovered: false,
+                pressed: false,
+            };
+            buttons
+        ];
+        let context = FramePartContext {
+            part: FramePartKind::Header,
+            width,
+            height: HEADER_SIZE,
+            scale,
+            state: WindowState::empty(),
+            title: Some(title),
+            buttons: &buttons,
+            frame_rect: Rect::new(0, 0, width as i32, HEADER_SIZE as i32),
+            shadow_size: 0,
+            corner_radius: 0,
+        };
+
+        let canvas_width = (width * scale) as usize;
+        let mut canvas = vec![0; canvas_width * (HEADER_SIZE * scale) as usize * 4];
+        let mut renderer = DefaultFrameRenderer::with_title_rasterizer(rasterize);
+        renderer.draw_title(&mut canvas, &context, title, true);
+
+        let columns = canvas
+            .chunks_exact(4)
+            .enumerate()
+            .filter(|(_, pixel)| pixel.iter().any(|&channel| channel != 0))
+            .map(|(idx, _)| (idx % canvas_width) as u32);
+        let first = columns.clone().min()?;
+        Some((first, columns.max()?))
+    }
+
+    #[test]
+    fn title_truncation() {
+        // A title which fits exactly is left alone.
+        assert_eq!(title_width("hello", 50), Some(50));
+
+        // One pixel less drops two characters, to make room for the ellipsis.
+        assert_eq!(title_width("hello", 49), Some(40));
+
+        // The space before the ellipsis is trimmed.
+        assert_eq!(title_width("ab cd", 30), Some(30));
+
+        // The title is only cut at character boundaries.
+        assert_eq!(title_width("éèêë", 35), Some(30));
+
+        // Nothing fits, not even the ellipsis.
+        assert_eq!(title_width("hello", 9), None);
+    }
+
+    #[test]
+    fn empty_title() {
+        assert_eq!(title_width("", 0), Some(0));
+        assert_eq!(title_columns("", 200, 1, 0), None);
+    }
+
+    #[test]
+    fn title_centering() {
+        // Centered in the header bar.
+        assert_eq!(title_columns("abcd", 200, 1, 0), Some((80, 119)));
+
+        // Moved to the left so it doesn't overlap the buttons.
+        assert_eq!(title_columns("abcdefgh", 200, 1, 3), Some((40, 119)));
+
+        // Truncated to fit between the paddings, then centered.
+        assert_eq!(title_columns("abcdefghij", 100, 1, 0), Some((10, 89)));
+    }
+
+    #[test]
+    fn fractional_scale() {
+        let scale = buffer_scale(1.5);
+        assert_eq!(scale, 2);
+        assert_eq!(buffer_scale(1.), 1);
+
+        // The title is rasterized and centered in buffer pixels.
+        assert_eq!(title_columns("abcd", 200, scale, 0), Some((160, 239)));
+    }
+
+    #[test]
+    fn rounded_rect() {
+        let rect = Rect::new(10, 10, 100, 50);
+        let distance = |radius, x, y| rounded_rect_distance(rect, radius, x, y);
+
+        // Inside, the distance to the nearest edge is negative.
+        assert_eq!(distance(10., 60., 35.), -25.);
+        assert_eq!(distance(10., 10., 35.), 0.);
+        assert_eq!(distance(10., 5., 35.), 5.);
+
+        // The corners are rounded.
+        assert_eq!(distance(0., 10., 10.), 0.);
+        assert!((distance(10., 10., 10.) - 10. * (2f64.sqrt() - 1.)).abs() < 1e-9);
+        assert!(distance(10., 12., 12.) > 0.);
+        assert!(distance(0., 12., 12.) < 0.);
+
+        // The radius is limited to half of the smallest side.
+        assert!((distance(100., 10., 10.) - 25. * (2f64.sqrt() - 1.)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn blend() {
+        const BACKGROUND: [u8; 4] = [200; 4];
+
+        // Opaque, half transparent and transparent premultiplied pixels.
+        let image = TitleImage {
+            width: 3,
+            height: 1,
+            pixels: vec![10, 20, 30, 255, 64, 64, 64, 128, 0, 0, 0, 0],
+        };
+        let pixel = |canvas: &[u8], x: usize, y: usize| -> [u8; 4] {
+            canvas[(y * 3 + x) * 4..][..4].try_into().unwrap()
+        };
+
+        let mut canvas = BACKGROUND.repeat(3 * 2);
+        blend_image(&mut canvas, 3, 2, &image, 0, 1);
+        assert_eq!(pixel(&canvas, 0, 1), [10, 20, 30, 255]);
+        assert_eq!(pixel(&canvas, 1, 1), [163, 163, 163, 227]);
+        assert_eq!(pixel(&canvas, 2, 1), BACKGROUND);
+        assert!(canvas[..3 * 4].iter().all(|&channel| channel == 200));
+
+        // The image is clipped to the canvas.
+        let mut canvas = BACKGROUND.repeat(3 * 2);
+        blend_image(&mut canvas, 3, 2, &image, 2, 0);
+        assert_eq!(pixel(&canvas, 2, 0), [10, 20, 30, 255]);
+        assert_eq!(canvas.iter().filter(|&&channel| channel != 200).count(), 4);
+
+        let mut canvas = BACKGROUND.repeat(3 * 2);
+        blend_image(&mut canvas, 3, 2, &image, 3, 2);
+        assert_eq!(canvas, BACKGROUND.repeat(3 * 2));
+
+        // An image without enough pixels is not drawn.
+        let image = TitleImage { width: 4, ..image };
+        blend_image(&mut canvas, 3, 2, &image, 0, 0);
+        assert_eq!(canvas, BACKGROUND.repeat(3 * 2));
+    }
+}
@@ -167,6 +167,8 @@ impl XdgShell {
                 xdg_toplevel,
                 toplevel_decoration,
                 pending_configure: Mutex::new(Default::default()),
+                decorations,
+                frame: Mutex::new(Default::default()),
             }
         });
 
@@ -0,0 +1,285 @@
+//! Client side decorations managed by the window itself.
+
+use std::{error::Error, fmt, num::NonZeroU32, time::Duration};
+
+use crate::reexports::client::backend::ObjectId;
+use crate::reexports::csd_frame::{
+    DecorationsFrame, FrameAction, FrameClick, WindowManagerCapabilities, WindowState,
+};
+
+use crate::{seat::pointer::CursorIcon, shell::xdg::XdgSurface};
+
+use super::{DecorationMode, Window, WindowConfigure, WindowDecorations};
+
+/// Object safe subset of [`DecorationsFrame`], so the frame can be stored in the window.
+pub(crate) trait ManagedFrame: Send {
+    fn on_click(
+        &mut self,
+        timestamp: Duration,
+        click: FrameClick,
+        pressed: bool,
+    ) -> Option<FrameAction>;
+
+    fn click_point_moved(
+        &mut self,
+        timestamp: Duration,
+        surface_id: &ObjectId,
+        x: f64,
+        y: f64,
+    ) -> Option<CursorIcon>;
+
+    fn click_point_left(&mut self);
+
+    fn update_state(&mut self, state: WindowState);
+
+    fn update_wm_capabilities(&mut self, wm_capabilities: WindowManagerCapabilities);
+
+    fn resize(&mut self, width: NonZeroU32, height: NonZeroU32);
+
+    fn set_scaling_factor(&mut self, scale_factor: f64);
+
+    fn location(&self) -> (i32, i32);
+
+    fn subtract_borders(
+        &self,
+        width: NonZeroU32,
+        height: NonZeroU32,
+    ) -> (Option<NonZeroU32>, Option<NonZeroU32>);
+
+    fn add_borders(&self, width: u32, height: u32) -> (u32, u32);
+
+    fn is_dirty(&self) -> bool;
+
+    fn is_hidden(&self) -> bool;
+
+    fn draw(&mut self) -> bool;
+
+    fn set_title(&mut self, title: String);
+}
+
+impl<F> ManagedFrame for F
+where
+    F: DecorationsFrame + Send,
+{
+    fn on_click(
+        &mut self,
+        timestamp: Duration,
+        click: FrameClick,
+        pressed: bool,
+    ) -> Option<FrameAction> {
+        DecorationsFrame::on_click(self, timestamp, click, pressed)
+    }
+
+    fn click_point_moved(
+        &mut self,
+        timestamp: Duration,
+        surface_id: &ObjectId,
+        x: f64,
+        y: f64,
+    ) -> Option<CursorIcon> {
+        DecorationsFrame::click_point_moved(self, timestamp, surface_id, x, y)
+    }
+
+    fn click_point_left(&mut self) {
+        DecorationsFrame::click_point_left(self)
+    }
+
+    fn update_state(&mut self, state: WindowState) {
+        DecorationsFrame::update_state(self, state)
+    }
+
+    fn update_wm_capabilities(&mut self, wm_capabilities: WindowManagerCapabilities) {
+        DecorationsFrame::update_wm_capabilities(self, wm_capabilities)
+    }
+
+    fn resize(&mut self, width: NonZeroU32, height: NonZeroU32) {
+        DecorationsFrame::resize(self, width, height)
+    }
+
+    fn set_scaling_factor(&mut self, scale_factor: f64) {
+        DecorationsFrame::set_scaling_factor(self, scale_factor)
+    }
+
+    fn location(&self) -> (i32, i32) {
+        DecorationsFrame::location(self)
+    }
+
+    fn subtract_borders(
+        &self,
+        width: NonZeroU32,
+        height: NonZeroU32,
+    ) -> (Option<NonZeroU32>, Option<NonZeroU32>) {
+        DecorationsFrame::subtract_borders(self, width, height)
+    }
+
+    fn add_borders(&self, width: u32, height: u32) -> (u32, u32) {
+        DecorationsFrame::add_borders(self, width, height)
+    }
+
+    fn is_dirty(&self) -> bool {
+        DecorationsFrame::is_dirty(self)
+    }
+
+    fn is_hidden(&self) -> bool {
+        DecorationsFrame::is_hidden(self)
+    }
+
+    fn draw(&mut self) -> bool {
+        DecorationsFrame::draw(self)
+    }
+
+    fn set_title(&mut self, title: String) {
+        DecorationsFrame::set_title(self, title)
+    }
+}
+
+/// Creates the frame of a window, and returns it along with the surfaces it is made of.
+pub(crate) type FrameFactory = Box<
+    dyn Fn(&Window) -> Result<(Box<dyn ManagedFrame>, Vec<ObjectId>), Box<dyn Error>> + Send + Sync,
+>;
+
+/// The decorations state of a window.
+#[derive(Default)]
+pub(crate) struct ManagedDecorations {
+    /// Creates the frame, set when the decorations are managed.
+    pub factory: Option<FrameFactory>,
+
+    /// The frame, present while the negotiated decoration mode is client side.
+    pub frame: Option<Box<dyn ManagedFrame>>,
+
+    /// The surfaces of the frame.
+    pub surfaces: Vec<ObjectId>,
+
+    /// The latest window title, applied to new frames.
+    pub title: String,
+
+    /// The latest scale factor, applied to new frames.
+    pub scale_factor: Option<f64>,
+
+    /// The frame surface the pointer is over.
+    pub hovered: Option<ObjectId>,
+
+    /// The latest cursor requested by the frame.
+    pub cursor: Option<CursorIcon>,
+
+    /// The size of the window inside of the frame, once known.
+    pub size: Option<(NonZeroU32, NonZeroU32)>,
+}
+
+impl ManagedDecorations {
+    /// Create or destroy the frame according to the configure, remove the decorations from the
+    /// configured size and resize the frame to it.
+    pub fn configure(&mut self, window: &Window, configure: &mut WindowConfigure) {
+        let factory = match self.factory.as_ref() {
+            Some(factory) => factory,
+            None => return,
+        };
+
+        if configure.decoration_mode != DecorationMode::Client
+            || window.0.decorations == WindowDecorations::None
+        {
+            self.remove_frame(window);
+            return;
+        }
+
+        if self.frame.is_none() {
+            match factory(window) {
+                Ok((mut frame, surfaces)) => {
+                    frame.set_title(self.title.clone());
+                    if let Some(scale_factor) = self.scale_factor {
+                        frame.set_scaling_factor(scale_factor);
+                    }
+                    self.frame = Some(frame);
+                    self.surfaces = surfaces;
+                }
+                Err(err) => {
+                    log::error!(target: "sctk", "failed to create the window decorations: {err}");
+                    return;
+                }
+            }
+        }
+
+        let frame = self.frame.as_mut().unwrap();
+
+        // The state must be updated first, since it affects the size of the borders.
+        frame.update_state(configure.state);
+        frame.update_wm_capabilities(configure.capabilities);
+
+        if let (Some(width), Some(height)) = configure.new_size {
+            // The size could be smaller than the borders, so clamp it to at least one pixel.
+            let (width, height) = frame.subtract_borders(width, height);
+            let one = NonZeroU32::MIN;
+            configure.new_size = (Some(width.unwrap_or(one)), Some(height.unwrap_or(one)));
+        }
+
+        // Without a configured size, the frame waits for the window to pick one with
+        // `Window::resize_decorations`.
+        let size = match (configure.new_size, self.size) {
+            ((Some(width), Some(height)), _) => Some((width, height)),
+            ((width, height), Some(size)) => {
+                Some((width.unwrap_or(size.0), height.unwrap_or(size.1)))
+            }
+            _ => None,
+        };
+
+        if let Some((width, height)) = size {
+            self.resize(window, width, height);
+        }
+    }
+
+    /// Destroy the frame, and remove it from the window geometry.
+    fn remove_frame(&mut self, window: &Window) {
+        if self.frame.take().is_none() {
+            return;
+        }
+
+        self.surfaces.clear();
+        self.hovered = None;
+        self.cursor = None;
+
+        if let Some((width, height)) = self.size.take() {
+            window.xdg_surface().set_window_geometry(0, 0, width.get() as i32, height.get() as i32);
+        }
+    }
+
+    /// Resize the frame to surround a window of the given size, and update the window geometry.
+    ///
+    /// This does nothing without a frame.
+    pub fn resize(&mut self, window: &Window, width: NonZeroU32, height: NonZeroU32) {
+        let frame = match self.frame.as_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        self.size = Some((width, height));
+        frame.resize(width, height);
+        let (x, y) = frame.location();
+        let (width, height) = frame.add_borders(width.get(), height.get());
+        window.xdg_surface().set_window_geometry(x, y, width as i32, height as i32);
+        self.draw();
+    }
+
+    /// Draw the frame, if it needs to be redrawn.
+    pub fn draw(&mut self) {
+        if let Some(frame) = self.frame.as_mut() {
+            if frame.is_dirty() && !frame.is_hidden() {
+                frame.draw();
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ManagedDecorations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManagedDecorations")
+            .field("managed", &self.factory.is_some())
+            .field("has_frame", &self.frame.is_some())
+            .field("title", &self.title)
+            .field("scale_factor", &self.scale_factor)
+            .field("hovered", &self.hovered)
+            .field("cursor", &self.cursor)
+            .field("surfaces", &self.surfaces)
+            .field("size", &self.size)
+            .finish()
+    }
+}
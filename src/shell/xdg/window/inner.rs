@@ -24,12 +24,15 @@ use crate::{
 };
 
 use super::{
-    DecorationMode, Window, WindowConfigure, WindowData, WindowHandler, WindowManagerCapabilities,
-    WindowState,
+    decorations::ManagedDecorations, DecorationMode, Window, WindowConfigure, WindowData,
+    WindowDecorations, WindowHandler, WindowManagerCapabilities, WindowState,
 };
 
 impl Drop for WindowInner {
     fn drop(&mut self) {
+        // Destroy the frame subsurfaces while the parent surface is still alive.
+        self.frame.get_mut().unwrap().frame = None;
+
         // XDG decoration says we must destroy the decoration object before the toplevel
         if let Some(toplevel_decoration) = self.toplevel_decoration.as_ref() {
             toplevel_decoration.destroy();
@@ -48,6 +51,8 @@ pub struct WindowInner {
     pub xdg_toplevel: xdg_toplevel::XdgToplevel,
     pub toplevel_decoration: Option<zxdg_toplevel_decoration_v1::ZxdgToplevelDecorationV1>,
     pub pending_configure: Mutex<WindowConfigure>,
+    pub decorations: WindowDecorations,
+    pub frame: Mutex<ManagedDecorations>,
 }
 
 impl ProvidesBoundGlobal<zxdg_decoration_manager_v1::ZxdgDecorationManagerV1, 1> for XdgShell {
@@ -76,7 +81,9 @@ where
                     // Acknowledge the configure per protocol requirements.
                     xdg_surface.ack_configure(serial);
 
-                    let configure = { window.0.pending_configure.lock().unwrap().clone() };
+                    let mut configure = { window.0.pending_configure.lock().unwrap().clone() };
+                    window.0.frame.lock().unwrap().configure(&window, &mut configure);
+
                     WindowHandler::configure(data, conn, qh, &window, configure, serial);
                 }
 
@@ -3,24 +3,37 @@
 use std::{
     num::NonZeroU32,
     sync::{Arc, Weak},
+    time::Duration,
 };
 
 use crate::reexports::client::{
-    protocol::{wl_output, wl_seat, wl_surface},
-    Connection, Proxy, QueueHandle,
+    protocol::{wl_output, wl_pointer, wl_seat, wl_subsurface, wl_surface},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use crate::reexports::csd_frame::{
+    FrameAction, FrameClick, ResizeEdge, WindowManagerCapabilities, WindowState,
 };
-use crate::reexports::csd_frame::{WindowManagerCapabilities, WindowState};
 use crate::reexports::protocols::{
     xdg::decoration::zv1::client::zxdg_toplevel_decoration_v1::{self, Mode},
     xdg::shell::client::{xdg_surface, xdg_toplevel},
 };
 
-use crate::shell::WaylandSurface;
+use crate::{
+    compositor::SurfaceData,
+    seat::pointer::{CursorIcon, PointerEvent, PointerEventKind, BTN_LEFT, BTN_RIGHT},
+    shell::WaylandSurface,
+    shm::Shm,
+    subcompositor::{SubcompositorState, SubsurfaceData},
+};
 
-use self::inner::WindowInner;
+use self::{decorations::ManagedFrame, inner::WindowInner};
 
-use super::XdgSurface;
+use super::{
+    fallback_frame::{DefaultFrameRenderer, FallbackFrame, FrameRenderer},
+    XdgSurface,
+};
 
+pub(super) mod decorations;
 pub(super) mod inner;
 
 /// Handler for toplevel operations on a [`Window`].
@@ -51,6 +64,23 @@ pub trait WindowHandler: Sized {
         configure: WindowConfigure,
         serial: u32,
     );
+
+    /// The pointer moved over the decorations managed by the window, which ask for the cursor to be
+    /// changed.
+    ///
+    /// This is only called from [`Window::handle_decorations_pointer_event`].
+    ///
+    /// The default implementation does nothing.
+    fn decorations_cursor(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        window: &Window,
+        pointer: &wl_pointer::WlPointer,
+        cursor: CursorIcon,
+    ) {
+        let _ = (conn, qh, window, pointer, cursor);
+    }
 }
 
 /// Decoration mode of a window.
@@ -219,7 +249,16 @@ impl Window {
     }
 
     pub fn set_title(&self, title: impl Into<String>) {
-        self.xdg_toplevel().set_title(title.into());
+        let title = title.into();
+        let mut decorations = self.0.frame.lock().unwrap();
+        if let Some(frame) = decorations.frame.as_mut() {
+            frame.set_title(title.clone());
+        }
+        decorations.title.clone_from(&title);
+        decorations.draw();
+        drop(decorations);
+
+        self.xdg_toplevel().set_title(title);
     }
 
     pub fn set_app_id(&self, app_id: impl Into<String>) {
@@ -293,6 +332,245 @@ impl Window {
         self.xdg_toplevel().set_max_size(max_size.0 as i32, max_size.1 as i32);
     }
 
+    // Managed decorations
+
+    /// Let the window manage its client side decorations using a [`FallbackFrame`].
+    ///
+    /// Whenever the negotiated decoration mode becomes [`DecorationMode::Client`], the frame is created,
+    /// and it is destroyed when the mode changes back to [`DecorationMode::Server`]. The frame is never
+    /// created if the window was created with [`WindowDecorations::None`].
+    ///
+    /// While the frame exists:
+    ///
+    /// - The size of [`WindowConfigure::new_size`] excludes the decorations, and the frame and the
+    ///   window geometry are resized to it before [`WindowHandler::configure`] is called. When the
+    ///   configure has no size, the window must pick one and pass it to [`Window::resize_decorations`],
+    ///   which should also be called whenever the window picks another size.
+    /// - Pointer events should be passed to [`Window::handle_decorations_pointer_event`] from
+    ///   [`PointerHandler::pointer_frame`].
+    /// - The frame is redrawn when needed. Since it is synchronized with the window surface when it is
+    ///   resized, the window surface must be committed after a configure as usual.
+    ///
+    /// The window geometry is left alone while there is no frame.
+    ///
+    /// This takes effect on the next configure.
+    ///
+    /// [`PointerHandler::pointer_frame`]: crate::seat::pointer::PointerHandler::pointer_frame
+    pub fn manage_decorations<State>(
+        &self,
+        shm: &Shm,
+        subcompositor: Arc<SubcompositorState>,
+        qh: &QueueHandle<State>,
+    ) where
+        State: Dispatch<wl_surface::WlSurface, SurfaceData<()>>
+            + Dispatch<wl_subsurface::WlSubsurface, SubsurfaceData>
+            + 'static,
+    {
+        self.manage_decorations_with_renderer(shm, subcompositor, qh, DefaultFrameRenderer::new())
+    }
+
+    /// Let the window manage its client side decorations using a [`FallbackFrame`] drawn by a custom
+    /// renderer.
+    ///
+    /// The renderer is cloned every time the frame is created. See [`Window::manage_decorations`].
+    pub fn manage_decorations_with_renderer<State, R>(
+        &self,
+        shm: &Shm,
+        subcompositor: Arc<SubcompositorState>,
+        qh: &QueueHandle<State>,
+        renderer: R,
+    ) where
+        State: Dispatch<wl_surface::WlSurface, SurfaceData<()>>
+            + Dispatch<wl_subsurface::WlSubsurface, SubsurfaceData>
+            + 'static,
+        R: FrameRenderer + Clone + Send + Sync + 'static,
+    {
+        let shm = Shm::from(shm.wl_shm().clone());
+        let qh = qh.clone();
+        let mut decorations = self.0.frame.lock().unwrap();
+        decorations.factory = Some(Box::new(move |parent| {
+            let frame = FallbackFrame::with_renderer(
+                parent,
+                &shm,
+                subcompositor.clone(),
+                qh.clone(),
+                renderer.clone(),
+            )?;
+
+            let surfaces = frame.surfaces().map(Proxy::id).collect();
+            Ok((Box::new(frame) as Box<dyn ManagedFrame>, surfaces))
+        }));
+    }
+
+    /// Whether the window currently has a managed client side decorations frame.
+    pub fn has_decorations(&self) -> bool {
+        self.0.frame.lock().unwrap().frame.is_some()
+    }
+
+    /// Resize the managed decorations to surround a window of the given size.
+    ///
+    /// The decorations are already resized to the configured size, so this is only needed when the
+    /// configure has no size, or when the window picks another size.
+    ///
+    /// This also updates the window geometry to include the decorations.
+    ///
+    /// This does nothing if the window has no managed decorations frame.
+    pub fn resize_decorations(&self, width: NonZeroU32, height: NonZeroU32) {
+        let mut decorations = self.0.frame.lock().unwrap();
+        if decorations.factory.is_some() {
+            decorations.resize(self, width, height);
+        }
+    }
+
+    /// Set the scale factor the managed decorations are drawn with.
+    pub fn set_decorations_scale_factor(&self, scale_factor: f64) {
+        let mut decorations = self.0.frame.lock().unwrap();
+        decorations.scale_factor = Some(scale_factor);
+        if let Some(frame) = decorations.frame.as_mut() {
+            frame.set_scaling_factor(scale_factor);
+        }
+        decorations.draw();
+    }
+
+    /// Process a pointer event for the managed decorations.
+    ///
+    /// This should be called from [`PointerHandler::pointer_frame`] for every event, and the events
+    /// for which it returns `true` should not be processed further.
+    ///
+    /// Events over the decorations move, resize, minimize or maximize the window. Pressing the close
+    /// button calls [`WindowHandler::request_close`], and the cursor to show over the decorations is
+    /// passed to [`WindowHandler::decorations_cursor`].
+    ///
+    /// Returns `false` for events over other surfaces, including the window itself.
+    ///
+    /// [`PointerHandler::pointer_frame`]: crate::seat::pointer::PointerHandler::pointer_frame
+    pub fn handle_decorations_pointer_event<D>(
+        &self,
+        data: &mut D,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+        pointer: &wl_pointer::WlPointer,
+        seat: &wl_seat::WlSeat,
+        event: &PointerEvent,
+    ) -> bool
+    where
+        D: WindowHandler,
+    {
+        let surface_id = event.surface.id();
+        let mut guard = self.0.frame.lock().unwrap();
+        let decorations = &mut *guard;
+        if !decorations.surfaces.contains(&surface_id) {
+            return false;
+        }
+
+        let frame = match decorations.frame.as_mut() {
+            Some(frame) => frame,
+            None => return false,
+        };
+
+        let (x, y) = event.position;
+        let mut action = None;
+        let mut cursor = None;
+        match event.kind {
+            PointerEventKind::Enter { .. } | PointerEventKind::Motion { .. } => {
+                let time = match event.kind {
+                    PointerEventKind::Motion { time } => Duration::from_millis(time as u64),
+                    _ => Duration::ZERO,
+                };
+
+                let entered = decorations.hovered.as_ref() != Some(&surface_id);
+                let new_cursor = frame.click_point_moved(time, &surface_id, x, y);
+                decorations.hovered = Some(surface_id);
+                match new_cursor {
+                    Some(new_cursor) if entered || decorations.cursor != Some(new_cursor) => {
+                        decorations.cursor = Some(new_cursor);
+                        cursor = Some(new_cursor);
+                    }
+                    _ => (),
+                }
+            }
+
+            _ if decorations.hovered.as_ref() != Some(&surface_id) => (),
+
+            PointerEventKind::Leave { .. } => {
+                frame.click_point_left();
+                decorations.hovered = None;
+                decorations.cursor = None;
+            }
+
+            PointerEventKind::Press { button, serial, time }
+            | PointerEventKind::Release { button, serial, time } => {
+                let pressed = matches!(event.kind, PointerEventKind::Press { .. });
+                let click = match button {
+                    BTN_LEFT => Some(FrameClick::Normal),
+                    BTN_RIGHT => Some(FrameClick::Alternate),
+                    _ => None,
+                };
+
+                if let Some(click) = click {
+                    action = frame
+                        .on_click(Duration::from_millis(time as u64), click, pressed)
+                        .map(|action| (action, serial));
+                }
+            }
+
+            PointerEventKind::Axis { .. } => (),
+        }
+
+        // Hovering and pressing the buttons changes how they are drawn.
+        decorations.draw();
+
+        // The handler may use the window, so the frame must not be borrowed anymore.
+        drop(guard);
+
+        if let Some(cursor) = cursor {
+            data.decorations_cursor(conn, qh, self, pointer, cursor);
+        }
+
+        if let Some((action, serial)) = action {
+            self.frame_action(data, conn, qh, seat, serial, action);
+        }
+
+        true
+    }
+
+    fn frame_action<D>(
+        &self,
+        data: &mut D,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+        seat: &wl_seat::WlSeat,
+        serial: u32,
+        action: FrameAction,
+    ) where
+        D: WindowHandler,
+    {
+        match action {
+            FrameAction::Close => data.request_close(conn, qh, self),
+            FrameAction::Minimize => self.set_minimized(),
+            FrameAction::Maximize => self.set_maximized(),
+            FrameAction::UnMaximize => self.unset_maximized(),
+            FrameAction::ShowMenu(x, y) => self.show_window_menu(seat, serial, (x, y)),
+            FrameAction::Move => self.move_(seat, serial),
+            FrameAction::Resize(edge) => {
+                let edge = match edge {
+                    ResizeEdge::None => xdg_toplevel::ResizeEdge::None,
+                    ResizeEdge::Top => xdg_toplevel::ResizeEdge::Top,
+                    ResizeEdge::Bottom => xdg_toplevel::ResizeEdge::Bottom,
+                    ResizeEdge::Left => xdg_toplevel::ResizeEdge::Left,
+                    ResizeEdge::TopLeft => xdg_toplevel::ResizeEdge::TopLeft,
+                    ResizeEdge::BottomLeft => xdg_toplevel::ResizeEdge::BottomLeft,
+                    ResizeEdge::Right => xdg_toplevel::ResizeEdge::Right,
+                    ResizeEdge::TopRight => xdg_toplevel::ResizeEdge::TopRight,
+                    ResizeEdge::BottomRight => xdg_toplevel::ResizeEdge::BottomRight,
+                    _ => return,
+                };
+                self.resize(seat, serial, edge);
+            }
+            _ => (),
+        }
+    }
+
     // Other
 
     /// Returns the underlying xdg toplevel wrapped by this window.
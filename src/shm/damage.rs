@@ -0,0 +1,195 @@
+//! Damage tracking across reused buffers.
+//!
+//! When a buffer from a [`SlotPool`](super::slot::SlotPool) or a
+//! [`MultiPool`](super::multi::MultiPool) is drawn again, it still contains the frame it was last
+//! drawn with, which may be several frames old with double or triple buffering. The age of a
+//! buffer is the number of frames since then, and the regions to repaint are the damage of the
+//! frame being drawn and of the frames the buffer missed.
+//!
+//! ```no_run
+//! # use smithay_client_toolkit::compositor::Rect;
+//! # use smithay_client_toolkit::shell::WaylandSurface;
+//! # use smithay_client_toolkit::shm::damage::DamageTracker;
+//! # fn draw(_: &[Rect<i32>]) {}
+//! # fn example(
+//! #     window: &impl WaylandSurface,
+//! #     buffer: &smithay_client_toolkit::reexports::client::protocol::wl_buffer::WlBuffer,
+//! #     damage: &mut DamageTracker,
+//! # ) {
+//! damage.add(Rect::new(0, 0, 64, 16));
+//!
+//! // `None` means the buffer contents are unknown, and everything should be repainted.
+//! if let Some(regions) = damage.repaint_regions(buffer) {
+//!     draw(&regions);
+//! }
+//!
+//! window.attach(Some(buffer), 0, 0);
+//! for rect in damage.submit(buffer) {
+//!     window.damage_buffer(rect, 1);
+//! }
+//! window.commit();
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+
+use wayland_client::{backend::ObjectId, protocol::wl_buffer, Proxy};
+
+use crate::compositor::Rect;
+
+/// Damage accumulated over the last frames, to repaint reused buffers partially.
+///
+/// Damage is in buffer coordinates.
+#[derive(Debug)]
+pub struct DamageTracker {
+    /// The damage of the frame being drawn.
+    pending: Vec<Rect<i32>>,
+
+    /// The damage of the last submitted frames, the most recent first.
+    history: VecDeque<Vec<Rect<i32>>>,
+
+    /// The maximum buffer age to track.
+    max_age: usize,
+
+    /// The number of submitted frames.
+    frame: u64,
+
+    /// The frame each buffer was last submitted with.
+    buffers: Vec<(ObjectId, u64)>,
+}
+
+impl DamageTracker {
+    /// Create a tracker for buffers up to `max_age` frames old, such as `3` for triple buffering.
+    ///
+    /// Older buffers are repainted entirely.
+    pub fn new(max_age: usize) -> Self {
+        Self {
+            pending: Vec::new(),
+            history: VecDeque::with_capacity(max_age),
+            max_age,
+            frame: 0,
+            buffers: Vec::new(),
+        }
+    }
+
+    /// Add damage to the frame being drawn.
+    pub fn add(&mut self, rect: Rect<i32>) {
+        if rect.width > 0 && rect.height > 0 && !self.pending.contains(&rect) {
+            self.pending.push(rect);
+        }
+    }
+
+    /// The damage of the frame being drawn.
+    pub fn pending(&self) -> &[Rect<i32>] {
+        &self.pending
+    }
+
+    /// The number of frames since `buffer` was submitted, or `0` if it is unknown or too old.
+    ///
+    /// A buffer submitted with the previous frame has an age of `1`.
+    pub fn buffer_age(&self, buffer: &wl_buffer::WlBuffer) -> usize {
+        let id = buffer.id();
+        self.buffers
+            .iter()
+            .find(|(buffer, _)| *buffer == id)
+            .map(|&(_, frame)| (self.frame - frame) as usize)
+            .filter(|&age| age <= self.max_age)
+            .unwrap_or(0)
+    }
+
+    /// The regions to repaint in a buffer of `age` frames, or `None` to repaint everything.
+    pub fn regions_for_age(&self, age: usize) -> Option<Vec<Rect<i32>>> {
+        if age == 0 || age > self.max_age || age > self.history.len() {
+            return None;
+        }
+
+        let mut regions = self.pending.clone();
+        for rect in self.history.iter().take(age - 1).flatten() {
+            if !regions.contains(rect) {
+                regions.push(*rect);
+            }
+        }
+        Some(regions)
+    }
+
+    /// The regions to repaint in `buffer`, or `None` to repaint everything.
+    pub fn repaint_regions(&self, buffer: &wl_buffer::WlBuffer) -> Option<Vec<Rect<i32>>> {
+        self.regions_for_age(self.buffer_age(buffer))
+    }
+
+    /// Record that the frame being drawn is submitted with `buffer`, and return its damage.
+    ///
+    /// The returned damage should be sent to the compositor, for example with
+    /// [`WaylandSurface::damage_buffer`](crate::shell::WaylandSurface::damage_buffer).
+    pub fn submit(&mut self, buffer: &wl_buffer::WlBuffer) -> Vec<Rect<i32>> {
+        let id = buffer.id();
+        let frame = self.frame;
+        match self.buffers.iter_mut().find(|(buffer, _)| *buffer == id) {
+            Some((_, buffer_frame)) => *buffer_frame = frame,
+            None => self.buffers.push((id, frame)),
+        }
+
+        self.submit_frame()
+    }
+
+    /// Forget the damage history, after the buffers were resized or destroyed.
+    ///
+    /// All buffers are repainted entirely the next time they are used.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.history.clear();
+        self.buffers.clear();
+    }
+
+    fn submit_frame(&mut self) -> Vec<Rect<i32>> {
+        let damage = std::mem::take(&mut self.pending);
+        self.frame += 1;
+
+        self.history.push_front(damage.clone());
+        self.history.truncate(self.max_age);
+
+        // Buffers which are too old are repainted entirely anyway.
+        let (frame, max_age) = (self.frame, self.max_age as u64);
+        self.buffers.retain(|&(_, buffer_frame)| frame - buffer_frame <= max_age);
+
+        damage
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn regions_for_age() {
+        let mut damage = DamageTracker::new(3);
+        assert_eq!(damage.regions_for_age(1), None);
+
+        let a = Rect::new(0, 0, 10, 10);
+        let b = Rect::new(10, 0, 10, 10);
+        let c = Rect::new(20, 0, 10, 10);
+        let d = Rect::new(30, 0, 10, 10);
+
+        damage.add(a);
+        damage.add(a);
+        damage.add(Rect::new(5, 5, 0, 10));
+        assert_eq!(damage.submit_frame(), vec![a]);
+
+        damage.add(b);
+        assert_eq!(damage.submit_frame(), vec![b]);
+        assert_eq!(damage.regions_for_age(3), None);
+
+        damage.add(c);
+        assert_eq!(damage.submit_frame(), vec![c]);
+
+        damage.add(d);
+        assert_eq!(damage.regions_for_age(0), None);
+        assert_eq!(damage.regions_for_age(1), Some(vec![d]));
+        assert_eq!(damage.regions_for_age(2), Some(vec![d, c]));
+        assert_eq!(damage.regions_for_age(3), Some(vec![d, c, b]));
+        assert_eq!(damage.regions_for_age(4), None);
+
+        damage.reset();
+        assert_eq!(damage.regions_for_age(1), None);
+    }
+}
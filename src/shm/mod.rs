@@ -1,3 +1,4 @@
+pub mod damage;
 pub mod multi;
 pub mod raw;
 pub mod slot;
@@ -5,9 +5,10 @@ use crate::reexports::client::protocol::wl_subsurface::WlSubsurface;
 use crate::reexports::client::protocol::wl_surface::WlSurface;
 use crate::reexports::client::{Connection, Dispatch, Proxy, QueueHandle};
 
-use crate::compositor::SurfaceData;
+use crate::compositor::{CompositorState, SurfaceData};
 use crate::dispatch2::Dispatch2;
-use crate::globals::GlobalData;
+use crate::error::GlobalError;
+use crate::globals::{GlobalData, ProvidesBoundGlobal};
 
 #[derive(Debug)]
 pub struct SubcompositorState {
@@ -62,6 +63,14 @@ impl SubcompositorState {
     }
 }
 
+impl ProvidesBoundGlobal<WlCompositor, { CompositorState::API_VERSION_MAX }>
+    for SubcompositorState
+{
+    fn bound_global(&self) -> Result<WlCompositor, GlobalError> {
+        Ok(self.compositor.clone())
+    }
+}
+
 impl<D> Dispatch2<WlSubsurface, D> for SubsurfaceData {
     fn event(
         &self,
@@ -0,0 +1,266 @@
+//! Cropping and scaling of surfaces.
+//!
+//! The `wp_viewporter` protocol decouples the size of a surface from the size of the buffer
+//! attached to it. A [`Viewport`] may crop the buffer to a source rectangle and scale the result
+//! to a destination size in surface-local coordinates.
+
+use std::num::NonZeroU32;
+
+use wayland_client::{
+    globals::GlobalList, protocol::wl_surface, Connection, Dispatch, Proxy, QueueHandle, Weak,
+};
+use wayland_protocols::wp::viewporter::client::{wp_viewport, wp_viewporter};
+
+use crate::{
+    compositor::{Rect, Surface},
+    dispatch2::Dispatch2,
+    error::GlobalError,
+    globals::{GlobalData, ProvidesBoundGlobal},
+    registry::GlobalProxy,
+};
+
+/// The largest value of a `wl_fixed`, used for the source rectangle.
+const FIXED_MAX: f64 = i32::MAX as f64 / 256.;
+
+/// A viewport parameter which the compositor would reject with a `bad_value` protocol error.
+#[derive(Debug, thiserror::Error)]
+pub enum ViewportError {
+    /// The source rectangle has a negative origin, a size which is not positive, or a value which
+    /// is not a finite fixed point number.
+    #[error("invalid viewport source {0:?}")]
+    InvalidSource(Rect<f64>),
+
+    /// The destination size does not fit in an `i32`.
+    #[error("invalid viewport destination {0}x{1}")]
+    InvalidDestination(NonZeroU32, NonZeroU32),
+}
+
+#[derive(Debug, Clone)]
+pub struct ViewporterState {
+    viewporter: GlobalProxy<wp_viewporter::WpViewporter>,
+}
+
+impl ViewporterState {
+    /// Bind `wp_viewporter` global, if it exists
+    pub fn bind<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<wp_viewporter::WpViewporter, GlobalData> + 'static,
+    {
+        let viewporter = GlobalProxy::from(globals.bind(qh, 1..=1, GlobalData));
+        Self { viewporter }
+    }
+
+    /// Create a [`Viewport`] for a given `wl_surface`.
+    ///
+    /// Returns error if `wp_viewporter` global is not present.
+    pub fn get_viewport<D>(
+        &self,
+        surface: &wl_surface::WlSurface,
+        qh: &QueueHandle<D>,
+    ) -> Result<Viewport, GlobalError>
+    where
+        D: Dispatch<wp_viewport::WpViewport, GlobalData> + 'static,
+    {
+        Viewport::new(self, surface, qh)
+    }
+
+    /// Create a [`Viewport`] for a given [`Surface`].
+    ///
+    /// Returns error if `wp_viewporter` global is not present.
+    pub fn get_surface_viewport<D>(
+        &self,
+        surface: &Surface,
+        qh: &QueueHandle<D>,
+    ) -> Result<Viewport, GlobalError>
+    where
+        D: Dispatch<wp_viewport::WpViewport, GlobalData> + 'static,
+    {
+        Viewport::for_surface(self, surface, qh)
+    }
+
+    /// The `wp_viewporter` global, if any.
+    pub fn wp_viewporter(&self) -> Result<&wp_viewporter::WpViewporter, GlobalError> {
+        self.viewporter.get()
+    }
+}
+
+impl ProvidesBoundGlobal<wp_viewporter::WpViewporter, 1> for ViewporterState {
+    fn bound_global(&self) -> Result<wp_viewporter::WpViewporter, GlobalError> {
+        self.viewporter.get().cloned()
+    }
+}
+
+/// An owned [`WpViewport`](wp_viewport::WpViewport) for a surface.
+///
+/// This destroys the viewport on drop, which resets the source rectangle and destination size of
+/// the surface on the next commit.
+///
+/// A surface may only have a single viewport at a time; creating a second one is a protocol
+/// error. The viewport may be dropped before or after its surface. Once the surface has been
+/// destroyed, such as by dropping its [`Surface`], the setters do nothing.
+#[derive(Debug)]
+pub struct Viewport {
+    viewport: wp_viewport::WpViewport,
+    surface: Weak<wl_surface::WlSurface>,
+}
+
+impl Viewport {
+    pub fn new<D>(
+        viewporter: &impl ProvidesBoundGlobal<wp_viewporter::WpViewporter, 1>,
+        surface: &wl_surface::WlSurface,
+        qh: &QueueHandle<D>,
+    ) -> Result<Self, GlobalError>
+    where
+        D: Dispatch<wp_viewport::WpViewport, GlobalData> + 'static,
+    {
+        let viewport = viewporter.bound_global()?.get_viewport(surface, qh, GlobalData);
+        Ok(Viewport { viewport, surface: surface.downgrade() })
+    }
+
+    pub fn for_surface<D>(
+        viewporter: &impl ProvidesBoundGlobal<wp_viewporter::WpViewporter, 1>,
+        surface: &Surface,
+        qh: &QueueHandle<D>,
+    ) -> Result<Self, GlobalError>
+    where
+        D: Dispatch<wp_viewport::WpViewport, GlobalData> + 'static,
+    {
+        Self::new(viewporter, surface.wl_surface(), qh)
+    }
+
+    /// Whether the surface of the viewport still exists.
+    ///
+    /// Using the viewport of a destroyed surface is a protocol error, so the setters do nothing
+    /// when this is `false`.
+    pub fn is_alive(&self) -> bool {
+        self.surface.upgrade().is_ok()
+    }
+
+    /// Set the area of the buffer, in buffer coordinates after the buffer transform and scale have
+    /// been applied, that is shown on the surface.
+    ///
+    /// A source of [`None`] uses the whole buffer.
+    ///
+    /// Returns [`ViewportError::InvalidSource`] without sending anything if the rectangle has a
+    /// negative origin or a size which is not positive.
+    ///
+    /// # Protocol errors
+    ///
+    /// The rectangle must lie within the buffer when the surface is committed.
+    pub fn set_source(&self, source: Option<Rect<f64>>) -> Result<(), ViewportError> {
+        if let Some(rect) = source.filter(|rect| !is_valid_source(rect)) {
+            return Err(ViewportError::InvalidSource(rect));
+        }
+
+        if !self.is_alive() {
+            return Ok(());
+        }
+
+        match source {
+            Some(rect) => self.viewport.set_source(rect.x, rect.y, rect.width, rect.height),
+            None => self.viewport.set_source(-1., -1., -1., -1.),
+        }
+        Ok(())
+    }
+
+    /// Set the size of the surface in surface-local coordinates.
+    ///
+    /// The content selected by [`set_source`](Self::set_source) is scaled to this size.
+    ///
+    /// Returns [`ViewportError::InvalidDestination`] without sending anything if the size is
+    /// larger than `i32::MAX`.
+    pub fn set_destination(
+        &self,
+        width: NonZeroU32,
+        height: NonZeroU32,
+    ) -> Result<(), ViewportError> {
+        let (Ok(w), Ok(h)) = (i32::try_from(width.get()), i32::try_from(height.get())) else {
+            return Err(ViewportError::InvalidDestination(width, height));
+        };
+
+        if self.is_alive() {
+            self.viewport.set_destination(w, h);
+        }
+        Ok(())
+    }
+
+    /// Unset the destination size, so the surface size is derived from the source rectangle or
+    /// the buffer.
+    pub fn unset_destination(&self) {
+        if self.is_alive() {
+            self.viewport.set_destination(-1, -1);
+        }
+    }
+
+    pub fn wp_viewport(&self) -> &wp_viewport::WpViewport {
+        &self.viewport
+    }
+}
+
+/// Whether `rect` can be sent as a source rectangle.
+fn is_valid_source(rect: &Rect<f64>) -> bool {
+    let in_range = |value: f64| (0. ..=FIXED_MAX).contains(&value);
+    in_range(rect.x)
+        && in_range(rect.y)
+        && in_range(rect.width)
+        && in_range(rect.height)
+        && rect.width > 0.
+        && rect.height > 0.
+}
+
+impl Drop for Viewport {
+    fn drop(&mut self) {
+        // Destroying the viewport is allowed after the surface was destroyed.
+        self.viewport.destroy();
+    }
+}
+
+impl<D> Dispatch2<wp_viewporter::WpViewporter, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &wp_viewporter::WpViewporter,
+        _: wp_viewporter::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("wp_viewporter has no events")
+    }
+}
+
+impl<D> Dispatch2<wp_viewport::WpViewport, D> for GlobalData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &wp_viewport::WpViewport,
+        _: wp_viewport::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        unreachable!("wp_viewport has no events")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn source_validation() {
+        assert!(is_valid_source(&Rect::new(0., 0., 1., 1.)));
+        assert!(is_valid_source(&Rect::new(0.5, 2., 0.25, FIXED_MAX)));
+
+        // The size must be positive.
+        assert!(!is_valid_source(&Rect::new(0., 0., 0., 1.)));
+        assert!(!is_valid_source(&Rect::new(0., 0., 1., -1.)));
+
+        // The origin must not be negative.
+        assert!(!is_valid_source(&Rect::new(-1., 0., 1., 1.)));
+        assert!(!is_valid_source(&Rect::new(0., -0.5, 1., 1.)));
+
+        // Values must fit in a `wl_fixed`.
+        assert!(!is_valid_source(&Rect::new(0., 0., FIXED_MAX + 1., 1.)));
+        assert!(!is_valid_source(&Rect::new(f64::NAN, 0., 1., 1.)));
+        assert!(!is_valid_source(&Rect::new(0., 0., f64::INFINITY, 1.)));
+    }
+}
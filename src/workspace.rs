@@ -0,0 +1,510 @@
+//! Listing and switching workspaces, for panels and workspace switchers.
+//!
+//! This uses `ext_workspace_manager_v1`. The compositor sends the changes of workspace groups and
+//! workspaces in batches, and [`WorkspaceHandler`] is only notified once a batch is complete.
+//!
+//! Requests such as [`WorkspaceState::activate`] are only applied by the compositor after
+//! [`WorkspaceState::commit`] is called, which allows to apply several changes at once.
+
+use crate::{dispatch2::Dispatch2, error::GlobalError, globals::GlobalData, registry::GlobalProxy};
+use std::sync::Mutex;
+use wayland_client::{
+    globals::GlobalList, protocol::wl_output, Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols::ext::workspace::v1::client::{
+    ext_workspace_group_handle_v1::{self, ExtWorkspaceGroupHandleV1, GroupCapabilities},
+    ext_workspace_handle_v1::{self, ExtWorkspaceHandleV1, WorkspaceCapabilities},
+    ext_workspace_manager_v1::{self, ExtWorkspaceManagerV1},
+};
+
+pub use ext_workspace_handle_v1::State as WorkspaceStates;
+
+/// Information about a workspace group.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct WorkspaceGroupInfo {
+    /// Requests the group supports
+    pub capabilities: GroupCapabilities,
+    /// Outputs the workspaces of the group are shown on
+    pub outputs: Vec<wl_output::WlOutput>,
+    /// Workspaces assigned to the group
+    pub workspaces: Vec<ExtWorkspaceHandleV1>,
+}
+
+impl Default for WorkspaceGroupInfo {
+    fn default() -> Self {
+        Self {
+            capabilities: GroupCapabilities::empty(),
+            outputs: Vec::new(),
+            workspaces: Vec::new(),
+        }
+    }
+}
+
+/// Information about a workspace.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct WorkspaceInfo {
+    /// Identifier stable across sessions, if the compositor provides one.
+    ///
+    /// This is not meant to be shown to the user, use the name instead.
+    pub id: Option<String>,
+    /// Human readable name
+    pub name: String,
+    /// Position of the workspace in the grid of its group, if the compositor arranges workspaces
+    /// in a grid.
+    ///
+    /// By convention, the first coordinate is X and the second is Y.
+    pub coordinates: Vec<u32>,
+    /// Active, urgent and hidden states
+    pub state: WorkspaceStates,
+    /// Requests the workspace supports
+    pub capabilities: WorkspaceCapabilities,
+    /// Group the workspace is assigned to
+    pub group: Option<ExtWorkspaceGroupHandleV1>,
+}
+
+impl Default for WorkspaceInfo {
+    fn default() -> Self {
+        Self {
+            id: None,
+            name: String::new(),
+            coordinates: Vec::new(),
+            state: WorkspaceStates::empty(),
+            capabilities: WorkspaceCapabilities::empty(),
+            group: None,
+        }
+    }
+}
+
+/// How a workspace group or workspace changed in a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    New,
+    Updated,
+    Removed,
+    /// Removed before it was ever announced.
+    Discarded,
+}
+
+#[derive(Debug, Default)]
+struct Pending<T> {
+    current_info: Option<T>,
+    pending_info: T,
+    changed: bool,
+    removed: bool,
+}
+
+impl<T: Clone> Pending<T> {
+    fn pending(&mut self) -> &mut T {
+        self.changed = true;
+        &mut self.pending_info
+    }
+
+    fn apply(&mut self) -> Option<Change> {
+        let change = if self.removed {
+            Some(if self.current_info.is_some() { Change::Removed } else { Change::Discarded })
+        } else if self.current_info.is_none() {
+            Some(Change::New)
+        } else if self.changed {
+            Some(Change::Updated)
+        } else {
+            None
+        };
+
+        if matches!(change, Some(Change::New | Change::Updated)) {
+            self.current_info = Some(self.pending_info.clone());
+        }
+        self.changed = false;
+        change
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug, Default)]
+pub struct WorkspaceGroupData(Mutex<Pending<WorkspaceGroupInfo>>);
+
+#[doc(hidden)]
+#[derive(Debug, Default)]
+pub struct WorkspaceData(Mutex<Pending<WorkspaceInfo>>);
+
+#[derive(Debug)]
+pub struct WorkspaceState {
+    manager: GlobalProxy<ExtWorkspaceManagerV1>,
+    groups: Vec<ExtWorkspaceGroupHandleV1>,
+    workspaces: Vec<ExtWorkspaceHandleV1>,
+    /// Objects created since the last `done` event.
+    new_groups: Vec<ExtWorkspaceGroupHandleV1>,
+    new_workspaces: Vec<ExtWorkspaceHandleV1>,
+}
+
+impl WorkspaceState {
+    pub fn new<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<ExtWorkspaceManagerV1, GlobalData> + 'static,
+    {
+        let manager = GlobalProxy::from(globals.bind(qh, 1..=1, GlobalData));
+        Self {
+            manager,
+            groups: Vec::new(),
+            workspaces: Vec::new(),
+            new_groups: Vec::new(),
+            new_workspaces: Vec::new(),
+        }
+    }
+
+    /// Returns list of workspace groups.
+    pub fn workspace_groups(&self) -> &[ExtWorkspaceGroupHandleV1] {
+        &self.groups
+    }
+
+    /// Returns list of workspaces.
+    pub fn workspaces(&self) -> &[ExtWorkspaceHandleV1] {
+        &self.workspaces
+    }
+
+    /// Returns information about a workspace group.
+    ///
+    /// This may be none if the group has been removed or the compositor has not sent
+    /// information about the group yet.
+    pub fn group_info(&self, group: &ExtWorkspaceGroupHandleV1) -> Option<WorkspaceGroupInfo> {
+        group.data::<WorkspaceGroupData>()?.0.lock().unwrap().current_info.clone()
+    }
+
+    /// Returns information about a workspace.
+    ///
+    /// This may be none if the workspace has been removed or the compositor has not sent
+    /// information about the workspace yet.
+    pub fn workspace_info(&self, workspace: &ExtWorkspaceHandleV1) -> Option<WorkspaceInfo> {
+        workspace.data::<WorkspaceData>()?.0.lock().unwrap().current_info.clone()
+    }
+
+    /// Request to activate a workspace.
+    ///
+    /// Ignored unless the workspace has the [`WorkspaceCapabilities::Activate`] capability.
+    pub fn activate(&self, workspace: &ExtWorkspaceHandleV1) {
+        workspace.activate();
+    }
+
+    /// Request to deactivate a workspace.
+    ///
+    /// Ignored unless the workspace has the [`WorkspaceCapabilities::Deactivate`] capability.
+    pub fn deactivate(&self, workspace: &ExtWorkspaceHandleV1) {
+        workspace.deactivate();
+    }
+
+    /// Request to move a workspace to another group.
+    ///
+    /// Ignored unless the workspace has the [`WorkspaceCapabilities::Assign`] capability.
+    pub fn assign(&self, workspace: &ExtWorkspaceHandleV1, group: &ExtWorkspaceGroupHandleV1) {
+        workspace.assign(group);
+    }
+
+    /// Request to remove a workspace.
+    ///
+    /// Ignored unless the workspace has the [`WorkspaceCapabilities::Remove`] capability.
+    pub fn remove(&self, workspace: &ExtWorkspaceHandleV1) {
+        workspace.remove();
+    }
+
+    /// Request to create a workspace named `name` in a group.
+    ///
+    /// Ignored unless the group has the [`GroupCapabilities::CreateWorkspace`] capability. The
+    /// compositor may use another name.
+    pub fn create_workspace(&self, group: &ExtWorkspaceGroupHandleV1, name: String) {
+        group.create_workspace(name);
+    }
+
+    /// Apply the requests made since the last commit.
+    pub fn commit(&self) -> Result<(), GlobalError> {
+        self.manager.get()?.commit();
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Ok(manager) = self.manager.get() {
+            manager.stop();
+        }
+    }
+}
+
+/// Handler trait for the workspace protocol.
+///
+/// The functions are called once the compositor sent all changes of a batch.
+pub trait WorkspaceHandler: Sized {
+    fn workspace_state(&mut self) -> &mut WorkspaceState;
+
+    /// A new workspace group has been created.
+    fn new_workspace_group(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        group: ExtWorkspaceGroupHandleV1,
+    );
+
+    /// An existing workspace group has changed.
+    fn update_workspace_group(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        group: ExtWorkspaceGroupHandleV1,
+    );
+
+    /// A workspace group has been removed.
+    ///
+    /// The info of the group is the one before it was removed.
+    fn workspace_group_removed(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        group: ExtWorkspaceGroupHandleV1,
+    );
+
+    /// A new workspace has been created.
+    fn new_workspace(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        workspace: ExtWorkspaceHandleV1,
+    );
+
+    /// An existing workspace has changed.
+    fn update_workspace(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        workspace: ExtWorkspaceHandleV1,
+    );
+
+    /// A workspace has been removed.
+    ///
+    /// The info of the workspace is the one before it was removed.
+    fn workspace_removed(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        workspace: ExtWorkspaceHandleV1,
+    );
+
+    fn finished(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>) {}
+}
+
+/// Apply the pending changes of a list of handles, removing the removed ones from the list.
+fn apply_changes<P, T>(
+    list: &mut Vec<P>,
+    new: Vec<P>,
+    data: impl Fn(&P) -> &Mutex<Pending<T>>,
+) -> Vec<(Change, P)>
+where
+    P: Proxy,
+    T: Clone,
+{
+    list.extend(new);
+
+    let mut changes = Vec::new();
+    list.retain(|handle| {
+        let change = data(handle).lock().unwrap().apply();
+        if let Some(change) = change {
+            changes.push((change, handle.clone()));
+        }
+        !matches!(change, Some(Change::Removed | Change::Discarded))
+    });
+    changes
+}
+
+impl<D> Dispatch2<ExtWorkspaceManagerV1, D> for GlobalData
+where
+    D: Dispatch<ExtWorkspaceGroupHandleV1, WorkspaceGroupData>
+        + Dispatch<ExtWorkspaceHandleV1, WorkspaceData>
+        + WorkspaceHandler
+        + 'static,
+{
+    fn event(
+        &self,
+        state: &mut D,
+        _: &ExtWorkspaceManagerV1,
+        event: ext_workspace_manager_v1::Event,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        match event {
+            ext_workspace_manager_v1::Event::WorkspaceGroup { workspace_group } => {
+                state.workspace_state().new_groups.push(workspace_group);
+            }
+            ext_workspace_manager_v1::Event::Workspace { workspace } => {
+                state.workspace_state().new_workspaces.push(workspace);
+            }
+            ext_workspace_manager_v1::Event::Done => {
+                let workspace_state = state.workspace_state();
+                let new_groups = std::mem::take(&mut workspace_state.new_groups);
+                let group_changes =
+                    apply_changes(&mut workspace_state.groups, new_groups, |group| {
+                        &group.data::<WorkspaceGroupData>().unwrap().0
+                    });
+                let new_workspaces = std::mem::take(&mut workspace_state.new_workspaces);
+                let workspace_changes =
+                    apply_changes(&mut workspace_state.workspaces, new_workspaces, |workspace| {
+                        &workspace.data::<WorkspaceData>().unwrap().0
+                    });
+
+                // Groups are announced before their workspaces, and removed after them.
+                for (change, group) in &group_changes {
+                    match change {
+                        Change::New => state.new_workspace_group(conn, qh, group.clone()),
+                        Change::Updated => state.update_workspace_group(conn, qh, group.clone()),
+                        _ => (),
+                    }
+                }
+                for (change, workspace) in workspace_changes {
+                    match change {
+                        Change::New => state.new_workspace(conn, qh, workspace),
+                        Change::Updated => state.update_workspace(conn, qh, workspace),
+                        Change::Removed => {
+                            state.workspace_removed(conn, qh, workspace.clone());
+                            workspace.destroy();
+                        }
+                        Change::Discarded => workspace.destroy(),
+                    }
+                }
+                for (change, group) in group_changes {
+                    match change {
+                        Change::Removed => {
+                            state.workspace_group_removed(conn, qh, group.clone());
+                            group.destroy();
+                        }
+                        Change::Discarded => group.destroy(),
+                        _ => (),
+                    }
+                }
+            }
+            ext_workspace_manager_v1::Event::Finished => {
+                // The manager is destroyed by the compositor.
+                state.finished(conn, qh);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    wayland_client::event_created_child!(D, ExtWorkspaceManagerV1, [
+        ext_workspace_manager_v1::EVT_WORKSPACE_GROUP_OPCODE => (ExtWorkspaceGroupHandleV1, Default::default()),
+        ext_workspace_manager_v1::EVT_WORKSPACE_OPCODE => (ExtWorkspaceHandleV1, Default::default())
+    ]);
+}
+
+impl<D> Dispatch2<ExtWorkspaceGroupHandleV1, D> for WorkspaceGroupData {
+    fn event(
+        &self,
+        _: &mut D,
+        group: &ExtWorkspaceGroupHandleV1,
+        event: ext_workspace_group_handle_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        let mut inner = self.0.lock().unwrap();
+        match event {
+            ext_workspace_group_handle_v1::Event::Capabilities { capabilities } => {
+                inner.pending().capabilities = match capabilities {
+                    WEnum::Value(capabilities) => capabilities,
+                    WEnum::Unknown(raw) => GroupCapabilities::from_bits_truncate(raw),
+                };
+            }
+            ext_workspace_group_handle_v1::Event::OutputEnter { output } => {
+                let outputs = &mut inner.pending().outputs;
+                if !outputs.contains(&output) {
+                    outputs.push(output);
+                }
+            }
+            ext_workspace_group_handle_v1::Event::OutputLeave { output } => {
+                inner.pending().outputs.retain(|o| *o != output);
+            }
+            ext_workspace_group_handle_v1::Event::WorkspaceEnter { workspace } => {
+                let workspaces = &mut inner.pending().workspaces;
+                if !workspaces.contains(&workspace) {
+                    workspaces.push(workspace.clone());
+                }
+                if let Some(data) = workspace.data::<WorkspaceData>() {
+                    data.0.lock().unwrap().pending().group = Some(group.clone());
+                }
+            }
+            ext_workspace_group_handle_v1::Event::WorkspaceLeave { workspace } => {
+                inner.pending().workspaces.retain(|w| *w != workspace);
+                if let Some(data) = workspace.data::<WorkspaceData>() {
+                    let mut workspace_inner = data.0.lock().unwrap();
+                    if workspace_inner.pending_info.group.as_ref() == Some(group) {
+                        workspace_inner.pending().group = None;
+                    }
+                }
+            }
+            ext_workspace_group_handle_v1::Event::Removed => {
+                inner.removed = true;
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch2<ExtWorkspaceHandleV1, D> for WorkspaceData {
+    fn event(
+        &self,
+        _: &mut D,
+        _: &ExtWorkspaceHandleV1,
+        event: ext_workspace_handle_v1::Event,
+        _: &Connection,
+        _: &QueueHandle<D>,
+    ) {
+        let mut inner = self.0.lock().unwrap();
+        match event {
+            ext_workspace_handle_v1::Event::Id { id } => {
+                inner.pending().id = Some(id);
+            }
+            ext_workspace_handle_v1::Event::Name { name } => {
+                inner.pending().name = name;
+            }
+            ext_workspace_handle_v1::Event::Coordinates { coordinates } => {
+                inner.pending().coordinates = coordinates
+                    .chunks_exact(4)
+                    .flat_map(TryInto::<[u8; 4]>::try_into)
+                    .map(u32::from_ne_bytes)
+                    .collect();
+            }
+            ext_workspace_handle_v1::Event::State { state } => {
+                inner.pending().state = match state {
+                    WEnum::Value(state) => state,
+                    WEnum::Unknown(raw) => WorkspaceStates::from_bits_truncate(raw),
+                };
+            }
+            ext_workspace_handle_v1::Event::Capabilities { capabilities } => {
+                inner.pending().capabilities = match capabilities {
+                    WEnum::Value(capabilities) => capabilities,
+                    WEnum::Unknown(raw) => WorkspaceCapabilities::from_bits_truncate(raw),
+                };
+            }
+            ext_workspace_handle_v1::Event::Removed => {
+                inner.removed = true;
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Change, Pending};
+
+    #[test]
+    fn pending_changes() {
+        let mut pending = Pending::<u32>::default();
+        assert_eq!(pending.apply(), Some(Change::New));
+        assert_eq!(pending.apply(), None);
+
+        *pending.pending() = 1;
+        assert_eq!(pending.apply(), Some(Change::Updated));
+        assert_eq!(pending.current_info, Some(1));
+
+        pending.removed = true;
+        assert_eq!(pending.apply(), Some(Change::Removed));
+
+        let mut discarded = Pending::<u32> { removed: true, ..Default::default() };
+        assert_eq!(discarded.apply(), Some(Change::Discarded));
+    }
+}